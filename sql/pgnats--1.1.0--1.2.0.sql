@@ -0,0 +1,35 @@
+ALTER TABLE pgnats.subscriptions ADD COLUMN IF NOT EXISTS with_subject BOOLEAN NOT NULL DEFAULT false;
+
+CREATE TABLE IF NOT EXISTS pgnats.kv_watches (
+    bucket TEXT NOT NULL,
+    key_pattern TEXT NOT NULL,
+    callback TEXT NOT NULL,
+    UNIQUE(bucket, key_pattern, callback)
+);
+
+CREATE OR REPLACE FUNCTION pgnats.cleanup_subscriptions_on_drop()
+RETURNS event_trigger AS $$
+DECLARE
+    obj record;
+    clean_name TEXT;
+BEGIN
+    FOR obj IN
+        SELECT * FROM pg_event_trigger_dropped_objects()
+    LOOP
+        IF obj.object_type = 'function' THEN
+            clean_name := split_part(obj.object_identity, '(', 1);
+            DELETE FROM pgnats.subscriptions
+            WHERE callback = clean_name;
+            DELETE FROM pgnats.kv_watches
+            WHERE callback = clean_name;
+        END IF;
+    END LOOP;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TABLE IF NOT EXISTS pgnats.protobuf_descriptors (
+    name TEXT PRIMARY KEY,
+    descriptor_set BYTEA NOT NULL
+);
+
+SELECT pg_catalog.pg_extension_config_dump('pgnats.protobuf_descriptors', '');
@@ -86,3 +86,277 @@ pub fn map_object_info(
         ))
     }))
 }
+
+#[allow(clippy::type_complexity)]
+#[cfg(feature = "kv")]
+pub fn map_kv_entries(
+    v: impl IntoIterator<Item = async_nats::jetstream::kv::Entry> + 'static,
+) -> pgrx::iter::TableIterator<
+    'static,
+    (
+        name!(revision, i64),
+        name!(operation, String),
+        name!(created, Option<pgrx::datum::TimestampWithTimeZone>),
+        name!(value, Option<Vec<u8>>),
+    ),
+> {
+    use async_nats::jetstream::kv::Operation;
+
+    pgrx::iter::TableIterator::new(v.into_iter().map(|v| {
+        let operation = match v.operation {
+            Operation::Put => "put",
+            Operation::Delete => "delete",
+            Operation::Purge => "purge",
+        };
+
+        (
+            v.revision.try_into().unwrap_or(i64::MAX),
+            operation.to_string(),
+            to_timestamptz(v.created),
+            (v.operation == Operation::Put).then(|| v.value.to_vec()),
+        )
+    }))
+}
+
+#[allow(clippy::type_complexity)]
+#[cfg(feature = "kv")]
+pub fn map_kv_status(
+    v: async_nats::jetstream::kv::Status,
+) -> pgrx::iter::TableIterator<
+    'static,
+    (
+        name!(bucket, String),
+        name!(values, i64),
+        name!(bytes, i64),
+        name!(history, i64),
+        name!(ttl, Option<f64>),
+        name!(max_bytes, Option<i64>),
+        name!(storage, String),
+        name!(replicas, i32),
+        name!(compression, bool),
+    ),
+> {
+    use async_nats::jetstream::stream::{Compression, StorageType};
+
+    let config = v.info.config;
+    let storage = match config.storage {
+        StorageType::File => "file",
+        StorageType::Memory => "memory",
+    };
+
+    pgrx::iter::TableIterator::once((
+        v.bucket,
+        v.info.state.messages.try_into().unwrap_or(i64::MAX),
+        v.info.state.bytes.try_into().unwrap_or(i64::MAX),
+        config.max_messages_per_subject,
+        (!config.max_age.is_zero()).then(|| config.max_age.as_secs_f64()),
+        (config.max_bytes >= 0).then_some(config.max_bytes),
+        storage.to_string(),
+        config.num_replicas.try_into().unwrap_or(i32::MAX),
+        matches!(config.compression, Some(Compression::S2)),
+    ))
+}
+
+#[allow(clippy::type_complexity)]
+#[cfg(feature = "object_store")]
+pub fn map_object_store_status(
+    bucket: String,
+    v: async_nats::jetstream::stream::Info,
+) -> pgrx::iter::TableIterator<
+    'static,
+    (
+        name!(bucket, String),
+        name!(description, Option<String>),
+        name!(size, i64),
+        name!(sealed, bool),
+        name!(stream, String),
+        name!(ttl, Option<f64>),
+        name!(max_bytes, Option<i64>),
+        name!(storage, String),
+        name!(replicas, i32),
+        name!(compression, bool),
+    ),
+> {
+    use async_nats::jetstream::stream::{Compression, StorageType};
+
+    let config = v.config;
+    let storage = match config.storage {
+        StorageType::File => "file",
+        StorageType::Memory => "memory",
+    };
+
+    pgrx::iter::TableIterator::once((
+        bucket,
+        config.description,
+        v.state.bytes.try_into().unwrap_or(i64::MAX),
+        config.sealed,
+        config.name,
+        (!config.max_age.is_zero()).then(|| config.max_age.as_secs_f64()),
+        (config.max_bytes >= 0).then_some(config.max_bytes),
+        storage.to_string(),
+        config.num_replicas.try_into().unwrap_or(i32::MAX),
+        matches!(config.compression, Some(Compression::S2)),
+    ))
+}
+
+#[allow(clippy::type_complexity)]
+pub fn map_stream_info(
+    v: impl IntoIterator<Item = async_nats::jetstream::stream::Info> + 'static,
+) -> pgrx::iter::TableIterator<
+    'static,
+    (
+        name!(name, String),
+        name!(subjects, Vec<String>),
+        name!(messages, i64),
+        name!(bytes, i64),
+        name!(first_seq, i64),
+        name!(last_seq, i64),
+        name!(consumers, i64),
+        name!(created, Option<pgrx::datum::TimestampWithTimeZone>),
+        name!(config, Option<pgrx::JsonB>),
+    ),
+> {
+    pgrx::iter::TableIterator::new(v.into_iter().map(|v| {
+        (
+            v.config.name.clone(),
+            v.config.subjects.clone(),
+            v.state.messages.try_into().unwrap_or(i64::MAX),
+            v.state.bytes.try_into().unwrap_or(i64::MAX),
+            v.state.first_sequence.try_into().unwrap_or(i64::MAX),
+            v.state.last_sequence.try_into().unwrap_or(i64::MAX),
+            v.state.consumer_count.try_into().unwrap_or(i64::MAX),
+            to_timestamptz(v.created),
+            serde_json::to_value(&v.config).ok().map(pgrx::JsonB),
+        )
+    }))
+}
+
+#[allow(clippy::type_complexity)]
+pub fn map_consumer_info(
+    v: impl IntoIterator<Item = async_nats::jetstream::consumer::Info> + 'static,
+) -> pgrx::iter::TableIterator<
+    'static,
+    (
+        name!(stream, String),
+        name!(name, String),
+        name!(pending, i64),
+        name!(ack_pending, i64),
+        name!(redelivered, i64),
+        name!(waiting, i64),
+        name!(delivered_seq, i64),
+        name!(ack_floor_seq, i64),
+        name!(created, Option<pgrx::datum::TimestampWithTimeZone>),
+        name!(config, Option<pgrx::JsonB>),
+    ),
+> {
+    pgrx::iter::TableIterator::new(v.into_iter().map(|v| {
+        (
+            v.stream_name,
+            v.name,
+            v.num_pending.try_into().unwrap_or(i64::MAX),
+            v.num_ack_pending.try_into().unwrap_or(i64::MAX),
+            v.num_redelivered.try_into().unwrap_or(i64::MAX),
+            v.num_waiting.try_into().unwrap_or(i64::MAX),
+            v.delivered.stream_sequence.try_into().unwrap_or(i64::MAX),
+            v.ack_floor.stream_sequence.try_into().unwrap_or(i64::MAX),
+            to_timestamptz(v.created),
+            serde_json::to_value(&v.config).ok().map(pgrx::JsonB),
+        )
+    }))
+}
+
+#[allow(clippy::type_complexity)]
+pub fn map_reply(
+    v: crate::nats_client::Reply,
+) -> pgrx::iter::TableIterator<
+    'static,
+    (
+        name!(payload, Vec<u8>),
+        name!(headers, Option<pgrx::JsonB>),
+        name!(subject, String),
+        name!(status, i32),
+    ),
+> {
+    pgrx::iter::TableIterator::once((
+        v.payload,
+        v.headers
+            .as_ref()
+            .map(|h| pgrx::JsonB(crate::utils::headers_to_json(h))),
+        v.subject,
+        v.status.into(),
+    ))
+}
+
+#[allow(clippy::type_complexity)]
+pub fn map_replies(
+    v: Vec<crate::nats_client::Reply>,
+) -> pgrx::iter::TableIterator<
+    'static,
+    (name!(payload, Vec<u8>), name!(headers, Option<pgrx::JsonB>)),
+> {
+    pgrx::iter::TableIterator::new(v.into_iter().map(|v| {
+        (
+            v.payload,
+            v.headers
+                .as_ref()
+                .map(|h| pgrx::JsonB(crate::utils::headers_to_json(h))),
+        )
+    }))
+}
+
+#[allow(clippy::type_complexity)]
+pub fn map_stored_message(
+    v: crate::nats_client::StoredMessage,
+) -> (
+    i64,
+    String,
+    Option<pgrx::JsonB>,
+    Vec<u8>,
+    Option<pgrx::datum::TimestampWithTimeZone>,
+) {
+    (
+        v.seq.try_into().unwrap_or(i64::MAX),
+        v.subject,
+        v.headers
+            .as_ref()
+            .map(|h| pgrx::JsonB(crate::utils::headers_to_json(h))),
+        v.payload,
+        to_timestamptz(v.time),
+    )
+}
+
+#[allow(clippy::type_complexity)]
+pub fn map_fetched_messages(
+    v: Vec<crate::nats_client::FetchedMessage>,
+) -> pgrx::iter::TableIterator<
+    'static,
+    (
+        name!(seq, i64),
+        name!(subject, String),
+        name!(headers, Option<pgrx::JsonB>),
+        name!(payload, Vec<u8>),
+        name!(time, Option<pgrx::datum::TimestampWithTimeZone>),
+        name!(delivered, i64),
+        name!(ack_token, String),
+    ),
+> {
+    pgrx::iter::TableIterator::new(v.into_iter().map(|v| {
+        let (seq, subject, headers, payload, time) = map_stored_message(v.message);
+        (
+            seq,
+            subject,
+            headers,
+            payload,
+            time,
+            v.delivered,
+            v.ack_token,
+        )
+    }))
+}
+
+fn to_timestamptz(time: time::OffsetDateTime) -> Option<pgrx::datum::TimestampWithTimeZone> {
+    pgrx::datum::TimestampWithTimeZone::try_from(crate::utils::unix_nanos_to_pg_timestamp(
+        time.unix_timestamp_nanos(),
+    ))
+    .ok()
+}
@@ -5,16 +5,32 @@ macro_rules! impl_nats_publish {
         pastey::paste! {
             #[pgrx::pg_extern]
             $(#[$attr])*
-            pub fn [<nats_publish_ $suffix>](subject: &str, payload: $ty, reply: ::pgrx::default!(Option<&str>, "NULL"), headers: ::pgrx::default!(Option<pgrx::JsonB>, "NULL")) -> anyhow::Result<()> {
+            pub fn [<nats_publish_ $suffix>](subject: &str, payload: $ty, reply: ::pgrx::default!(Option<&str>, "NULL"), headers: ::pgrx::default!(Option<pgrx::JsonB>, "NULL"), flush: ::pgrx::default!(bool, false)) -> anyhow::Result<()> {
                 CTX.with_borrow_mut(|ctx| {
                     ctx.rt.block_on(async {
                         let res = ctx.nats_connection.publish(subject, payload, reply, headers.map(|h| h.0)).await;
                         tokio::task::yield_now().await;
-                        res
+                        res?;
+
+                        if flush {
+                            ctx.nats_connection.flush_confirmed($crate::nats_client::PUBLISH_FLUSH_TIMEOUT).await?;
+                        }
+
+                        anyhow::Ok(())
                     })
                 })
             }
 
+            #[pgrx::pg_extern]
+            #[doc = concat!("Asynchronous version of [`nats_publish_", stringify!($suffix), "`], returning without waiting for the publish.")]
+            #[doc = ""]
+            #[doc = "Failures are returned by `nats_pending_errors` or raised when the transaction commits."]
+            pub fn [<nats_publish_ $suffix _async>](subject: &str, payload: $ty, headers: ::pgrx::default!(Option<pgrx::JsonB>, "NULL")) -> anyhow::Result<()> {
+                use $crate::utils::ToBytes;
+
+                $crate::api::publish_async(subject, payload.to_bytes()?, headers.map(|h| h.0))
+            }
+
             #[pgrx::pg_extern]
             #[doc = concat!("JetStream version of [`nats_publish_", stringify!($suffix), "`].")]
             pub fn [<nats_publish_ $suffix _stream>](subject: &str, payload: $ty, headers: ::pgrx::default!(Option<pgrx::JsonB>, "NULL")) -> anyhow::Result<()> {
@@ -37,11 +53,40 @@ macro_rules! impl_nats_request {
         pastey::paste! {
             #[pgrx::pg_extern]
             $(#[$attr])*
-                pub fn [<nats_request_ $suffix>](subject: &str, payload: $ty, timeout: Option<i32>) -> anyhow::Result<Vec<u8>> {
+                pub fn [<nats_request_ $suffix>](subject: &str, payload: $ty, timeout: Option<i32>) -> anyhow::Result<$ty> {
                 CTX.with_borrow_mut(|ctx| {
                     ctx.rt.block_on(ctx.nats_connection.request(subject, payload, timeout.and_then(|x| x.try_into().ok())))
                 })
             }
+
+            #[allow(clippy::type_complexity)]
+            #[pgrx::pg_extern]
+            #[doc = concat!("Version of [`nats_request_", stringify!($suffix), "`] sending headers and returning the whole reply.")]
+            #[doc = ""]
+            #[doc = "Returns a single row with the reply payload, headers, subject and status. The status is"]
+            #[doc = "`200` for an ordinary reply and `503` when the subject has no responders, while a"]
+            #[doc = "request left unanswered fails with a timeout error."]
+            pub fn [<nats_request_ $suffix _with_headers>](
+                subject: &str,
+                payload: $ty,
+                headers: ::pgrx::default!(Option<pgrx::JsonB>, "NULL"),
+                timeout: ::pgrx::default!(Option<i32>, "NULL"),
+            ) -> anyhow::Result<
+                pgrx::iter::TableIterator<
+                    'static,
+                    (
+                        pgrx::name!(payload, Vec<u8>),
+                        pgrx::name!(headers, Option<pgrx::JsonB>),
+                        pgrx::name!(subject, String),
+                        pgrx::name!(status, i32),
+                    ),
+                >,
+            > {
+                CTX.with_borrow_mut(|ctx| {
+                    ctx.rt.block_on(ctx.nats_connection.request_with_headers(subject, payload, headers.map(|h| h.0), timeout.and_then(|x| x.try_into().ok())))
+                })
+                .map(super::conv::map_reply)
+            }
         }
     };
 }
@@ -60,6 +105,37 @@ macro_rules! impl_nats_put {
                     .map(|v| v.try_into().unwrap_or(i64::MAX))
                 })
             }
+
+            #[pgrx::pg_extern]
+            #[doc = concat!("Version of [`nats_put_", stringify!($suffix), "`] storing the value with a time to live.")]
+            pub fn [<nats_put_ $suffix _with_ttl>](bucket: String, key: &str, data: $ty, ttl: pgrx::datum::Interval) -> anyhow::Result<i64> {
+                let ttl = $crate::utils::interval_to_duration(ttl)?;
+
+                CTX.with_borrow_mut(|ctx| {
+                    ctx.rt.block_on(ctx.nats_connection.put_value_with_ttl(bucket, key, data, ttl))
+                    .map(|v| v.try_into().unwrap_or(i64::MAX))
+                })
+            }
+
+            #[pgrx::pg_extern]
+            #[doc = concat!("Version of [`nats_put_", stringify!($suffix), "`] that fails with SQLSTATE `40001` if the key already exists.")]
+            pub fn [<nats_kv_create_ $suffix>](bucket: String, key: &str, data: $ty) -> anyhow::Result<i64> {
+                $crate::api::raise_conflict(CTX.with_borrow_mut(|ctx| {
+                    ctx.rt.block_on(ctx.nats_connection.create_value(bucket, key, data))
+                    .map(|v| v.try_into().unwrap_or(i64::MAX))
+                }))
+            }
+
+            #[pgrx::pg_extern]
+            #[doc = concat!("Version of [`nats_put_", stringify!($suffix), "`] that fails with SQLSTATE `40001` if the latest revision of the key is not `revision`.")]
+            pub fn [<nats_kv_update_ $suffix>](bucket: String, key: &str, data: $ty, revision: i64) -> anyhow::Result<i64> {
+                let revision = u64::try_from(revision)?;
+
+                $crate::api::raise_conflict(CTX.with_borrow_mut(|ctx| {
+                    ctx.rt.block_on(ctx.nats_connection.update_value(bucket, key, data, revision))
+                    .map(|v| v.try_into().unwrap_or(i64::MAX))
+                }))
+            }
         }
     };
 }
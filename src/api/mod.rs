@@ -9,6 +9,9 @@ use pgrx::{name, pg_extern};
 
 use crate::{config::fetch_config, constants::FDW_EXTENSION_NAME, ctx::CTX};
 
+#[cfg(feature = "kv")]
+use crate::nats_client::KvConflict;
+
 shadow_rs::shadow!(build);
 
 /// Reloads NATS connection if configuration has changed
@@ -81,3 +84,152 @@ pub fn pgnats_version() -> pgrx::iter::TableIterator<
         build::LAST_TAG.to_string(),
     )])
 }
+
+/// Raises a rejected conditional KV write with SQLSTATE `40001` (`serialization_failure`),
+/// so that callers can tell conflicts apart from other errors and retry them.
+#[cfg(feature = "kv")]
+pub(crate) fn raise_conflict<T>(result: anyhow::Result<T>) -> anyhow::Result<T> {
+    if let Some(conflict) = result
+        .as_ref()
+        .err()
+        .and_then(|err| err.downcast_ref::<KvConflict>())
+    {
+        pgrx::ereport!(
+            ERROR,
+            pgrx::PgSqlErrorCode::ERRCODE_T_R_SERIALIZATION_FAILURE,
+            format!("[PGNATS]: {conflict}")
+        );
+    }
+
+    result
+}
+
+/// Raises a file failing digest verification with SQLSTATE `XX001` (`data_corrupted`),
+/// so that corrupted files can be told apart from other errors.
+#[cfg(feature = "object_store")]
+pub(crate) fn raise_digest_mismatch<T>(result: anyhow::Result<T>) -> anyhow::Result<T> {
+    if let Some(mismatch) = result
+        .as_ref()
+        .err()
+        .and_then(|err| err.downcast_ref::<crate::nats_client::DigestMismatch>())
+    {
+        pgrx::ereport!(
+            ERROR,
+            pgrx::PgSqlErrorCode::ERRCODE_DATA_CORRUPTED,
+            format!("[PGNATS]: {mismatch}")
+        );
+    }
+
+    result
+}
+
+/// Queues a publish on the runtime without waiting for it. Failures are collected and
+/// reported by `nats_pending_errors`, or raised when the transaction commits.
+pub(crate) fn publish_async(
+    subject: &str,
+    payload: Vec<u8>,
+    headers: Option<serde_json::Value>,
+) -> anyhow::Result<()> {
+    crate::subject::validate_subject(subject, false)?;
+
+    CTX.with_borrow_mut(|ctx| {
+        let client = ctx.rt.block_on(ctx.nats_connection.get_client())?;
+        let errors = ctx.async_publishes.errors.clone();
+        let subject = subject.to_string();
+        let headers = headers.map(crate::utils::extract_headers).transpose()?;
+
+        let task = ctx.rt.spawn(async move {
+            let result = match headers {
+                Some(headers) => {
+                    client
+                        .publish_with_headers(subject.clone(), headers, payload.into())
+                        .await
+                }
+                None => client.publish(subject.clone(), payload.into()).await,
+            };
+
+            if let Err(err) = result {
+                errors
+                    .lock()
+                    .unwrap_or_else(std::sync::PoisonError::into_inner)
+                    .push((Some(subject), err.to_string()));
+            }
+        });
+
+        let publishes = &mut ctx.async_publishes;
+        publishes.tasks.retain(|task| !task.is_finished());
+        publishes.tasks.push(task);
+
+        if !publishes.registered {
+            publishes.registered = true;
+
+            let _ = pgrx::register_xact_callback(
+                pgrx::PgXactCallbackEvent::PreCommit,
+                raise_async_publish_errors,
+            );
+            let _ = pgrx::register_xact_callback(
+                pgrx::PgXactCallbackEvent::Abort,
+                discard_async_publishes,
+            );
+        }
+
+        Ok(())
+    })
+}
+
+/// Waits for the queued publishes, flushes them to the server and returns the failures
+/// that have not been reported yet.
+pub(crate) fn take_async_publish_errors() -> Vec<(Option<String>, String)> {
+    CTX.with_borrow_mut(|ctx| {
+        let tasks = std::mem::take(&mut ctx.async_publishes.tasks);
+        ctx.rt.block_on(async {
+            for task in tasks {
+                let _ = task.await;
+            }
+        });
+
+        let mut errors = std::mem::take(
+            &mut *ctx
+                .async_publishes
+                .errors
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner),
+        );
+
+        if let Err(err) = ctx.rt.block_on(ctx.nats_connection.flush()) {
+            errors.push((None, err.to_string()));
+        }
+
+        errors
+    })
+}
+
+/// Cancels the publishes queued by a transaction that rolls back and drops their failures,
+/// so that they are not reported to the next transaction.
+pub(crate) fn discard_async_publishes() {
+    CTX.with_borrow_mut(|ctx| {
+        // Tasks still running keep the old error list, so a late failure cannot leak either.
+        for task in std::mem::take(&mut ctx.async_publishes).tasks {
+            task.abort();
+        }
+    });
+}
+
+/// Fails the commit if any publish queued by the transaction failed.
+fn raise_async_publish_errors() {
+    CTX.with_borrow_mut(|ctx| ctx.async_publishes.registered = false);
+
+    let errors = take_async_publish_errors();
+
+    if !errors.is_empty() {
+        let errors: Vec<_> = errors
+            .into_iter()
+            .map(|(subject, reason)| match subject {
+                Some(subject) => format!("Failed to publish to '{subject}': {reason}"),
+                None => format!("Failed to flush published messages: {reason}"),
+            })
+            .collect();
+
+        crate::error!("{}", errors.join("; "));
+    }
+}
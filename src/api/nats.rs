@@ -1,13 +1,28 @@
+use async_nats::jetstream::AckKind;
 #[cfg(feature = "sub")]
 use pgrx::pg_sys;
 use pgrx::{name, pg_extern};
 
 use super::conv::map_server_info;
-use crate::{ctx::CTX, impl_nats_publish, impl_nats_request, utils::resolve_bytea_name};
+use crate::{ctx::CTX, impl_nats_publish, impl_nats_request, utils::resolve_subscriber_name};
 
 #[cfg(feature = "kv")]
 use crate::{impl_nats_get, impl_nats_put};
 
+/// How long `nats_stream_read` waits for the next message before it stops reading.
+const STREAM_READ_IDLE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// How long the end of a transaction waits for the acks of messages fetched with `auto_ack`.
+const ACK_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Size of the chunks streamed between large objects and the object store.
+#[cfg(feature = "object_store")]
+const FILE_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Number of chunks queued for an upload from a large object.
+#[cfg(feature = "object_store")]
+const FILE_CHUNK_QUEUE: usize = 4;
+
 impl_nats_publish! {
     /// Publishes a raw binary message to the specified NATS subject.
     ///
@@ -128,7 +143,7 @@ impl_nats_request! {
     /// * `timeout` - Optional maximum duration to wait for response in ms
     ///
     /// # Returns
-    /// * `Ok(String)` - Text response on success
+    /// * `Ok(String)` - Text response on success; fails if the response is not valid UTF-8
     ///
     /// # SQL Usage
     /// ```sql
@@ -147,7 +162,7 @@ impl_nats_request! {
     /// * `timeout` - Optional maximum duration to wait for response in ms
     ///
     /// # Returns
-    /// * `Ok(pgrx::Json)` - JSON response on success
+    /// * `Ok(pgrx::Json)` - JSON response on success; fails if the response is not valid JSON
     ///
     /// # SQL Usage
     /// ```sql
@@ -166,7 +181,8 @@ impl_nats_request! {
     /// * `timeout` - Optional maximum duration to wait for response in ms
     ///
     /// # Returns
-    /// * `Ok(pgrx::JsonB)` - Binary JSON response on success
+    /// * `Ok(pgrx::JsonB)` - Binary JSON response on success; fails if the response is not
+    ///   valid JSON
     ///
     /// # SQL Usage
     /// ```sql
@@ -176,6 +192,458 @@ impl_nats_request! {
     jsonb, pgrx::JsonB
 }
 
+/// Waits until the NATS server has received every message published so far by this session.
+///
+/// A successful publish only means that the message was buffered by the client. After
+/// `nats_flush`, the messages published before it are known to have reached the server.
+///
+/// # Arguments
+/// * `timeout` *(optional)* - Maximum duration to wait for the server in ms, 5000 by default
+///
+/// # Returns
+/// * `Ok(())` - If the server confirmed the messages within the timeout
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_publish_text('audit.events', 'logged in');
+/// SELECT nats_flush(1000);
+/// ```
+#[pg_extern]
+pub fn nats_flush(timeout: pgrx::default!(i32, 5000)) -> anyhow::Result<()> {
+    let timeout = u64::try_from(timeout)
+        .ok()
+        .filter(|timeout| *timeout > 0)
+        .map(std::time::Duration::from_millis)
+        .ok_or_else(|| anyhow::anyhow!("Timeout must be positive"))?;
+
+    CTX.with_borrow_mut(|ctx| {
+        ctx.rt
+            .block_on(ctx.nats_connection.flush_confirmed(timeout))
+    })
+}
+
+/// Waits for the messages published with `nats_publish_*_async` and returns the failures
+/// that have not been reported yet.
+///
+/// Reported failures are cleared, so they are not raised again when the transaction commits.
+///
+/// # Returns
+/// * One row per failed publish, with the subject and the reason. A failure to flush the
+///   queued messages to the server has a `NULL` subject.
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT * FROM nats_pending_errors();
+/// ```
+#[pg_extern]
+pub fn nats_pending_errors(
+) -> pgrx::iter::TableIterator<'static, (name!(subject, Option<String>), name!(reason, String))> {
+    pgrx::iter::TableIterator::new(super::take_async_publish_errors())
+}
+
+/// Sends a request to every instance of a service and collects their replies.
+///
+/// Replies are collected until `timeout` elapses or `max_replies` replies have arrived.
+/// With `sentinel`, an empty reply also ends the collection and is not returned.
+///
+/// # Arguments
+/// * `subject` - NATS subject to send the request to
+/// * `payload` - Binary request data
+/// * `timeout` - Maximum duration to wait for replies in ms
+/// * `max_replies` *(optional)* - Maximum number of replies, unlimited by default
+/// * `sentinel` *(optional)* - Stop at the first empty reply
+///
+/// # Returns
+/// * `Ok(_)` - One row per reply with its payload and headers, in the order they arrived;
+///   no rows if the subject has no responders
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT convert_from(payload, 'UTF8')
+/// FROM nats_request_many('service.status', ''::bytea, 500);
+/// ```
+#[allow(clippy::type_complexity)]
+#[pg_extern]
+pub fn nats_request_many(
+    subject: &str,
+    payload: Vec<u8>,
+    timeout: i32,
+    max_replies: pgrx::default!(Option<i32>, "NULL"),
+    sentinel: pgrx::default!(bool, false),
+) -> anyhow::Result<
+    pgrx::iter::TableIterator<
+        'static,
+        (name!(payload, Vec<u8>), name!(headers, Option<pgrx::JsonB>)),
+    >,
+> {
+    let timeout = u64::try_from(timeout)
+        .ok()
+        .filter(|timeout| *timeout > 0)
+        .map(std::time::Duration::from_millis)
+        .ok_or_else(|| anyhow::anyhow!("Timeout must be positive"))?;
+    let max_replies = max_replies.map(usize::try_from).transpose()?;
+
+    CTX.with_borrow_mut(|ctx| {
+        ctx.rt.block_on(ctx.nats_connection.request_many(
+            subject,
+            payload,
+            timeout,
+            max_replies,
+            sentinel,
+        ))
+    })
+    .map(super::conv::map_replies)
+}
+
+/// Checks whether a string is a valid NATS subject.
+///
+/// A valid subject is made of non-empty tokens separated by dots and contains no whitespace.
+/// Wildcards (`*` and a final `>`) are only accepted with `allow_wildcards`.
+///
+/// # Arguments
+/// * `subject` - Subject to check
+/// * `allow_wildcards` *(optional)* - Accept subject patterns with wildcards
+///
+/// # Returns
+/// * `bool` - Whether the subject is valid
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_subject_is_valid('orders.eu.created');     -- true
+/// SELECT nats_subject_is_valid('orders..created');       -- false
+/// SELECT nats_subject_is_valid('orders.>', true);        -- true
+/// SELECT 'orders.eu.created'::nats_subject;
+/// ```
+#[pg_extern(immutable, parallel_safe)]
+pub fn nats_subject_is_valid(subject: &str, allow_wildcards: pgrx::default!(bool, false)) -> bool {
+    crate::subject::validate_subject(subject, allow_wildcards).is_ok()
+}
+
+pgrx::extension_sql!(
+    r#"
+    CREATE DOMAIN nats_subject AS text CHECK (nats_subject_is_valid(VALUE, false));
+    "#,
+    name = "create_nats_subject_domain",
+    requires = [nats_subject_is_valid]
+);
+
+/// Checks whether a subject matches a subject pattern.
+///
+/// A `*` token of the pattern matches exactly one token and a final `>` token matches
+/// one or more tokens, as in NATS subscriptions.
+///
+/// # Arguments
+/// * `subject` - Subject to test, without wildcards
+/// * `pattern` - Subject pattern, possibly with wildcards
+///
+/// # Returns
+/// * `Ok(bool)` - Whether the subject matches the pattern; fails if either is invalid
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_subject_matches('orders.eu.created', 'orders.*.created'); -- true
+/// SELECT nats_subject_matches('orders.eu.created', 'orders.>');         -- true
+/// SELECT nats_subject_matches('orders', 'orders.>');                    -- false
+/// ```
+#[pg_extern(immutable, parallel_safe)]
+pub fn nats_subject_matches(subject: &str, pattern: &str) -> anyhow::Result<bool> {
+    crate::subject::validate_subject(subject, false)?;
+    crate::subject::validate_subject(pattern, true)?;
+
+    Ok(crate::subject::subject_matches(subject, pattern))
+}
+
+/// Builds a subject from a template by replacing `{name}` placeholders with the values of
+/// a JSON object.
+///
+/// Values may be strings, numbers or booleans and must each form a single subject token,
+/// so a parameter can neither add tokens nor introduce wildcards. The resulting subject is
+/// validated before it is returned.
+///
+/// # Arguments
+/// * `template` - Subject template, e.g. `orders.{region}.{id}`
+/// * `params` - Values of the placeholders as a `jsonb` object
+///
+/// # Returns
+/// * `Ok(String)` - The formatted subject; fails if a parameter is missing or invalid
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_publish_text(
+///     nats_subject_format('orders.{region}.{id}', '{"region": "eu", "id": 42}'),
+///     'created'
+/// );
+/// ```
+#[pg_extern(immutable, parallel_safe)]
+pub fn nats_subject_format(template: &str, params: pgrx::JsonB) -> anyhow::Result<String> {
+    crate::subject::format_subject(template, &params.0)
+}
+
+/// Encodes a `jsonb` value as MessagePack.
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_msgpack_encode('{"id": 42}');
+/// ```
+#[pg_extern(immutable, parallel_safe)]
+pub fn nats_msgpack_encode(value: pgrx::JsonB) -> anyhow::Result<Vec<u8>> {
+    crate::codec::encode_msgpack(&value.0)
+}
+
+/// Decodes a MessagePack payload into `jsonb`, failing for values JSON cannot represent,
+/// such as binary strings or maps with non-string keys.
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_msgpack_decode(payload) ->> 'id' FROM nats_stream_read('ORDERS');
+/// ```
+#[pg_extern(immutable, parallel_safe)]
+pub fn nats_msgpack_decode(payload: &[u8]) -> anyhow::Result<pgrx::JsonB> {
+    crate::codec::decode_msgpack(payload).map(pgrx::JsonB)
+}
+
+/// Encodes a `jsonb` value as CBOR.
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_cbor_encode('{"id": 42}');
+/// ```
+#[pg_extern(immutable, parallel_safe)]
+pub fn nats_cbor_encode(value: pgrx::JsonB) -> anyhow::Result<Vec<u8>> {
+    crate::codec::encode_cbor(&value.0)
+}
+
+/// Decodes a CBOR payload into `jsonb`, failing for values JSON cannot represent, such as
+/// byte strings or maps with non-string keys.
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_cbor_decode(payload) ->> 'id' FROM nats_stream_read('ORDERS');
+/// ```
+#[pg_extern(immutable, parallel_safe)]
+pub fn nats_cbor_decode(payload: &[u8]) -> anyhow::Result<pgrx::JsonB> {
+    crate::codec::decode_cbor(payload).map(pgrx::JsonB)
+}
+
+pgrx::extension_sql!(
+    r#"
+    CREATE SCHEMA IF NOT EXISTS pgnats;
+
+    CREATE TABLE IF NOT EXISTS pgnats.protobuf_descriptors (
+        name TEXT PRIMARY KEY,
+        descriptor_set BYTEA NOT NULL
+    );
+
+    SELECT pg_catalog.pg_extension_config_dump('pgnats.protobuf_descriptors', '');
+    "#,
+    name = "create_protobuf_descriptors_table",
+);
+
+/// Registers a Protobuf descriptor set under `name`, replacing any set registered under the
+/// same name. The message types it defines can then be used by the `nats_protobuf_*` functions.
+///
+/// # Arguments
+/// * `name` - Name of the descriptor set
+/// * `descriptor_set` - Serialized `FileDescriptorSet`, as written by `protoc --descriptor_set_out`
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_protobuf_register('orders', pg_read_binary_file('/etc/protos/orders.pb'));
+/// ```
+#[pg_extern]
+pub fn nats_protobuf_register(name: &str, descriptor_set: &[u8]) -> anyhow::Result<()> {
+    crate::codec::validate_descriptor_set(descriptor_set)?;
+
+    pgrx::Spi::run_with_args(
+        "INSERT INTO pgnats.protobuf_descriptors VALUES ($1, $2) \
+         ON CONFLICT (name) DO UPDATE SET descriptor_set = EXCLUDED.descriptor_set",
+        &[name.into(), descriptor_set.into()],
+    )?;
+
+    Ok(())
+}
+
+/// Encodes a `jsonb` value, in the Protobuf JSON mapping, as a message of a registered type.
+///
+/// # Arguments
+/// * `message_type` - Fully qualified name of the message type, e.g. `orders.v1.Order`
+/// * `value` - Message as a `jsonb` object
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_protobuf_encode('orders.v1.Order', '{"id": 42, "status": "CREATED"}');
+/// ```
+#[pg_extern]
+pub fn nats_protobuf_encode(message_type: &str, value: pgrx::JsonB) -> anyhow::Result<Vec<u8>> {
+    crate::codec::encode_protobuf(load_protobuf_descriptor(message_type)?, value.0)
+}
+
+/// Decodes a message of a registered Protobuf type into `jsonb`, using the Protobuf JSON mapping.
+///
+/// # Arguments
+/// * `message_type` - Fully qualified name of the message type, e.g. `orders.v1.Order`
+/// * `payload` - Encoded message
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_protobuf_decode('orders.v1.Order', nats_request_binary('orders.get', '\x082a', 1000));
+/// ```
+#[pg_extern]
+pub fn nats_protobuf_decode(message_type: &str, payload: &[u8]) -> anyhow::Result<pgrx::JsonB> {
+    crate::codec::decode_protobuf(load_protobuf_descriptor(message_type)?, payload).map(pgrx::JsonB)
+}
+
+fn load_protobuf_descriptor(
+    message_type: &str,
+) -> anyhow::Result<prost_reflect::MessageDescriptor> {
+    let descriptor_sets = pgrx::Spi::connect(|client| {
+        client
+            .select(
+                "SELECT descriptor_set FROM pgnats.protobuf_descriptors ORDER BY name",
+                None,
+                &[],
+            )
+            .map(|tuples| {
+                tuples
+                    .filter_map(|tuple| {
+                        tuple
+                            .get_by_name::<Vec<u8>, _>("descriptor_set")
+                            .ok()
+                            .flatten()
+                    })
+                    .collect::<Vec<_>>()
+            })
+    })?;
+
+    crate::codec::find_message_descriptor(descriptor_sets, message_type)
+}
+
+/// Publishes a `jsonb` value encoded as MessagePack to the specified NATS subject.
+///
+/// # Arguments
+/// * `subject` - NATS subject to publish to
+/// * `payload` - Value to encode and publish
+/// * `reply_to` *(optional)* – Subject to which any reply should be sent
+/// * `headers` *(optional)* – Key-value headers to include in the message, as `jsonb`
+/// * `flush` *(optional)* – Wait until the server has received the message
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_publish_msgpack('orders.created', '{"id": 42}');
+/// ```
+#[pg_extern]
+pub fn nats_publish_msgpack(
+    subject: &str,
+    payload: pgrx::JsonB,
+    reply: pgrx::default!(Option<&str>, "NULL"),
+    headers: pgrx::default!(Option<pgrx::JsonB>, "NULL"),
+    flush: pgrx::default!(bool, false),
+) -> anyhow::Result<()> {
+    let payload = crate::codec::encode_msgpack(&payload.0)?;
+
+    nats_publish_binary(subject, payload, reply, headers, flush)
+}
+
+/// Publishes a `jsonb` value encoded as CBOR to the specified NATS subject.
+///
+/// # Arguments
+/// * `subject` - NATS subject to publish to
+/// * `payload` - Value to encode and publish
+/// * `reply_to` *(optional)* – Subject to which any reply should be sent
+/// * `headers` *(optional)* – Key-value headers to include in the message, as `jsonb`
+/// * `flush` *(optional)* – Wait until the server has received the message
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_publish_cbor('orders.created', '{"id": 42}');
+/// ```
+#[pg_extern]
+pub fn nats_publish_cbor(
+    subject: &str,
+    payload: pgrx::JsonB,
+    reply: pgrx::default!(Option<&str>, "NULL"),
+    headers: pgrx::default!(Option<pgrx::JsonB>, "NULL"),
+    flush: pgrx::default!(bool, false),
+) -> anyhow::Result<()> {
+    let payload = crate::codec::encode_cbor(&payload.0)?;
+
+    nats_publish_binary(subject, payload, reply, headers, flush)
+}
+
+/// Publishes a `jsonb` value encoded as a message of a registered Protobuf type.
+///
+/// # Arguments
+/// * `subject` - NATS subject to publish to
+/// * `message_type` - Fully qualified name of the message type, e.g. `orders.v1.Order`
+/// * `payload` - Message as a `jsonb` object, in the Protobuf JSON mapping
+/// * `reply_to` *(optional)* – Subject to which any reply should be sent
+/// * `headers` *(optional)* – Key-value headers to include in the message, as `jsonb`
+/// * `flush` *(optional)* – Wait until the server has received the message
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_publish_protobuf('orders.created', 'orders.v1.Order', '{"id": 42}');
+/// ```
+#[pg_extern]
+pub fn nats_publish_protobuf(
+    subject: &str,
+    message_type: &str,
+    payload: pgrx::JsonB,
+    reply: pgrx::default!(Option<&str>, "NULL"),
+    headers: pgrx::default!(Option<pgrx::JsonB>, "NULL"),
+    flush: pgrx::default!(bool, false),
+) -> anyhow::Result<()> {
+    let payload = nats_protobuf_encode(message_type, payload)?;
+
+    nats_publish_binary(subject, payload, reply, headers, flush)
+}
+
+/// Sends a MessagePack-encoded request and decodes the MessagePack reply.
+///
+/// # Arguments
+/// * `subject` - NATS subject to send the request to
+/// * `payload` - Request value, encoded as MessagePack
+/// * `timeout` - Optional maximum duration to wait for the reply in ms
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_request_msgpack('orders.get', '{"id": 42}', 1000);
+/// ```
+#[pg_extern]
+pub fn nats_request_msgpack(
+    subject: &str,
+    payload: pgrx::JsonB,
+    timeout: Option<i32>,
+) -> anyhow::Result<pgrx::JsonB> {
+    let payload = crate::codec::encode_msgpack(&payload.0)?;
+    let reply = nats_request_binary(subject, payload, timeout)?;
+
+    nats_msgpack_decode(&reply)
+}
+
+/// Sends a CBOR-encoded request and decodes the CBOR reply.
+///
+/// # Arguments
+/// * `subject` - NATS subject to send the request to
+/// * `payload` - Request value, encoded as CBOR
+/// * `timeout` - Optional maximum duration to wait for the reply in ms
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_request_cbor('orders.get', '{"id": 42}', 1000);
+/// ```
+#[pg_extern]
+pub fn nats_request_cbor(
+    subject: &str,
+    payload: pgrx::JsonB,
+    timeout: Option<i32>,
+) -> anyhow::Result<pgrx::JsonB> {
+    let payload = crate::codec::encode_cbor(&payload.0)?;
+    let reply = nats_request_binary(subject, payload, timeout)?;
+
+    nats_cbor_decode(&reply)
+}
+
 #[cfg(feature = "kv")]
 impl_nats_put! {
     /// Stores a raw binary value in the KV bucket under the specified key.
@@ -252,6 +720,36 @@ impl_nats_put! {
     jsonb, pgrx::JsonB
 }
 
+/// Stores a `jsonb` value encoded as MessagePack in the KV bucket under the specified key.
+///
+/// # Returns
+/// * `Ok(i64)` - The revision number of the stored value on success.
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_put_msgpack('settings', 'limits', '{"max_orders": 100}');
+/// ```
+#[cfg(feature = "kv")]
+#[pg_extern]
+pub fn nats_put_msgpack(bucket: String, key: &str, data: pgrx::JsonB) -> anyhow::Result<i64> {
+    nats_put_binary(bucket, key, crate::codec::encode_msgpack(&data.0)?)
+}
+
+/// Stores a `jsonb` value encoded as CBOR in the KV bucket under the specified key.
+///
+/// # Returns
+/// * `Ok(i64)` - The revision number of the stored value on success.
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_put_cbor('settings', 'limits', '{"max_orders": 100}');
+/// ```
+#[cfg(feature = "kv")]
+#[pg_extern]
+pub fn nats_put_cbor(bucket: String, key: &str, data: pgrx::JsonB) -> anyhow::Result<i64> {
+    nats_put_binary(bucket, key, crate::codec::encode_cbor(&data.0)?)
+}
+
 #[cfg(feature = "kv")]
 impl_nats_get! {
     /// Retrieves a raw binary value from the KV bucket by the specified key.
@@ -328,6 +826,42 @@ impl_nats_get! {
     jsonb, pgrx::JsonB
 }
 
+/// Retrieves a MessagePack value from the KV bucket and decodes it into `jsonb`.
+///
+/// # Returns
+/// * `Ok(Some(pgrx::JsonB))` - The decoded value if the key exists
+/// * `Ok(None)` - If the key does not exist
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_get_msgpack('settings', 'limits');
+/// ```
+#[cfg(feature = "kv")]
+#[pg_extern]
+pub fn nats_get_msgpack(bucket: String, key: &str) -> anyhow::Result<Option<pgrx::JsonB>> {
+    nats_get_binary(bucket, key)?
+        .map(|value| nats_msgpack_decode(&value))
+        .transpose()
+}
+
+/// Retrieves a CBOR value from the KV bucket and decodes it into `jsonb`.
+///
+/// # Returns
+/// * `Ok(Some(pgrx::JsonB))` - The decoded value if the key exists
+/// * `Ok(None)` - If the key does not exist
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_get_cbor('settings', 'limits');
+/// ```
+#[cfg(feature = "kv")]
+#[pg_extern]
+pub fn nats_get_cbor(bucket: String, key: &str) -> anyhow::Result<Option<pgrx::JsonB>> {
+    nats_get_binary(bucket, key)?
+        .map(|value| nats_cbor_decode(&value))
+        .transpose()
+}
+
 /// Deletes a value from the NATS KV bucket by the specified key.
 ///
 /// # Arguments
@@ -350,26 +884,249 @@ pub fn nats_delete_value(bucket: String, key: &str) -> anyhow::Result<()> {
     })
 }
 
-/// Retrieves information about the NATS server connection.
+/// Purges a key from the NATS KV bucket, removing all its revisions.
+///
+/// Unlike [`nats_delete_value`], which adds a delete marker and keeps the history,
+/// purging leaves only a single purge marker for the key.
+///
+/// # Arguments
+/// * `bucket` - The name of the KV bucket
+/// * `key` - The key to purge
 ///
 /// # Returns
-/// * `Ok(ServerInfo)` - Contains details about the NATS server if successful
+/// * `Ok(())` - If the purge was successful
 ///
 /// # SQL Usage
 /// ```sql
-/// SELECT nats_get_server_info();
+/// SELECT nats_kv_purge('sessions', 'session_42');
 /// ```
-#[allow(clippy::type_complexity)]
+#[cfg(feature = "kv")]
 #[pg_extern]
-pub fn nats_get_server_info() -> anyhow::Result<
-    pgrx::iter::TableIterator<
-        'static,
-        (
-            name!(server_id, String),
-            name!(server_name, String),
-            name!(host, String),
-            name!(port, i32),
-            name!(version, String),
+pub fn nats_kv_purge(bucket: String, key: &str) -> anyhow::Result<()> {
+    CTX.with_borrow_mut(|ctx| {
+        ctx.rt
+            .block_on(ctx.nats_connection.purge_value(bucket, key))
+    })
+}
+
+/// Creates a NATS KV bucket with explicit settings.
+///
+/// Buckets used by `nats_put_*` and the other KV functions are created on first use with
+/// the server defaults, unless the `kv_auto_create` server option is set to `false`.
+///
+/// # Arguments
+/// * `bucket` - The name of the KV bucket
+/// * `options` *(optional)* - Settings as `jsonb`: `history`, `ttl` (seconds),
+///   `limit_markers` (seconds, enables per-key TTLs), `max_bytes`, `storage` (`file` or
+///   `memory`), `replicas` and `compression`
+///
+/// # Returns
+/// * `Ok(())` - If the bucket was created or already exists with the same settings
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_kv_bucket_create('sessions', '{"history": 5, "ttl": 3600, "storage": "memory"}');
+/// ```
+#[cfg(feature = "kv")]
+#[pg_extern]
+pub fn nats_kv_bucket_create(
+    bucket: String,
+    options: pgrx::default!(Option<pgrx::JsonB>, "NULL"),
+) -> anyhow::Result<()> {
+    let options = match options {
+        Some(options) => serde_json::from_value(options.0)
+            .map_err(|err| anyhow::anyhow!("Invalid bucket options: {err}"))?,
+        None => crate::nats_client::KvBucketOptions::default(),
+    };
+
+    CTX.with_borrow_mut(|ctx| {
+        ctx.rt
+            .block_on(ctx.nats_connection.create_bucket(bucket, options))
+    })
+}
+
+/// Deletes a NATS KV bucket together with all its keys.
+///
+/// # Arguments
+/// * `bucket` - The name of the KV bucket
+///
+/// # Returns
+/// * `Ok(())` - If the bucket was deleted
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_kv_bucket_delete('sessions');
+/// ```
+#[cfg(feature = "kv")]
+#[pg_extern]
+pub fn nats_kv_bucket_delete(bucket: String) -> anyhow::Result<()> {
+    CTX.with_borrow_mut(|ctx| ctx.rt.block_on(ctx.nats_connection.delete_bucket(bucket)))
+}
+
+/// Returns the settings and usage of a NATS KV bucket.
+///
+/// # Arguments
+/// * `bucket` - The name of the KV bucket
+///
+/// # Returns
+/// * `Ok(_)` - A single row with the number of values, the size in bytes and the settings
+///   of the bucket; `ttl` and `max_bytes` are `NULL` when unlimited
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT * FROM nats_kv_bucket_status('sessions');
+/// ```
+#[allow(clippy::type_complexity)]
+#[cfg(feature = "kv")]
+#[pg_extern]
+pub fn nats_kv_bucket_status(
+    bucket: String,
+) -> anyhow::Result<
+    pgrx::iter::TableIterator<
+        'static,
+        (
+            name!(bucket, String),
+            name!(values, i64),
+            name!(bytes, i64),
+            name!(history, i64),
+            name!(ttl, Option<f64>),
+            name!(max_bytes, Option<i64>),
+            name!(storage, String),
+            name!(replicas, i32),
+            name!(compression, bool),
+        ),
+    >,
+> {
+    CTX.with_borrow_mut(|ctx| {
+        ctx.rt
+            .block_on(ctx.nats_connection.get_bucket_status(bucket))
+            .map(super::conv::map_kv_status)
+    })
+}
+
+/// Lists the keys of the NATS KV bucket, deleted and purged keys excluded.
+///
+/// # Arguments
+/// * `bucket` - The name of the KV bucket
+/// * `filter` *(optional)* - Key pattern with NATS wildcards (`*`, `>`), all keys by default
+///
+/// # Returns
+/// * `Ok(_)` - Iterator over the matching keys
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT * FROM nats_kv_keys('user_profiles');
+/// SELECT * FROM nats_kv_keys('config', 'app.*.timeout');
+/// ```
+#[cfg(feature = "kv")]
+#[pg_extern]
+pub fn nats_kv_keys(
+    bucket: String,
+    filter: pgrx::default!(Option<&str>, "NULL"),
+) -> anyhow::Result<pgrx::iter::SetOfIterator<'static, String>> {
+    CTX.with_borrow_mut(|ctx| {
+        ctx.rt
+            .block_on(ctx.nats_connection.get_keys(bucket, filter.unwrap_or(">")))
+            .map(pgrx::iter::SetOfIterator::new)
+    })
+}
+
+/// Retrieves every revision of the key still kept in the NATS KV bucket, oldest first.
+///
+/// # Arguments
+/// * `bucket` - The name of the KV bucket
+/// * `key` - The key to retrieve the history of
+///
+/// # Returns
+/// * `Ok(_)` - Iterator over the revisions with the operation (`put`, `delete` or `purge`),
+///   the time of the revision and its value
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT * FROM nats_kv_history('user_profiles', 'user123');
+/// ```
+#[allow(clippy::type_complexity)]
+#[cfg(feature = "kv")]
+#[pg_extern]
+pub fn nats_kv_history(
+    bucket: String,
+    key: &str,
+) -> anyhow::Result<
+    pgrx::iter::TableIterator<
+        'static,
+        (
+            name!(revision, i64),
+            name!(operation, String),
+            name!(created, Option<pgrx::datum::TimestampWithTimeZone>),
+            name!(value, Option<Vec<u8>>),
+        ),
+    >,
+> {
+    CTX.with_borrow_mut(|ctx| {
+        ctx.rt
+            .block_on(ctx.nats_connection.get_history(bucket, key))
+            .map(super::conv::map_kv_entries)
+    })
+}
+
+/// Retrieves the value of the key at the specified revision from the NATS KV bucket.
+///
+/// # Arguments
+/// * `bucket` - The name of the KV bucket
+/// * `key` - The key to retrieve the value of
+/// * `revision` - Revision of the key, as returned by `nats_put_*` or `nats_kv_history`
+///
+/// # Returns
+/// * `Ok(Some(Vec<u8>))` - If the revision exists and stores a value
+/// * `Ok(None)` - If the revision does not exist or deletes the key
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_kv_get_revision('user_profiles', 'user123', 42);
+/// ```
+#[cfg(feature = "kv")]
+#[pg_extern]
+pub fn nats_kv_get_revision(
+    bucket: String,
+    key: &str,
+    revision: i64,
+) -> anyhow::Result<Option<Vec<u8>>> {
+    let revision = u64::try_from(revision)?;
+
+    CTX.with_borrow_mut(|ctx| {
+        ctx.rt
+            .block_on(
+                ctx.nats_connection
+                    .get_entry_for_revision(bucket, key, revision),
+            )
+            .map(|entry| {
+                entry
+                    .filter(|e| e.operation == async_nats::jetstream::kv::Operation::Put)
+                    .map(|e| e.value.to_vec())
+            })
+    })
+}
+
+/// Retrieves information about the NATS server connection.
+///
+/// # Returns
+/// * `Ok(ServerInfo)` - Contains details about the NATS server if successful
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_get_server_info();
+/// ```
+#[allow(clippy::type_complexity)]
+#[pg_extern]
+pub fn nats_get_server_info() -> anyhow::Result<
+    pgrx::iter::TableIterator<
+        'static,
+        (
+            name!(server_id, String),
+            name!(server_name, String),
+            name!(host, String),
+            name!(port, i32),
+            name!(version, String),
             name!(auth_required, bool),
             name!(tls_requiered, bool),
             name!(max_payload, i64),
@@ -386,169 +1143,1359 @@ pub fn nats_get_server_info() -> anyhow::Result<
 > {
     CTX.with_borrow_mut(|ctx| {
         ctx.rt
-            .block_on(ctx.nats_connection.get_server_info())
-            .map(|v| map_server_info(std::iter::once(v)))
+            .block_on(ctx.nats_connection.get_server_info())
+            .map(|v| map_server_info(std::iter::once(v)))
+    })
+}
+
+/// Retrieves a file's content from the NATS object store by its name.
+///
+/// # Arguments
+/// * `store` - The name of the object store
+/// * `name` - The name of the file to retrieve
+///
+/// # Returns
+/// * `Ok(Vec<u8>)` - The file content as a byte array if successful
+///
+/// # Errors
+/// With `verify`, fails with SQLSTATE `XX001` (`data_corrupted`) if the content does not
+/// match the SHA-256 digest stored with the file.
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_get_file('documents', 'report.pdf');
+/// SELECT nats_get_file('documents', 'report.pdf', verify => true);
+/// ```
+#[pg_extern]
+#[cfg(feature = "object_store")]
+pub fn nats_get_file(
+    store: String,
+    name: &str,
+    verify: pgrx::default!(bool, false),
+) -> anyhow::Result<Vec<u8>> {
+    if verify {
+        return super::raise_digest_mismatch(CTX.with_borrow_mut(|ctx| {
+            ctx.rt
+                .block_on(ctx.nats_connection.get_verified_file(store, name))
+        }));
+    }
+
+    CTX.with_borrow_mut(|ctx| ctx.rt.block_on(ctx.nats_connection.get_file(store, name)))
+}
+
+/// Checks the content of a file in the NATS object store against the SHA-256 digest
+/// stored with it, reading the file in chunks.
+///
+/// # Arguments
+/// * `store` - The name of the object store
+/// * `name` - The name of the file
+///
+/// # Returns
+/// * `Ok(bool)` - `true` if the content matches the digest, `false` if the file is corrupted
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT name FROM nats_get_file_list('documents') WHERE NOT nats_verify_file('documents', name);
+/// ```
+#[pg_extern]
+#[cfg(feature = "object_store")]
+pub fn nats_verify_file(store: String, name: &str) -> anyhow::Result<bool> {
+    CTX.with_borrow_mut(|ctx| {
+        ctx.rt
+            .block_on(ctx.nats_connection.verify_file(store, name))
+    })
+}
+
+/// Uploads a file to the NATS object store.
+///
+/// # Arguments
+/// * `store` - The name of the object store
+/// * `name` - The name under which to store the file
+/// * `content` - The file content as a byte array
+///
+/// # Returns
+/// * `Ok(())` - If the upload was successful
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_put_file('documents', 'report.pdf', 'binary data'::bytea);
+/// ```
+#[pg_extern]
+#[cfg(feature = "object_store")]
+pub fn nats_put_file(store: String, name: &str, content: Vec<u8>) -> anyhow::Result<()> {
+    CTX.with_borrow_mut(|ctx| {
+        ctx.rt
+            .block_on(ctx.nats_connection.put_file(store, name, content))
+    })
+}
+
+/// Uploads a file to the NATS object store with a description, metadata and headers.
+///
+/// # Arguments
+/// * `store` - The name of the object store
+/// * `name` - The name under which to store the file
+/// * `content` - The file content as a byte array
+/// * `description` *(optional)* - A description of the file
+/// * `metadata` *(optional)* - A JSON object of string values stored with the file
+/// * `headers` *(optional)* - A JSON object of headers stored with the file
+///
+/// # Returns
+/// * `Ok(_)` - A row with the metadata of the uploaded file, with the same columns as
+///   [`nats_get_file_info`]
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT digest FROM nats_put_file_with_meta(
+///     'documents', 'report.pdf', 'binary data'::bytea,
+///     description => 'Quarterly report',
+///     metadata => '{"owner": "finance"}',
+///     headers => '{"Content-Type": "application/pdf"}'
+/// );
+/// ```
+#[allow(clippy::type_complexity)]
+#[pg_extern]
+#[cfg(feature = "object_store")]
+pub fn nats_put_file_with_meta(
+    store: String,
+    name: &str,
+    content: Vec<u8>,
+    description: pgrx::default!(Option<String>, "NULL"),
+    metadata: pgrx::default!(Option<pgrx::JsonB>, "NULL"),
+    headers: pgrx::default!(Option<pgrx::JsonB>, "NULL"),
+) -> anyhow::Result<
+    pgrx::iter::TableIterator<
+        'static,
+        (
+            name!(name, String),
+            name!(description, Option<String>),
+            name!(metadata, pgrx::JsonB),
+            name!(headers, Option<pgrx::JsonB>),
+            name!(options, Option<pgrx::JsonB>),
+            name!(bucket, String),
+            name!(nuid, String),
+            name!(size, i64),
+            name!(chunks, i64),
+            name!(modified, Option<String>),
+            name!(digest, Option<String>),
+            name!(delete, bool),
+        ),
+    >,
+> {
+    let meta = crate::nats_client::FileMeta {
+        description,
+        metadata: metadata
+            .map(|v| crate::utils::json_to_file_metadata(v.0))
+            .transpose()?
+            .unwrap_or_default(),
+        headers: headers
+            .map(|v| crate::utils::extract_headers(v.0))
+            .transpose()?,
+    };
+
+    CTX.with_borrow_mut(|ctx| {
+        ctx.rt
+            .block_on(
+                ctx.nats_connection
+                    .put_file_with_meta(store, name, content, meta),
+            )
+            .map(|v| super::conv::map_object_info(std::iter::once(v)))
+    })
+}
+
+/// Renames a file of the NATS object store or changes its description, metadata or
+/// headers, without uploading its content again.
+///
+/// # Arguments
+/// * `store` - The name of the object store
+/// * `name` - The current name of the file
+/// * `new_name` *(optional)* - The new name of the file
+/// * `description` *(optional)* - The new description
+/// * `metadata` *(optional)* - The new metadata, replacing the current one
+/// * `headers` *(optional)* - The new headers, replacing the current ones
+///
+/// Arguments left `NULL` keep the current value.
+///
+/// # Returns
+/// * `Ok(_)` - A row with the updated metadata of the file, with the same columns as
+///   [`nats_get_file_info`]
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT * FROM nats_update_file_meta('documents', 'report.pdf', new_name => 'report-q1.pdf');
+/// SELECT * FROM nats_update_file_meta('documents', 'report-q1.pdf', metadata => '{"owner": "audit"}');
+/// ```
+#[allow(clippy::type_complexity)]
+#[pg_extern]
+#[cfg(feature = "object_store")]
+pub fn nats_update_file_meta(
+    store: String,
+    name: &str,
+    new_name: pgrx::default!(Option<String>, "NULL"),
+    description: pgrx::default!(Option<String>, "NULL"),
+    metadata: pgrx::default!(Option<pgrx::JsonB>, "NULL"),
+    headers: pgrx::default!(Option<pgrx::JsonB>, "NULL"),
+) -> anyhow::Result<
+    pgrx::iter::TableIterator<
+        'static,
+        (
+            name!(name, String),
+            name!(description, Option<String>),
+            name!(metadata, pgrx::JsonB),
+            name!(headers, Option<pgrx::JsonB>),
+            name!(options, Option<pgrx::JsonB>),
+            name!(bucket, String),
+            name!(nuid, String),
+            name!(size, i64),
+            name!(chunks, i64),
+            name!(modified, Option<String>),
+            name!(digest, Option<String>),
+            name!(delete, bool),
+        ),
+    >,
+> {
+    let metadata = metadata
+        .map(|v| crate::utils::json_to_file_metadata(v.0))
+        .transpose()?;
+    let headers = headers
+        .map(|v| crate::utils::extract_headers(v.0))
+        .transpose()?;
+
+    CTX.with_borrow_mut(|ctx| {
+        ctx.rt
+            .block_on(ctx.nats_connection.update_file_meta(
+                store,
+                name,
+                new_name,
+                description,
+                metadata,
+                headers,
+            ))
+            .map(|v| super::conv::map_object_info(std::iter::once(v)))
+    })
+}
+
+/// Deletes a file from the NATS object store.
+///
+/// # Arguments
+/// * `store` - The name of the object store
+/// * `name` - The name of the file to delete
+///
+/// # Returns
+/// * `Ok(())` - If the deletion was successful
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_delete_file('documents', 'old_report.pdf');
+/// ```
+#[pg_extern]
+#[cfg(feature = "object_store")]
+pub fn nats_delete_file(store: String, name: &str) -> anyhow::Result<()> {
+    CTX.with_borrow_mut(|ctx| {
+        ctx.rt
+            .block_on(ctx.nats_connection.delete_file(store, name))
+    })
+}
+
+/// Uploads a file from the filesystem of the database server to the NATS object store,
+/// streaming it in chunks.
+///
+/// Requires the privileges of the `pg_read_server_files` role, like `COPY ... FROM` a file.
+///
+/// # Arguments
+/// * `store` - The name of the object store
+/// * `name` - The name under which to store the file
+/// * `path` - The path of the file on the server, relative to the data directory if not absolute
+///
+/// # Returns
+/// * `Ok(i64)` - The size of the uploaded file in bytes
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_put_file_from_path('backups', 'dump.tar', '/var/backups/dump.tar');
+/// ```
+#[pg_extern]
+#[cfg(feature = "object_store")]
+pub fn nats_put_file_from_path(store: String, name: &str, path: &str) -> anyhow::Result<i64> {
+    crate::utils::check_server_files_access("pg_read_server_files")?;
+
+    let info = CTX.with_borrow_mut(|ctx| {
+        let mut file = ctx
+            .rt
+            .block_on(tokio::fs::File::open(path))
+            .map_err(|err| anyhow::anyhow!("Failed to open file '{path}': {err}"))?;
+
+        ctx.rt.block_on(
+            ctx.nats_connection
+                .put_file_from_reader(store, name, &mut file),
+        )
+    })?;
+
+    Ok(info.size.try_into().unwrap_or(i64::MAX))
+}
+
+/// Downloads a file from the NATS object store to the filesystem of the database server,
+/// streaming it in chunks. An existing file at `path` is overwritten.
+///
+/// Requires the privileges of the `pg_write_server_files` role, like `COPY ... TO` a file.
+///
+/// # Arguments
+/// * `store` - The name of the object store
+/// * `name` - The name of the file to download
+/// * `path` - The path of the file on the server, relative to the data directory if not absolute
+///
+/// # Returns
+/// * `Ok(i64)` - The number of bytes written
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_get_file_to_path('backups', 'dump.tar', '/var/restore/dump.tar');
+/// ```
+#[pg_extern]
+#[cfg(feature = "object_store")]
+pub fn nats_get_file_to_path(store: String, name: &str, path: &str) -> anyhow::Result<i64> {
+    crate::utils::check_server_files_access("pg_write_server_files")?;
+
+    let written = CTX.with_borrow_mut(|ctx| {
+        let mut object = ctx
+            .rt
+            .block_on(ctx.nats_connection.get_file_reader(store, name))?;
+
+        ctx.rt.block_on(async {
+            let mut file = tokio::fs::File::create(path)
+                .await
+                .map_err(|err| anyhow::anyhow!("Failed to create file '{path}': {err}"))?;
+            let written = tokio::io::copy(&mut object, &mut file).await?;
+            file.sync_all().await?;
+
+            anyhow::Ok(written)
+        })
+    })?;
+
+    Ok(written.try_into().unwrap_or(i64::MAX))
+}
+
+/// Uploads the content of a PostgreSQL large object to the NATS object store, reading it
+/// in chunks, so that files larger than the `bytea` limit can be stored.
+///
+/// # Arguments
+/// * `store` - The name of the object store
+/// * `name` - The name under which to store the file
+/// * `loid` - The OID of the large object
+///
+/// # Returns
+/// * `Ok(i64)` - The size of the uploaded file in bytes
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_put_file_from_lo('videos', 'intro.mp4', lo_import('/tmp/intro.mp4'));
+/// ```
+#[pg_extern]
+#[cfg(feature = "object_store")]
+pub fn nats_put_file_from_lo(
+    store: String,
+    name: String,
+    loid: pgrx::pg_sys::Oid,
+) -> anyhow::Result<i64> {
+    let (sender, mut reader) = crate::nats_client::ChunkReader::channel(FILE_CHUNK_QUEUE);
+
+    let upload = CTX.with_borrow_mut(|ctx| {
+        let store = ctx
+            .rt
+            .block_on(ctx.nats_connection.get_object_store(store))?;
+
+        anyhow::Ok(
+            ctx.rt
+                .spawn(async move { store.put(name.as_str(), &mut reader).await }),
+        )
+    })?;
+
+    let chunk_size = i32::try_from(FILE_CHUNK_SIZE)?;
+    let mut offset = 0_i64;
+
+    loop {
+        let chunk = pgrx::Spi::get_one_with_args::<Vec<u8>>(
+            "SELECT lo_get($1, $2, $3)",
+            &[loid.into(), offset.into(), chunk_size.into()],
+        )?
+        .unwrap_or_default();
+        let last = chunk.is_empty();
+        offset += i64::try_from(chunk.len())?;
+
+        // A failed send means the upload has already stopped; its error is reported below.
+        if sender.blocking_send(chunk).is_err() || last {
+            break;
+        }
+    }
+
+    drop(sender);
+
+    let info = CTX.with_borrow(|ctx| ctx.rt.block_on(upload))??;
+
+    Ok(info.size.try_into().unwrap_or(i64::MAX))
+}
+
+/// Downloads a file from the NATS object store into a new PostgreSQL large object,
+/// writing it in chunks, so that files larger than the `bytea` limit can be loaded.
+///
+/// # Arguments
+/// * `store` - The name of the object store
+/// * `name` - The name of the file to download
+///
+/// # Returns
+/// * `Ok(Oid)` - The OID of the created large object
+///
+/// # SQL Usage
+/// ```sql
+/// INSERT INTO videos (name, content) VALUES ('intro', nats_get_file_to_lo('videos', 'intro.mp4'));
+/// ```
+#[pg_extern]
+#[cfg(feature = "object_store")]
+pub fn nats_get_file_to_lo(store: String, name: &str) -> anyhow::Result<pgrx::pg_sys::Oid> {
+    use tokio::io::AsyncReadExt;
+
+    let mut object = CTX.with_borrow_mut(|ctx| {
+        ctx.rt
+            .block_on(ctx.nats_connection.get_file_reader(store, name))
+    })?;
+
+    let loid = pgrx::Spi::get_one::<pgrx::pg_sys::Oid>("SELECT lo_from_bytea(0, ''::bytea)")?
+        .ok_or_else(|| anyhow::anyhow!("Failed to create large object"))?;
+
+    let mut buffer = vec![0; FILE_CHUNK_SIZE];
+    let mut offset = 0_i64;
+
+    loop {
+        let read = CTX.with_borrow(|ctx| ctx.rt.block_on(object.read(&mut buffer)))?;
+        let Some(chunk) = buffer.get(..read).filter(|chunk| !chunk.is_empty()) else {
+            break;
+        };
+
+        pgrx::Spi::run_with_args(
+            "SELECT lo_put($1, $2, $3)",
+            &[loid.into(), offset.into(), chunk.into()],
+        )?;
+        offset += i64::try_from(read)?;
+    }
+
+    Ok(loid)
+}
+
+/// Adds a link to a file, which can then be read through the link like the file itself.
+///
+/// # Arguments
+/// * `store` - The name of the object store receiving the link
+/// * `name` - The name of the link
+/// * `target` - The name of the linked file
+/// * `target_store` *(optional)* - The object store of the linked file, `store` by default
+///
+/// # Returns
+/// * `Ok(_)` - A row with the metadata of the link, with the same columns as
+///   [`nats_get_file_info`]; `options` holds the link target
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT * FROM nats_link_file('documents', 'latest.pdf', 'report-q1.pdf');
+/// SELECT * FROM nats_link_file('public', 'report.pdf', 'report-q1.pdf', 'documents');
+/// ```
+#[allow(clippy::type_complexity)]
+#[pg_extern]
+#[cfg(feature = "object_store")]
+pub fn nats_link_file(
+    store: String,
+    name: &str,
+    target: &str,
+    target_store: pgrx::default!(Option<String>, "NULL"),
+) -> anyhow::Result<
+    pgrx::iter::TableIterator<
+        'static,
+        (
+            name!(name, String),
+            name!(description, Option<String>),
+            name!(metadata, pgrx::JsonB),
+            name!(headers, Option<pgrx::JsonB>),
+            name!(options, Option<pgrx::JsonB>),
+            name!(bucket, String),
+            name!(nuid, String),
+            name!(size, i64),
+            name!(chunks, i64),
+            name!(modified, Option<String>),
+            name!(digest, Option<String>),
+            name!(delete, bool),
+        ),
+    >,
+> {
+    let target_store = target_store.unwrap_or_else(|| store.clone());
+
+    CTX.with_borrow_mut(|ctx| {
+        ctx.rt
+            .block_on(
+                ctx.nats_connection
+                    .link_file(store, name, target_store, target),
+            )
+            .map(|v| super::conv::map_object_info(std::iter::once(v)))
+    })
+}
+
+/// Adds a link to a whole object store.
+///
+/// # Arguments
+/// * `store` - The name of the object store receiving the link
+/// * `name` - The name of the link
+/// * `target_store` - The name of the linked object store
+///
+/// # Returns
+/// * `Ok(_)` - A row with the metadata of the link, with the same columns as
+///   [`nats_get_file_info`]; `options` holds the link target
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT * FROM nats_link_object_store('documents', 'archive', 'documents_2024');
+/// ```
+#[allow(clippy::type_complexity)]
+#[pg_extern]
+#[cfg(feature = "object_store")]
+pub fn nats_link_object_store(
+    store: String,
+    name: &str,
+    target_store: String,
+) -> anyhow::Result<
+    pgrx::iter::TableIterator<
+        'static,
+        (
+            name!(name, String),
+            name!(description, Option<String>),
+            name!(metadata, pgrx::JsonB),
+            name!(headers, Option<pgrx::JsonB>),
+            name!(options, Option<pgrx::JsonB>),
+            name!(bucket, String),
+            name!(nuid, String),
+            name!(size, i64),
+            name!(chunks, i64),
+            name!(modified, Option<String>),
+            name!(digest, Option<String>),
+            name!(delete, bool),
+        ),
+    >,
+> {
+    CTX.with_borrow_mut(|ctx| {
+        ctx.rt
+            .block_on(
+                ctx.nats_connection
+                    .link_object_store(store, name, target_store),
+            )
+            .map(|v| super::conv::map_object_info(std::iter::once(v)))
+    })
+}
+
+/// Creates a NATS object store with explicit settings.
+///
+/// Object stores used by `nats_put_file` and the other file functions are created on
+/// first use with the server defaults.
+///
+/// # Arguments
+/// * `store` - The name of the object store
+/// * `options` *(optional)* - Settings as `jsonb`: `description`, `ttl` (seconds),
+///   `max_bytes`, `storage` (`file` or `memory`), `replicas` and `compression`
+///
+/// # Returns
+/// * `Ok(())` - If the object store was created or already exists with the same settings
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_object_store_create('documents', '{"max_bytes": 1073741824, "replicas": 3}');
+/// ```
+#[pg_extern]
+#[cfg(feature = "object_store")]
+pub fn nats_object_store_create(
+    store: String,
+    options: pgrx::default!(Option<pgrx::JsonB>, "NULL"),
+) -> anyhow::Result<()> {
+    let options = match options {
+        Some(options) => serde_json::from_value(options.0)
+            .map_err(|err| anyhow::anyhow!("Invalid object store options: {err}"))?,
+        None => crate::nats_client::ObjectStoreOptions::default(),
+    };
+
+    CTX.with_borrow_mut(|ctx| {
+        ctx.rt
+            .block_on(ctx.nats_connection.create_object_store(store, options))
+    })
+}
+
+/// Deletes a NATS object store together with all its files.
+///
+/// # Arguments
+/// * `store` - The name of the object store
+///
+/// # Returns
+/// * `Ok(())` - If the object store was deleted
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_object_store_delete('documents');
+/// ```
+#[pg_extern]
+#[cfg(feature = "object_store")]
+pub fn nats_object_store_delete(store: String) -> anyhow::Result<()> {
+    CTX.with_borrow_mut(|ctx| {
+        ctx.rt
+            .block_on(ctx.nats_connection.delete_object_store(store))
+    })
+}
+
+/// Seals a NATS object store. Its files can still be read, but no longer added, changed or
+/// deleted. Sealing cannot be undone.
+///
+/// # Arguments
+/// * `store` - The name of the object store
+///
+/// # Returns
+/// * `Ok(())` - If the object store was sealed
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_object_store_seal('documents_2024');
+/// ```
+#[pg_extern]
+#[cfg(feature = "object_store")]
+pub fn nats_object_store_seal(store: &str) -> anyhow::Result<()> {
+    CTX.with_borrow_mut(|ctx| {
+        ctx.rt
+            .block_on(ctx.nats_connection.seal_object_store(store))
+    })
+}
+
+/// Returns the settings and usage of a NATS object store.
+///
+/// # Arguments
+/// * `store` - The name of the object store
+///
+/// # Returns
+/// * `Ok(_)` - A single row with the size in bytes, whether the object store is sealed, the
+///   name of its backing stream and its settings; `ttl` and `max_bytes` are `NULL` when
+///   unlimited
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT size, sealed, stream FROM nats_object_store_status('documents');
+/// ```
+#[allow(clippy::type_complexity)]
+#[pg_extern]
+#[cfg(feature = "object_store")]
+pub fn nats_object_store_status(
+    store: String,
+) -> anyhow::Result<
+    pgrx::iter::TableIterator<
+        'static,
+        (
+            name!(bucket, String),
+            name!(description, Option<String>),
+            name!(size, i64),
+            name!(sealed, bool),
+            name!(stream, String),
+            name!(ttl, Option<f64>),
+            name!(max_bytes, Option<i64>),
+            name!(storage, String),
+            name!(replicas, i32),
+            name!(compression, bool),
+        ),
+    >,
+> {
+    CTX.with_borrow_mut(|ctx| {
+        ctx.rt
+            .block_on(ctx.nats_connection.get_object_store_status(&store))
+            .map(|info| super::conv::map_object_store_status(store, info))
+    })
+}
+
+/// Retrieves metadata information for a specific file in the NATS object store.
+///
+/// # Arguments
+/// * `store` - The name of the object store
+/// * `name` - The name of the file
+///
+/// # Returns
+/// * `Ok(_)` - A row with file metadata if successful
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT * FROM nats_get_file_info('documents', 'report.pdf');
+/// ```
+#[allow(clippy::type_complexity)]
+#[pg_extern]
+#[cfg(feature = "object_store")]
+pub fn nats_get_file_info(
+    store: String,
+    name: &str,
+) -> anyhow::Result<
+    pgrx::iter::TableIterator<
+        'static,
+        (
+            name!(name, String),
+            name!(description, Option<String>),
+            name!(metadata, pgrx::JsonB),
+            name!(headers, Option<pgrx::JsonB>),
+            name!(options, Option<pgrx::JsonB>),
+            name!(bucket, String),
+            name!(nuid, String),
+            name!(size, i64),
+            name!(chunks, i64),
+            name!(modified, Option<String>),
+            name!(digest, Option<String>),
+            name!(delete, bool),
+        ),
+    >,
+> {
+    CTX.with_borrow_mut(|ctx| {
+        ctx.rt
+            .block_on(ctx.nats_connection.get_file_info(store, name))
+            .map(|v| super::conv::map_object_info(std::iter::once(v)))
+    })
+}
+
+/// Retrieves a list of all files in the specified NATS object store.
+///
+/// # Arguments
+/// * `store` - The name of the object store
+///
+/// # Returns
+/// * `Ok(_)` - Iterator with metadata for all files
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT * FROM nats_get_file_list('documents');
+#[allow(clippy::type_complexity)]
+#[pg_extern]
+#[cfg(feature = "object_store")]
+pub fn nats_get_file_list(
+    store: String,
+) -> anyhow::Result<
+    pgrx::iter::TableIterator<
+        'static,
+        (
+            name!(name, String),
+            name!(description, Option<String>),
+            name!(metadata, pgrx::JsonB),
+            name!(headers, Option<pgrx::JsonB>),
+            name!(options, Option<pgrx::JsonB>),
+            name!(bucket, String),
+            name!(nuid, String),
+            name!(size, i64),
+            name!(chunks, i64),
+            name!(modified, Option<String>),
+            name!(digest, Option<String>),
+            name!(delete, bool),
+        ),
+    >,
+> {
+    CTX.with_borrow_mut(|ctx| {
+        ctx.rt
+            .block_on(ctx.nats_connection.get_file_list(store))
+            .map(|v| super::conv::map_object_info(v))
+    })
+}
+
+/// Creates a JetStream stream.
+///
+/// # Arguments
+/// * `name` - The name of the stream
+/// * `config` *(optional)* - Stream configuration as `jsonb`, using the field names of the
+///   JetStream API (`subjects`, `retention`, `max_msgs`, `max_bytes`, `max_age` in
+///   nanoseconds, `storage`, `num_replicas`, ...). Omitted fields use the server defaults.
+///
+/// # Returns
+/// * `Ok(())` - If the stream was created or already exists with the same configuration
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_stream_create('ORDERS', '{"subjects": ["orders.>"], "max_msgs": 100000}');
+/// ```
+#[pg_extern]
+pub fn nats_stream_create(
+    name: String,
+    config: pgrx::default!(Option<pgrx::JsonB>, "NULL"),
+) -> anyhow::Result<()> {
+    let config = config.map_or_else(|| serde_json::json!({}), |c| c.0);
+
+    CTX.with_borrow_mut(|ctx| {
+        ctx.rt
+            .block_on(ctx.nats_connection.create_stream(name, config))
+            .map(|_| ())
+    })
+}
+
+/// Updates the configuration of an existing JetStream stream.
+///
+/// Only the fields present in `config` are changed; the rest of the current
+/// configuration is kept.
+///
+/// # Arguments
+/// * `name` - The name of the stream
+/// * `config` - Configuration changes as `jsonb`, using the field names of the JetStream API
+///
+/// # Returns
+/// * `Ok(())` - If the stream was updated
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_stream_update('ORDERS', '{"subjects": ["orders.>", "refunds.>"]}');
+/// ```
+#[pg_extern]
+pub fn nats_stream_update(name: String, config: pgrx::JsonB) -> anyhow::Result<()> {
+    CTX.with_borrow_mut(|ctx| {
+        ctx.rt
+            .block_on(ctx.nats_connection.update_stream(name, config.0))
+            .map(|_| ())
+    })
+}
+
+/// Deletes a JetStream stream together with all its messages and consumers.
+///
+/// # Arguments
+/// * `name` - The name of the stream
+///
+/// # Returns
+/// * `Ok(())` - If the stream was deleted
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_stream_delete('ORDERS');
+/// ```
+#[pg_extern]
+pub fn nats_stream_delete(name: String) -> anyhow::Result<()> {
+    CTX.with_borrow_mut(|ctx| ctx.rt.block_on(ctx.nats_connection.delete_stream(name)))
+}
+
+/// Returns the state and configuration of a JetStream stream.
+///
+/// # Arguments
+/// * `name` - The name of the stream
+///
+/// # Returns
+/// * `Ok(_)` - A single row with the subjects, the number of messages and bytes, the first
+///   and last sequence, the number of consumers, the creation time and the configuration
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT messages, last_seq FROM nats_stream_info('ORDERS');
+/// ```
+#[allow(clippy::type_complexity)]
+#[pg_extern]
+pub fn nats_stream_info(
+    name: String,
+) -> anyhow::Result<
+    pgrx::iter::TableIterator<
+        'static,
+        (
+            name!(name, String),
+            name!(subjects, Vec<String>),
+            name!(messages, i64),
+            name!(bytes, i64),
+            name!(first_seq, i64),
+            name!(last_seq, i64),
+            name!(consumers, i64),
+            name!(created, Option<pgrx::datum::TimestampWithTimeZone>),
+            name!(config, Option<pgrx::JsonB>),
+        ),
+    >,
+> {
+    CTX.with_borrow_mut(|ctx| {
+        ctx.rt
+            .block_on(ctx.nats_connection.get_stream_info(name))
+            .map(|info| super::conv::map_stream_info([info]))
+    })
+}
+
+/// Lists the JetStream streams with their state and configuration.
+///
+/// # Returns
+/// * `Ok(_)` - One row per stream, with the same columns as [`nats_stream_info`]
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT name, messages FROM nats_stream_list();
+/// ```
+#[allow(clippy::type_complexity)]
+#[pg_extern]
+pub fn nats_stream_list() -> anyhow::Result<
+    pgrx::iter::TableIterator<
+        'static,
+        (
+            name!(name, String),
+            name!(subjects, Vec<String>),
+            name!(messages, i64),
+            name!(bytes, i64),
+            name!(first_seq, i64),
+            name!(last_seq, i64),
+            name!(consumers, i64),
+            name!(created, Option<pgrx::datum::TimestampWithTimeZone>),
+            name!(config, Option<pgrx::JsonB>),
+        ),
+    >,
+> {
+    CTX.with_borrow_mut(|ctx| {
+        ctx.rt
+            .block_on(ctx.nats_connection.list_streams())
+            .map(super::conv::map_stream_info)
     })
 }
 
-/// Retrieves a file's content from the NATS object store by its name.
+/// Retrieves the message stored in a JetStream stream at the specified sequence.
 ///
 /// # Arguments
-/// * `store` - The name of the object store
-/// * `name` - The name of the file to retrieve
+/// * `stream` - The name of the stream
+/// * `seq` - The stream sequence of the message
 ///
 /// # Returns
-/// * `Ok(Vec<u8>)` - The file content as a byte array if successful
+/// * `Ok(_)` - A single row with the sequence, subject, headers, payload and time of the
+///   message; fails if there is no message at that sequence
 ///
 /// # SQL Usage
 /// ```sql
-/// SELECT nats_get_file('documents', 'report.pdf');
+/// SELECT subject, payload FROM nats_stream_get_message('ORDERS', 42);
 /// ```
+#[allow(clippy::type_complexity)]
 #[pg_extern]
-#[cfg(feature = "object_store")]
-pub fn nats_get_file(store: String, name: &str) -> anyhow::Result<Vec<u8>> {
-    CTX.with_borrow_mut(|ctx| ctx.rt.block_on(ctx.nats_connection.get_file(store, name)))
+pub fn nats_stream_get_message(
+    stream: &str,
+    seq: i64,
+) -> anyhow::Result<
+    pgrx::iter::TableIterator<
+        'static,
+        (
+            name!(seq, i64),
+            name!(subject, String),
+            name!(headers, Option<pgrx::JsonB>),
+            name!(payload, Vec<u8>),
+            name!(time, Option<pgrx::datum::TimestampWithTimeZone>),
+        ),
+    >,
+> {
+    let seq = u64::try_from(seq)?;
+
+    CTX.with_borrow_mut(|ctx| {
+        ctx.rt
+            .block_on(ctx.nats_connection.get_stream_message(stream, seq))
+            .map(|message| {
+                pgrx::iter::TableIterator::once(super::conv::map_stored_message(message))
+            })
+    })
 }
 
-/// Uploads a file to the NATS object store.
+/// Retrieves the last message stored in a JetStream stream for the specified subject.
 ///
 /// # Arguments
-/// * `store` - The name of the object store
-/// * `name` - The name under which to store the file
-/// * `content` - The file content as a byte array
+/// * `stream` - The name of the stream
+/// * `subject` - The subject of the message
 ///
 /// # Returns
-/// * `Ok(())` - If the upload was successful
+/// * `Ok(_)` - A single row with the sequence, subject, headers, payload and time of the
+///   message; fails if the stream has no message for the subject
 ///
 /// # SQL Usage
 /// ```sql
-/// SELECT nats_put_file('documents', 'report.pdf', 'binary data'::bytea);
+/// SELECT payload, time FROM nats_stream_get_last('ORDERS', 'orders.42');
 /// ```
+#[allow(clippy::type_complexity)]
 #[pg_extern]
-#[cfg(feature = "object_store")]
-pub fn nats_put_file(store: String, name: &str, content: Vec<u8>) -> anyhow::Result<()> {
+pub fn nats_stream_get_last(
+    stream: &str,
+    subject: &str,
+) -> anyhow::Result<
+    pgrx::iter::TableIterator<
+        'static,
+        (
+            name!(seq, i64),
+            name!(subject, String),
+            name!(headers, Option<pgrx::JsonB>),
+            name!(payload, Vec<u8>),
+            name!(time, Option<pgrx::datum::TimestampWithTimeZone>),
+        ),
+    >,
+> {
     CTX.with_borrow_mut(|ctx| {
         ctx.rt
-            .block_on(ctx.nats_connection.put_file(store, name, content))
+            .block_on(ctx.nats_connection.get_last_stream_message(stream, subject))
+            .map(|message| {
+                pgrx::iter::TableIterator::once(super::conv::map_stored_message(message))
+            })
     })
 }
 
-/// Deletes a file from the NATS object store.
+/// Reads the messages of a JetStream stream in sequence order through an ephemeral
+/// ordered consumer.
+///
+/// Only the messages already stored when the read starts are returned. Rows are fetched
+/// lazily, so large ranges can be streamed into `INSERT ... SELECT`.
 ///
 /// # Arguments
-/// * `store` - The name of the object store
-/// * `name` - The name of the file to delete
+/// * `stream` - The name of the stream
+/// * `start_seq` *(optional)* - The first sequence to read, `1` by default
+/// * `max_msgs` *(optional)* - The maximum number of messages to return, unlimited by default
+/// * `filter_subject` *(optional)* - Only return messages matching this subject
 ///
 /// # Returns
-/// * `Ok(())` - If the deletion was successful
+/// * `Ok(_)` - One row per message with its sequence, subject, headers, payload and time
 ///
 /// # SQL Usage
 /// ```sql
-/// SELECT nats_delete_file('documents', 'old_report.pdf');
+/// INSERT INTO orders_history (seq, payload)
+/// SELECT seq, payload FROM nats_stream_read('ORDERS', 1000, 500, 'orders.paid');
 /// ```
+#[allow(clippy::type_complexity)]
 #[pg_extern]
-#[cfg(feature = "object_store")]
-pub fn nats_delete_file(store: String, name: &str) -> anyhow::Result<()> {
+pub fn nats_stream_read(
+    stream: &str,
+    start_seq: pgrx::default!(i64, 1),
+    max_msgs: pgrx::default!(Option<i64>, "NULL"),
+    filter_subject: pgrx::default!(Option<String>, "NULL"),
+) -> anyhow::Result<
+    pgrx::iter::TableIterator<
+        'static,
+        (
+            name!(seq, i64),
+            name!(subject, String),
+            name!(headers, Option<pgrx::JsonB>),
+            name!(payload, Vec<u8>),
+            name!(time, Option<pgrx::datum::TimestampWithTimeZone>),
+        ),
+    >,
+> {
+    use async_nats::jetstream::consumer::DeliverPolicy;
+    use futures::StreamExt;
+
+    let start_sequence = u64::try_from(start_seq)?.max(1);
+    let max_msgs = max_msgs.map(u64::try_from).transpose()?;
+
+    let (pending, mut messages) = CTX.with_borrow_mut(|ctx| {
+        ctx.rt.block_on(ctx.nats_connection.read_stream(
+            stream,
+            filter_subject,
+            DeliverPolicy::ByStartSequence { start_sequence },
+        ))
+    })?;
+
+    let mut remaining = max_msgs.map_or(pending, |max| max.min(pending));
+
+    let rows = std::iter::from_fn(move || {
+        if remaining == 0 {
+            return None;
+        }
+        remaining -= 1;
+
+        let message = CTX.with_borrow(|ctx| {
+            ctx.rt.block_on(tokio::time::timeout(
+                STREAM_READ_IDLE_TIMEOUT,
+                messages.next(),
+            ))
+        });
+
+        let message = match message {
+            Ok(Some(message)) => message
+                .map_err(anyhow::Error::from)
+                .and_then(crate::nats_client::StoredMessage::try_from),
+            Ok(None) | Err(_) => return None,
+        };
+
+        match message {
+            Ok(message) => Some(super::conv::map_stored_message(message)),
+            Err(err) => {
+                crate::error!("{err}");
+                None
+            }
+        }
+    });
+
+    Ok(pgrx::iter::TableIterator::new(rows))
+}
+
+/// Creates a durable JetStream consumer on a stream.
+///
+/// # Arguments
+/// * `stream` - The name of the stream
+/// * `name` - The durable name of the consumer
+/// * `config` *(optional)* - Consumer configuration as `jsonb`, using the field names of the
+///   JetStream API (`filter_subject`, `deliver_policy`, `ack_policy`, `ack_wait` in
+///   nanoseconds, `max_deliver`, ...). Omitted fields use the server defaults.
+///
+/// # Returns
+/// * `Ok(())` - If the consumer was created or already exists with the same configuration
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_consumer_create('ORDERS', 'billing', '{"filter_subject": "orders.paid"}');
+/// ```
+#[pg_extern]
+pub fn nats_consumer_create(
+    stream: &str,
+    name: &str,
+    config: pgrx::default!(Option<pgrx::JsonB>, "NULL"),
+) -> anyhow::Result<()> {
+    let config = config.map_or_else(|| serde_json::json!({}), |c| c.0);
+
     CTX.with_borrow_mut(|ctx| {
         ctx.rt
-            .block_on(ctx.nats_connection.delete_file(store, name))
+            .block_on(ctx.nats_connection.create_consumer(stream, name, config))
+            .map(|_| ())
     })
 }
 
-/// Retrieves metadata information for a specific file in the NATS object store.
+/// Deletes a JetStream consumer.
 ///
 /// # Arguments
-/// * `store` - The name of the object store
-/// * `name` - The name of the file
+/// * `stream` - The name of the stream
+/// * `name` - The name of the consumer
 ///
 /// # Returns
-/// * `Ok(_)` - A row with file metadata if successful
+/// * `Ok(())` - If the consumer was deleted
 ///
 /// # SQL Usage
 /// ```sql
-/// SELECT * FROM nats_get_file_info('documents', 'report.pdf');
+/// SELECT nats_consumer_delete('ORDERS', 'billing');
+/// ```
+#[pg_extern]
+pub fn nats_consumer_delete(stream: &str, name: &str) -> anyhow::Result<()> {
+    CTX.with_borrow_mut(|ctx| {
+        ctx.rt
+            .block_on(ctx.nats_connection.delete_consumer(stream, name))
+    })
+}
+
+/// Returns the progress and configuration of a JetStream consumer.
+///
+/// # Arguments
+/// * `stream` - The name of the stream
+/// * `name` - The name of the consumer
+///
+/// # Returns
+/// * `Ok(_)` - A single row with the number of pending, unacknowledged, redelivered
+///   messages and waiting pull requests, the last delivered and acknowledged stream
+///   sequences, the creation time and the configuration
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT pending, ack_pending FROM nats_consumer_info('ORDERS', 'billing');
 /// ```
 #[allow(clippy::type_complexity)]
 #[pg_extern]
-#[cfg(feature = "object_store")]
-pub fn nats_get_file_info(
-    store: String,
+pub fn nats_consumer_info(
+    stream: &str,
     name: &str,
 ) -> anyhow::Result<
     pgrx::iter::TableIterator<
         'static,
         (
+            name!(stream, String),
             name!(name, String),
-            name!(description, Option<String>),
-            name!(metadata, pgrx::JsonB),
-            name!(headers, Option<pgrx::JsonB>),
-            name!(options, Option<pgrx::JsonB>),
-            name!(bucket, String),
-            name!(nuid, String),
-            name!(size, i64),
-            name!(chunks, i64),
-            name!(modified, Option<String>),
-            name!(digest, Option<String>),
-            name!(delete, bool),
+            name!(pending, i64),
+            name!(ack_pending, i64),
+            name!(redelivered, i64),
+            name!(waiting, i64),
+            name!(delivered_seq, i64),
+            name!(ack_floor_seq, i64),
+            name!(created, Option<pgrx::datum::TimestampWithTimeZone>),
+            name!(config, Option<pgrx::JsonB>),
         ),
     >,
 > {
     CTX.with_borrow_mut(|ctx| {
         ctx.rt
-            .block_on(ctx.nats_connection.get_file_info(store, name))
-            .map(|v| super::conv::map_object_info(std::iter::once(v)))
+            .block_on(ctx.nats_connection.get_consumer_info(stream, name))
+            .map(|info| super::conv::map_consumer_info([info]))
     })
 }
 
-/// Retrieves a list of all files in the specified NATS object store.
+/// Lists the consumers of a JetStream stream with their progress and configuration.
 ///
 /// # Arguments
-/// * `store` - The name of the object store
+/// * `stream` - The name of the stream
 ///
 /// # Returns
-/// * `Ok(_)` - Iterator with metadata for all files
+/// * `Ok(_)` - One row per consumer, with the same columns as [`nats_consumer_info`]
 ///
 /// # SQL Usage
 /// ```sql
-/// SELECT * FROM nats_get_file_list('documents');
+/// SELECT name, pending FROM nats_consumer_list('ORDERS') WHERE pending > 1000;
+/// ```
 #[allow(clippy::type_complexity)]
 #[pg_extern]
-#[cfg(feature = "object_store")]
-pub fn nats_get_file_list(
-    store: String,
+pub fn nats_consumer_list(
+    stream: &str,
 ) -> anyhow::Result<
     pgrx::iter::TableIterator<
         'static,
         (
+            name!(stream, String),
             name!(name, String),
-            name!(description, Option<String>),
-            name!(metadata, pgrx::JsonB),
+            name!(pending, i64),
+            name!(ack_pending, i64),
+            name!(redelivered, i64),
+            name!(waiting, i64),
+            name!(delivered_seq, i64),
+            name!(ack_floor_seq, i64),
+            name!(created, Option<pgrx::datum::TimestampWithTimeZone>),
+            name!(config, Option<pgrx::JsonB>),
+        ),
+    >,
+> {
+    CTX.with_borrow_mut(|ctx| {
+        ctx.rt
+            .block_on(ctx.nats_connection.list_consumers(stream))
+            .map(super::conv::map_consumer_info)
+    })
+}
+
+/// Pulls a batch of messages from a durable JetStream pull consumer.
+///
+/// Each message comes with an ack token to pass to [`nats_ack`] or [`nats_nak`] once the
+/// message has been processed. With `auto_ack`, the messages are acknowledged when the
+/// current transaction commits and negatively acknowledged if it aborts.
+///
+/// # Arguments
+/// * `stream` - The name of the stream
+/// * `consumer` - The name of the pull consumer
+/// * `batch` - The maximum number of messages to fetch
+/// * `timeout` *(optional)* - How long to wait for the batch to fill, `5 seconds` by default
+/// * `auto_ack` *(optional)* - Acknowledge the messages at the end of the transaction
+///
+/// # Returns
+/// * `Ok(_)` - One row per message with its sequence, subject, headers, payload, time,
+///   delivery count and ack token; fewer than `batch` rows if the timeout elapses first
+///
+/// # SQL Usage
+/// ```sql
+/// INSERT INTO orders (payload)
+/// SELECT payload FROM nats_consumer_fetch('ORDERS', 'ingest', 100, auto_ack => true);
+/// ```
+#[allow(clippy::type_complexity)]
+#[pg_extern]
+pub fn nats_consumer_fetch(
+    stream: &str,
+    consumer: &str,
+    batch: i32,
+    timeout: pgrx::default!(pgrx::datum::Interval, "'5 seconds'"),
+    auto_ack: pgrx::default!(bool, false),
+) -> anyhow::Result<
+    pgrx::iter::TableIterator<
+        'static,
+        (
+            name!(seq, i64),
+            name!(subject, String),
             name!(headers, Option<pgrx::JsonB>),
-            name!(options, Option<pgrx::JsonB>),
-            name!(bucket, String),
-            name!(nuid, String),
-            name!(size, i64),
-            name!(chunks, i64),
-            name!(modified, Option<String>),
-            name!(digest, Option<String>),
-            name!(delete, bool),
+            name!(payload, Vec<u8>),
+            name!(time, Option<pgrx::datum::TimestampWithTimeZone>),
+            name!(delivered, i64),
+            name!(ack_token, String),
         ),
     >,
 > {
+    let batch = usize::try_from(batch)
+        .ok()
+        .filter(|batch| *batch > 0)
+        .ok_or_else(|| anyhow::anyhow!("Batch size must be positive"))?;
+    let timeout = crate::utils::interval_to_duration(timeout)?;
+
+    let messages = CTX.with_borrow_mut(|ctx| {
+        ctx.rt.block_on(
+            ctx.nats_connection
+                .fetch_messages(stream, consumer, batch, timeout),
+        )
+    })?;
+
+    if auto_ack && !messages.is_empty() {
+        CTX.with_borrow_mut(|ctx| {
+            if ctx.pending_acks.is_empty() {
+                let _ = pgrx::register_xact_callback(pgrx::PgXactCallbackEvent::Commit, || {
+                    acknowledge_pending(AckKind::Ack)
+                });
+                let _ = pgrx::register_xact_callback(pgrx::PgXactCallbackEvent::Abort, || {
+                    acknowledge_pending(AckKind::Nak(None))
+                });
+            }
+
+            ctx.pending_acks
+                .extend(messages.iter().map(|message| message.ack_token.clone()));
+        });
+    }
+
+    Ok(super::conv::map_fetched_messages(messages))
+}
+
+/// Acknowledges a message fetched with [`nats_consumer_fetch`], so that it is not
+/// delivered again.
+///
+/// # Arguments
+/// * `token` - The ack token of the message
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_ack(ack_token) FROM fetched WHERE processed;
+/// ```
+#[pg_extern]
+pub fn nats_ack(token: String) -> anyhow::Result<()> {
     CTX.with_borrow_mut(|ctx| {
         ctx.rt
-            .block_on(ctx.nats_connection.get_file_list(store))
-            .map(|v| super::conv::map_object_info(v))
+            .block_on(ctx.nats_connection.acknowledge([token], AckKind::Ack))
+    })
+}
+
+/// Negatively acknowledges a message fetched with [`nats_consumer_fetch`], so that it is
+/// delivered again.
+///
+/// # Arguments
+/// * `token` - The ack token of the message
+/// * `delay` *(optional)* - How long the server waits before redelivering the message,
+///   immediately by default
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_nak(ack_token, '30 seconds') FROM fetched WHERE NOT processed;
+/// ```
+#[pg_extern]
+pub fn nats_nak(
+    token: String,
+    delay: pgrx::default!(Option<pgrx::datum::Interval>, "NULL"),
+) -> anyhow::Result<()> {
+    let delay = delay.map(crate::utils::interval_to_duration).transpose()?;
+
+    CTX.with_borrow_mut(|ctx| {
+        ctx.rt.block_on(
+            ctx.nats_connection
+                .acknowledge([token], AckKind::Nak(delay)),
+        )
     })
 }
 
+/// Acknowledges the messages fetched with `auto_ack` once the transaction has ended.
+/// Errors cannot be raised at this point, so they are only reported as warnings, and the
+/// wait is bounded so that an unreachable server cannot hold up the end of the transaction.
+fn acknowledge_pending(kind: AckKind) {
+    CTX.with_borrow_mut(|ctx| {
+        let tokens = std::mem::take(&mut ctx.pending_acks);
+
+        match ctx.rt.block_on(tokio::time::timeout(
+            ACK_TIMEOUT,
+            ctx.nats_connection.acknowledge(tokens, kind),
+        )) {
+            Ok(Ok(())) => {}
+            Ok(Err(err)) => crate::warn!("Failed to acknowledge fetched messages: {err}"),
+            Err(_) => crate::warn!(
+                "Timed out acknowledging fetched messages after {} seconds",
+                ACK_TIMEOUT.as_secs()
+            ),
+        }
+    });
+}
+
 /// Subscribes to a NATS subject and associates it with a PostgreSQL callback function.
 ///
 /// Multiple callback functions can be subscribed to the same subject — each will be invoked
-/// independently when a matching message is received.
+/// independently when a matching message is received. Subjects may contain wildcards;
+/// overlapping subscriptions share a single NATS subscription, and each message is passed to
+/// the callbacks of every subscribed subject it matches.
 ///
 /// # Arguments
-/// * `subject` - The NATS subject to subscribe to (e.g., "events.user.created")
+/// * `subject` - The NATS subject to subscribe to (e.g., "events.user.created" or "events.>")
 /// * `fn_oid` - The OID of the PostgreSQL function to invoke when a message is received
 ///
 /// # Returns
@@ -561,8 +2508,9 @@ pub fn nats_get_file_list(
 /// ```
 ///
 /// # Warning
-/// The specified PostgreSQL function **must accept a single argument of type `bytea`**,
-/// which will contain the message payload received from NATS.
+/// The specified PostgreSQL function **must accept an argument of type `bytea`**,
+/// which will contain the message payload received from NATS, optionally followed by an
+/// argument of type `text`, which will contain the subject of the message.
 #[pg_extern]
 #[cfg(feature = "sub")]
 pub fn nats_subscribe(subject: String, fn_oid: pg_sys::Oid) -> anyhow::Result<()> {
@@ -573,7 +2521,9 @@ pub fn nats_subscribe(subject: String, fn_oid: pg_sys::Oid) -> anyhow::Result<()
         anyhow::bail!("Subscriptions are not allowed in replica mode");
     }
 
-    let fn_name = resolve_bytea_name(fn_oid)?
+    crate::subject::validate_subject(&subject, true)?;
+
+    let (fn_name, with_subject) = resolve_subscriber_name(fn_oid)?
         .ok_or_else(|| anyhow::anyhow!("Failed to get function name"))?;
 
     crate::bgw::launcher::send_message_to_launcher_with_retry(
@@ -585,6 +2535,7 @@ pub fn nats_subscribe(subject: String, fn_oid: pg_sys::Oid) -> anyhow::Result<()
             db_oid: unsafe { pgrx::pg_sys::MyDatabaseId }.to_u32(),
             subject,
             fn_name,
+            with_subject,
         },
         5,
         std::time::Duration::from_secs(1),
@@ -617,7 +2568,7 @@ pub fn nats_unsubscribe(subject: String, fn_oid: pg_sys::Oid) -> anyhow::Result<
         anyhow::bail!("Subscriptions are not allowed in replica mode");
     }
 
-    let fn_name = resolve_bytea_name(fn_oid)?
+    let (fn_name, _) = resolve_subscriber_name(fn_oid)?
         .ok_or_else(|| anyhow::anyhow!("Failed to get function name"))?;
 
     crate::bgw::launcher::send_message_to_launcher_with_retry(
@@ -634,3 +2585,120 @@ pub fn nats_unsubscribe(subject: String, fn_oid: pg_sys::Oid) -> anyhow::Result<
         std::time::Duration::from_secs(1),
     )
 }
+
+/// Watches a Key-Value bucket and invokes a PostgreSQL callback function on every change.
+///
+/// The watch is handled by the subscriber background worker and persists across restarts.
+/// Every `put`, `delete` or `purge` on a key matching `key_pattern` results in one call.
+///
+/// # Arguments
+/// * `bucket` - The name of the NATS KV bucket to watch
+/// * `key_pattern` - Key or wildcard pattern to watch (e.g., "config.>")
+/// * `fn_oid` - The OID of the PostgreSQL function to invoke on each change
+///
+/// # Returns
+/// * `Ok(())` - If the watch request was successfully sent
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_kv_watch('settings', 'config.>', 'schema.on_config_change'::regproc);
+/// ```
+///
+/// # Warning
+/// The specified PostgreSQL function **must accept four arguments** of types
+/// `(key text, value bytea, revision bigint, operation text)`. For `delete` and
+/// `purge` operations `value` is `NULL`.
+#[pg_extern]
+#[cfg(all(feature = "sub", feature = "kv"))]
+pub fn nats_kv_watch(
+    bucket: String,
+    key_pattern: String,
+    fn_oid: pg_sys::Oid,
+) -> anyhow::Result<()> {
+    // SAFETY: Calling Postgres backend function which takes no arguments,
+    // has no side effects, and does not rely on any Rust-managed memory.
+    // Safe as long as we are running inside a valid Postgres backend process.
+    if unsafe { pgrx::pg_sys::RecoveryInProgress() } {
+        anyhow::bail!("KV watches are not allowed in replica mode");
+    }
+
+    let fn_name = resolve_kv_watch_name(fn_oid)?;
+
+    crate::bgw::launcher::send_message_to_launcher_with_retry(
+        &crate::bgw::LAUNCHER_MESSAGE_BUS,
+        crate::bgw::launcher::message::LauncherMessage::KvWatch {
+            // SAFETY: `MyDatabaseId` is a Postgres backend global which is initialized
+            // before extension code is executed. Postgres backends are single-threaded,
+            // and this variable is immutable after initialization.
+            db_oid: unsafe { pgrx::pg_sys::MyDatabaseId }.to_u32(),
+            bucket,
+            key_pattern,
+            fn_name,
+        },
+        5,
+        std::time::Duration::from_secs(1),
+    )
+}
+
+/// Stops a Key-Value watch previously registered with [`nats_kv_watch`].
+///
+/// Only the specified callback function will be removed. Other callbacks watching the
+/// same bucket and key pattern will remain active.
+///
+/// # Arguments
+/// * `bucket` - The name of the watched NATS KV bucket
+/// * `key_pattern` - The key pattern used when registering the watch
+/// * `fn_oid` - The OID of the previously registered PostgreSQL function
+///
+/// # Returns
+/// * `Ok(())` - If the unwatch request was successfully sent
+///
+/// # SQL Usage
+/// ```sql
+/// SELECT nats_kv_unwatch('settings', 'config.>', 'schema.on_config_change'::regproc);
+/// ```
+#[pg_extern]
+#[cfg(all(feature = "sub", feature = "kv"))]
+pub fn nats_kv_unwatch(
+    bucket: String,
+    key_pattern: String,
+    fn_oid: pg_sys::Oid,
+) -> anyhow::Result<()> {
+    // SAFETY: Calling Postgres backend function which takes no arguments,
+    // has no side effects, and does not rely on any Rust-managed memory.
+    // Safe as long as we are running inside a valid Postgres backend process.
+    if unsafe { pgrx::pg_sys::RecoveryInProgress() } {
+        anyhow::bail!("KV watches are not allowed in replica mode");
+    }
+
+    let fn_name = resolve_kv_watch_name(fn_oid)?;
+
+    crate::bgw::launcher::send_message_to_launcher_with_retry(
+        &crate::bgw::LAUNCHER_MESSAGE_BUS,
+        crate::bgw::launcher::message::LauncherMessage::KvUnwatch {
+            // SAFETY: `MyDatabaseId` is a Postgres backend global which is initialized
+            // before extension code is executed. Postgres backends are single-threaded,
+            // and this variable is immutable after initialization.
+            db_oid: unsafe { pgrx::pg_sys::MyDatabaseId }.to_u32(),
+            bucket,
+            key_pattern,
+            fn_name,
+        },
+        5,
+        std::time::Duration::from_secs(1),
+    )
+}
+
+#[cfg(all(feature = "sub", feature = "kv"))]
+fn resolve_kv_watch_name(fn_oid: pg_sys::Oid) -> anyhow::Result<String> {
+    crate::utils::resolve_function_name(
+        fn_oid,
+        &[
+            pg_sys::TEXTOID,
+            pg_sys::BYTEAOID,
+            pg_sys::INT8OID,
+            pg_sys::TEXTOID,
+        ],
+    )?
+    .ok_or_else(|| anyhow::anyhow!("Failed to get function name"))
+}
@@ -8,15 +8,16 @@ use crate::{
     },
     config::parse_config,
     error,
+    fdw::validate_table_options,
 };
 
 extension_sql!(
     r#"
-    CREATE FOREIGN DATA WRAPPER pgnats_fdw VALIDATOR pgnats_fdw_validator;
+    CREATE FOREIGN DATA WRAPPER pgnats_fdw HANDLER pgnats_fdw_handler VALIDATOR pgnats_fdw_validator;
     -- CREATE SERVER nats_fdw_server FOREIGN DATA WRAPPER pgnats_fdw OPTIONS (host 'localhost', port '4222');
     "#,
     name = "create_fdw",
-    requires = [pgnats_fdw_validator]
+    requires = ["create_fdw_handler", pgnats_fdw_validator]
 );
 
 extension_sql!(
@@ -81,5 +82,9 @@ pub fn fdw_validator<const N: usize>(
         ) {
             error!("{err}");
         }
+    } else if oid == sys::ForeignTableRelationId {
+        if let Err(err) = validate_table_options(&options) {
+            error!("{err}");
+        }
     }
 }
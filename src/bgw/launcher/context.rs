@@ -57,11 +57,16 @@ impl LauncherContext {
         db_oid: u32,
         subject: String,
         fn_name: String,
+        with_subject: bool,
     ) -> anyhow::Result<()> {
         if let Some(entry) = self.workers.get_mut(&db_oid) {
             send_subscriber_message(
                 &mut entry.sender,
-                SubscriberMessage::Subscribe { subject, fn_name },
+                SubscriberMessage::Subscribe {
+                    subject,
+                    fn_name,
+                    with_subject,
+                },
             )?;
         }
 
@@ -84,6 +89,48 @@ impl LauncherContext {
         Ok(())
     }
 
+    pub fn handle_kv_watch_message(
+        &mut self,
+        db_oid: u32,
+        bucket: String,
+        key_pattern: String,
+        fn_name: String,
+    ) -> anyhow::Result<()> {
+        if let Some(entry) = self.workers.get_mut(&db_oid) {
+            send_subscriber_message(
+                &mut entry.sender,
+                SubscriberMessage::KvWatch {
+                    bucket,
+                    key_pattern,
+                    fn_name,
+                },
+            )?;
+        }
+
+        Ok(())
+    }
+
+    pub fn handle_kv_unwatch_message(
+        &mut self,
+        db_oid: u32,
+        bucket: String,
+        key_pattern: String,
+        fn_name: String,
+    ) -> anyhow::Result<()> {
+        if let Some(entry) = self.workers.get_mut(&db_oid) {
+            send_subscriber_message(
+                &mut entry.sender,
+                SubscriberMessage::KvUnwatch {
+                    bucket,
+                    key_pattern,
+                    fn_name,
+                },
+            )?;
+        }
+
+        Ok(())
+    }
+
     pub fn handle_subscriber_exit_message(&mut self, db_oid: u32) {
         self.shutdown_worker(db_oid);
     }
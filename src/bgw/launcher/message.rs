@@ -23,12 +23,25 @@ pub enum LauncherMessage {
         db_oid: u32,
         subject: String,
         fn_name: String,
+        with_subject: bool,
     },
     Unsubscribe {
         db_oid: u32,
         subject: String,
         fn_name: String,
     },
+    KvWatch {
+        db_oid: u32,
+        bucket: String,
+        key_pattern: String,
+        fn_name: String,
+    },
+    KvUnwatch {
+        db_oid: u32,
+        bucket: String,
+        key_pattern: String,
+        fn_name: String,
+    },
     SubscriberExit {
         db_oid: u32,
         reason: Result<(), String>,
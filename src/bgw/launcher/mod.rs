@@ -152,8 +152,11 @@ pub fn process_launcher_bus<const N: usize>(
                 db_oid,
                 subject,
                 fn_name,
+                with_subject,
             } => {
-                if let Err(err) = ctx.handle_subscribe_message(db_oid, subject, fn_name) {
+                if let Err(err) =
+                    ctx.handle_subscribe_message(db_oid, subject, fn_name, with_subject)
+                {
                     warn!(
                         context = LAUNCHER_CTX,
                         "Failed to process subscription (db_oid: {}): {}", db_oid, err
@@ -182,6 +185,45 @@ pub fn process_launcher_bus<const N: usize>(
                     );
                 }
             }
+            LauncherMessage::KvWatch {
+                db_oid,
+                bucket,
+                key_pattern,
+                fn_name,
+            } => {
+                if let Err(err) = ctx.handle_kv_watch_message(db_oid, bucket, key_pattern, fn_name)
+                {
+                    warn!(
+                        context = LAUNCHER_CTX,
+                        "Failed to process KV watch (db_oid: {}): {}", db_oid, err
+                    );
+                } else {
+                    debug!(
+                        context = LAUNCHER_CTX,
+                        "Registered KV watch: db_oid={}", db_oid
+                    );
+                }
+            }
+            LauncherMessage::KvUnwatch {
+                db_oid,
+                bucket,
+                key_pattern,
+                fn_name,
+            } => {
+                if let Err(err) =
+                    ctx.handle_kv_unwatch_message(db_oid, bucket, key_pattern, fn_name)
+                {
+                    warn!(
+                        context = LAUNCHER_CTX,
+                        "Failed to process KV unwatch (db_oid: {}): {}", db_oid, err
+                    );
+                } else {
+                    debug!(
+                        context = LAUNCHER_CTX,
+                        "Removed KV watch: db_oid={}", db_oid
+                    );
+                }
+            }
             LauncherMessage::SubscriberExit { db_oid, reason } => {
                 match reason {
                     Ok(()) => {
@@ -15,6 +15,7 @@ pub mod ring_queue;
 pub mod subscriber;
 
 pub const SUBSCRIPTIONS_TABLE_NAME: &str = "pgnats.subscriptions";
+pub const KV_WATCHES_TABLE_NAME: &str = "pgnats.kv_watches";
 pub const LAUNCHER_ENTRY_POINT: &str = "background_worker_launcher_entry_point";
 pub const SUBSCRIBER_ENTRY_POINT: &str = "background_worker_subscriber_entry_point";
 
@@ -28,8 +29,16 @@ extension_sql!(
     CREATE TABLE IF NOT EXISTS pgnats.subscriptions (
         subject TEXT NOT NULL,
         callback TEXT NOT NULL,
+        with_subject BOOLEAN NOT NULL DEFAULT false,
         UNIQUE(subject, callback)
     );
+
+    CREATE TABLE IF NOT EXISTS pgnats.kv_watches (
+        bucket TEXT NOT NULL,
+        key_pattern TEXT NOT NULL,
+        callback TEXT NOT NULL,
+        UNIQUE(bucket, key_pattern, callback)
+    );
     "#,
     name = "create_subscriptions_table",
 );
@@ -49,6 +58,8 @@ extension_sql!(
                 clean_name := split_part(obj.object_identity, '(', 1);
                 DELETE FROM pgnats.subscriptions
                 WHERE callback = clean_name;
+                DELETE FROM pgnats.kv_watches
+                WHERE callback = clean_name;
             END IF;
         END LOOP;
     END;
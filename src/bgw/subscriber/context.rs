@@ -7,7 +7,11 @@ use crate::{
         notification::PgInstanceNotification,
         subscriber::{
             InternalWorkerMessage, NatsConnectionState,
-            pg_api::{CallError, PgInstanceStatus, fetch_status, fetch_subject_with_callbacks},
+            message::{KvWatchEntry, KvWatchKey},
+            pg_api::{
+                CallError, PgInstanceStatus, fetch_kv_watches, fetch_status,
+                fetch_subject_with_callbacks,
+            },
         },
     },
     config::Config,
@@ -45,7 +49,11 @@ impl SubscriberContext {
         }
     }
 
-    pub fn check_migration(&mut self, subscriptions_table_name: &str) -> anyhow::Result<()> {
+    pub fn check_migration(
+        &mut self,
+        subscriptions_table_name: &str,
+        kv_watches_table_name: &str,
+    ) -> anyhow::Result<()> {
         #[cfg(not(feature = "pg_test"))]
         let state = BackgroundWorker::transaction(fetch_status);
 
@@ -55,13 +63,14 @@ impl SubscriberContext {
         match (self.status, state) {
             (PgInstanceStatus::Master, PgInstanceStatus::Replica) => {
                 self.status = PgInstanceStatus::Replica;
-                let _ = self.nats.unsubscribe_all();
+                self.nats.unsubscribe_all();
+                let _ = self.nats.unwatch_all();
 
                 self.send_notification()?;
             }
             (PgInstanceStatus::Replica, PgInstanceStatus::Master) => {
                 self.status = PgInstanceStatus::Master;
-                self.restore_state(subscriptions_table_name)?;
+                self.restore_state(subscriptions_table_name, kv_watches_table_name)?;
 
                 self.send_notification()?;
             }
@@ -80,16 +89,32 @@ impl SubscriberContext {
         Ok(())
     }
 
-    pub fn restore_state(&mut self, subscriptions_table_name: &str) -> anyhow::Result<()> {
+    pub fn restore_state(
+        &mut self,
+        subscriptions_table_name: &str,
+        kv_watches_table_name: &str,
+    ) -> anyhow::Result<()> {
         let subs = BackgroundWorker::transaction(|| {
             fetch_subject_with_callbacks(subscriptions_table_name)
         })?;
 
-        for (subject, fn_name) in subs {
+        for (subject, fn_name, with_subject) in subs {
             let _ = self.sender.send(InternalWorkerMessage::Subscribe {
                 register: false,
                 subject,
                 fn_name,
+                with_subject,
+            });
+        }
+
+        let watches = BackgroundWorker::transaction(|| fetch_kv_watches(kv_watches_table_name))?;
+
+        for (bucket, key_pattern, fn_name) in watches {
+            let _ = self.sender.send(InternalWorkerMessage::KvWatch {
+                register: false,
+                bucket,
+                key_pattern,
+                fn_name,
             });
         }
 
@@ -104,27 +129,72 @@ impl SubscriberContext {
         self.status == PgInstanceStatus::Replica
     }
 
-    pub fn handle_subscribe(&mut self, subject: Arc<str>, fn_name: Arc<str>) {
-        self.nats
-            .subscribe(subject, fn_name, &self.rt, self.sender.clone());
+    pub fn handle_subscribe(&mut self, subject: Arc<str>, fn_name: Arc<str>, with_subject: bool) {
+        self.nats.subscribe(
+            subject,
+            fn_name,
+            with_subject,
+            &self.rt,
+            self.sender.clone(),
+        );
     }
 
     pub fn handle_unsubscribe(&mut self, subject: Arc<str>, fn_name: Arc<str>) {
-        self.nats.unsubscribe(subject, fn_name);
+        self.nats
+            .unsubscribe(subject, fn_name, &self.rt, self.sender.clone());
     }
 
     pub fn handle_unsubscribe_subject(&mut self, subject: &str) {
-        self.nats.unsubscribe_subject(subject);
+        self.nats
+            .unsubscribe_subject(subject, &self.rt, self.sender.clone());
+    }
+
+    pub fn handle_subscription_ready(&mut self, subscription: u64) {
+        self.nats
+            .subscription_ready(subscription, &self.rt, self.sender.clone());
     }
 
     pub fn handle_callback(
         &mut self,
+        subscription: u64,
         subject: &str,
         data: Arc<[u8]>,
         db_name: &str,
-        callback: impl Fn(&str, &[u8]) -> Result<(), CallError>,
+        callback: impl Fn(&str, bool, &str, &[u8]) -> Result<(), CallError>,
     ) {
-        self.nats.run_callbacks(subject, db_name, data, callback);
+        self.nats.run_callbacks(
+            subscription,
+            subject,
+            db_name,
+            data,
+            &self.rt,
+            self.sender.clone(),
+            callback,
+        );
+    }
+
+    pub fn handle_kv_watch(&mut self, watch: KvWatchKey, fn_name: Arc<str>) {
+        self.nats
+            .watch(watch, fn_name, &self.rt, self.sender.clone());
+    }
+
+    pub fn handle_kv_unwatch(&mut self, watch: KvWatchKey, fn_name: Arc<str>) {
+        self.nats.unwatch(watch, fn_name);
+    }
+
+    pub fn handle_kv_watch_failed(&mut self, watch: &KvWatchKey) {
+        self.nats.unwatch_key(watch);
+    }
+
+    pub fn handle_kv_callback(
+        &mut self,
+        watch: &KvWatchKey,
+        entry: &KvWatchEntry,
+        db_name: &str,
+        callback: impl Fn(&str, &KvWatchEntry) -> Result<(), CallError>,
+    ) {
+        self.nats
+            .run_watch_callbacks(watch, db_name, entry, callback);
     }
 
     pub fn send_notification(&self) -> anyhow::Result<()> {
@@ -12,11 +12,22 @@ pub enum SubscriberMessage {
     Subscribe {
         subject: String,
         fn_name: String,
+        with_subject: bool,
     },
     Unsubscribe {
         subject: String,
         fn_name: String,
     },
+    KvWatch {
+        bucket: String,
+        key_pattern: String,
+        fn_name: String,
+    },
+    KvUnwatch {
+        bucket: String,
+        key_pattern: String,
+        fn_name: String,
+    },
     #[cfg(any(test, feature = "pg_test"))]
     ChangeStatus {
         is_master: bool,
@@ -28,17 +39,53 @@ pub(super) enum InternalWorkerMessage {
         register: bool,
         subject: String,
         fn_name: String,
+        with_subject: bool,
     },
     Unsubscribe {
         subject: Arc<str>,
         fn_name: Arc<str>,
     },
     CallbackCall {
+        subscription: u64,
         subject: Arc<str>,
         data: Arc<[u8]>,
     },
+    SubscriptionReady {
+        subscription: u64,
+    },
     UnsubscribeSubject {
         subject: Arc<str>,
         reason: String,
     },
+    KvWatch {
+        register: bool,
+        bucket: String,
+        key_pattern: String,
+        fn_name: String,
+    },
+    KvUnwatch {
+        watch: KvWatchKey,
+        fn_name: Arc<str>,
+    },
+    KvCallbackCall {
+        watch: KvWatchKey,
+        entry: KvWatchEntry,
+    },
+    KvWatchFailed {
+        watch: KvWatchKey,
+        reason: String,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct KvWatchKey {
+    pub bucket: Arc<str>,
+    pub key_pattern: Arc<str>,
+}
+
+pub struct KvWatchEntry {
+    pub key: String,
+    pub value: Option<Arc<[u8]>>,
+    pub revision: i64,
+    pub operation: &'static str,
 }
@@ -1,5 +1,6 @@
 mod context;
 mod nats;
+mod trie;
 
 pub mod message;
 pub mod pg_api;
@@ -27,11 +28,14 @@ use crate::{
         ring_queue::RingQueue,
         subscriber::{
             context::SubscriberContext,
-            message::{InternalWorkerMessage, SubscriberMessage},
+            message::{InternalWorkerMessage, KvWatchKey, SubscriberMessage},
             nats::NatsConnectionState,
-            pg_api::{call_function, delete_subject_callback, insert_subject_callback},
+            pg_api::{
+                call_function, call_kv_watch_function, delete_kv_watch, delete_subject_callback,
+                insert_kv_watch, insert_subject_callback,
+            },
         },
-        LAUNCHER_MESSAGE_BUS, SUBSCRIPTIONS_TABLE_NAME,
+        KV_WATCHES_TABLE_NAME, LAUNCHER_MESSAGE_BUS, SUBSCRIPTIONS_TABLE_NAME,
     },
     config::{fetch_config, fetch_fdw_server_name},
     constants::{EXTENSION_NAME, FDW_EXTENSION_NAME},
@@ -58,6 +62,7 @@ pub extern "C-unwind" fn background_worker_subscriber_entry_point(arg: sys::Datu
     if let Err(err) = background_worker_subscriber_main(
         &LAUNCHER_MESSAGE_BUS,
         SUBSCRIPTIONS_TABLE_NAME,
+        KV_WATCHES_TABLE_NAME,
         FDW_EXTENSION_NAME,
         db_oid,
         dsmh,
@@ -72,6 +77,7 @@ pub extern "C-unwind" fn background_worker_subscriber_entry_point(arg: sys::Datu
 pub fn background_worker_subscriber_main<const N: usize>(
     launcher_bus: &PgLwLock<RingQueue<N>>,
     sub_table_name: &str,
+    watch_table_name: &str,
     fdw_extension_name: &str,
     db_oid: sys::Oid,
     dsmh: DsmHandle,
@@ -95,6 +101,7 @@ pub fn background_worker_subscriber_main<const N: usize>(
     let result = background_worker_subscriber_main_internal(
         launcher_bus,
         sub_table_name,
+        watch_table_name,
         fdw_extension_name,
         db_oid.to_u32(),
         &db_name,
@@ -116,6 +123,7 @@ pub fn background_worker_subscriber_main<const N: usize>(
 fn background_worker_subscriber_main_internal<const N: usize>(
     launcher_bus: &PgLwLock<RingQueue<N>>,
     sub_table_name: &str,
+    watch_table_name: &str,
     fdw_extension_name: &str,
     db_oid: u32,
     db_name: &str,
@@ -164,7 +172,7 @@ fn background_worker_subscriber_main_internal<const N: usize>(
     if ctx.is_master() {
         log!(context = db_name, "Restoring previous subscription state");
 
-        if let Err(error) = ctx.restore_state(sub_table_name) {
+        if let Err(error) = ctx.restore_state(sub_table_name, watch_table_name) {
             warn!(
                 context = db_name,
                 "Failed to restore subscription state: {}", error
@@ -187,7 +195,7 @@ fn background_worker_subscriber_main_internal<const N: usize>(
             _ => {}
         }
 
-        if let Err(err) = ctx.check_migration(sub_table_name) {
+        if let Err(err) = ctx.check_migration(sub_table_name, watch_table_name) {
             warn!(context = db_name, "Migration check failed: {}", err);
         }
 
@@ -214,7 +222,7 @@ fn background_worker_subscriber_main_internal<const N: usize>(
                 continue;
             }
 
-            handle_internal_message(&mut ctx, message, sub_table_name, db_name);
+            handle_internal_message(&mut ctx, message, sub_table_name, watch_table_name, db_name);
         }
     }
 
@@ -261,7 +269,11 @@ fn handle_message_from_shared_queue(
                 );
             }
         }
-        SubscriberMessage::Subscribe { subject, fn_name } => {
+        SubscriberMessage::Subscribe {
+            subject,
+            fn_name,
+            with_subject,
+        } => {
             debug!(
                 context = db_name,
                 "Handling Subscribe for subject '{}', fn '{}'", subject, fn_name
@@ -271,6 +283,7 @@ fn handle_message_from_shared_queue(
                 register: true,
                 subject: subject.to_string(),
                 fn_name: fn_name.to_string(),
+                with_subject,
             });
         }
         SubscriberMessage::Unsubscribe { subject, fn_name } => {
@@ -284,6 +297,47 @@ fn handle_message_from_shared_queue(
                 fn_name: Arc::from(fn_name.as_str()),
             });
         }
+        SubscriberMessage::KvWatch {
+            bucket,
+            key_pattern,
+            fn_name,
+        } => {
+            debug!(
+                context = db_name,
+                "Handling KvWatch for bucket '{}', key pattern '{}', fn '{}'",
+                bucket,
+                key_pattern,
+                fn_name
+            );
+
+            let _ = sender.send(InternalWorkerMessage::KvWatch {
+                register: true,
+                bucket,
+                key_pattern,
+                fn_name,
+            });
+        }
+        SubscriberMessage::KvUnwatch {
+            bucket,
+            key_pattern,
+            fn_name,
+        } => {
+            debug!(
+                context = db_name,
+                "Handling KvUnwatch for bucket '{}', key pattern '{}', fn '{}'",
+                bucket,
+                key_pattern,
+                fn_name
+            );
+
+            let _ = sender.send(InternalWorkerMessage::KvUnwatch {
+                watch: KvWatchKey {
+                    bucket: Arc::from(bucket),
+                    key_pattern: Arc::from(key_pattern),
+                },
+                fn_name: Arc::from(fn_name),
+            });
+        }
         #[cfg(any(test, feature = "pg_test"))]
         SubscriberMessage::ChangeStatus { is_master } => {
             if is_master {
@@ -299,6 +353,7 @@ fn handle_internal_message(
     ctx: &mut SubscriberContext,
     msg: InternalWorkerMessage,
     subscriptions_table_name: &str,
+    kv_watches_table_name: &str,
     db_name: &str,
 ) {
     match msg {
@@ -306,6 +361,7 @@ fn handle_internal_message(
             register,
             subject,
             fn_name,
+            with_subject,
         } => {
             debug!(
                 context = db_name,
@@ -314,7 +370,12 @@ fn handle_internal_message(
 
             if register {
                 if let Err(error) = BackgroundWorker::transaction(|| {
-                    insert_subject_callback(subscriptions_table_name, &subject, &fn_name)
+                    insert_subject_callback(
+                        subscriptions_table_name,
+                        &subject,
+                        &fn_name,
+                        with_subject,
+                    )
                 }) {
                     warn!(
                         context = db_name,
@@ -331,7 +392,7 @@ fn handle_internal_message(
                 }
             }
 
-            ctx.handle_subscribe(Arc::from(subject), Arc::from(fn_name));
+            ctx.handle_subscribe(Arc::from(subject), Arc::from(fn_name), with_subject);
         }
         InternalWorkerMessage::Unsubscribe { subject, fn_name } => {
             debug!(
@@ -358,15 +419,35 @@ fn handle_internal_message(
 
             ctx.handle_unsubscribe(subject, fn_name);
         }
-        InternalWorkerMessage::CallbackCall { subject, data } => {
+        InternalWorkerMessage::CallbackCall {
+            subscription,
+            subject,
+            data,
+        } => {
             debug!(
                 context = db_name,
                 "Dispatching callbacks for subject '{}'", subject
             );
 
-            ctx.handle_callback(&subject, data, db_name, |callback, data| {
-                BackgroundWorker::transaction(|| call_function(callback, data))
-            });
+            ctx.handle_callback(
+                subscription,
+                &subject,
+                data,
+                db_name,
+                |callback, with_subject, subject, data| {
+                    BackgroundWorker::transaction(|| {
+                        call_function(callback, with_subject, subject, data)
+                    })
+                },
+            );
+        }
+        InternalWorkerMessage::SubscriptionReady { subscription } => {
+            debug!(
+                context = db_name,
+                "NATS subscription {} is confirmed by the server", subscription
+            );
+
+            ctx.handle_subscription_ready(subscription);
         }
         InternalWorkerMessage::UnsubscribeSubject { subject, reason } => {
             warn!(
@@ -375,6 +456,116 @@ fn handle_internal_message(
             );
             ctx.handle_unsubscribe_subject(&subject)
         }
+        InternalWorkerMessage::KvWatch {
+            register,
+            bucket,
+            key_pattern,
+            fn_name,
+        } => {
+            debug!(
+                context = db_name,
+                "Received KV watch request: bucket='{}', key_pattern='{}', fn='{}'",
+                bucket,
+                key_pattern,
+                fn_name
+            );
+
+            if register {
+                if let Err(error) = BackgroundWorker::transaction(|| {
+                    insert_kv_watch(kv_watches_table_name, &bucket, &key_pattern, &fn_name)
+                }) {
+                    warn!(
+                        context = db_name,
+                        "Failed to register KV watch in catalog: bucket='{}', key_pattern='{}', callback='{}': {}",
+                        bucket,
+                        key_pattern,
+                        fn_name,
+                        error
+                    );
+                } else {
+                    debug!(
+                        context = db_name,
+                        "Inserted KV watch: bucket='{}', key_pattern='{}', callback='{}'",
+                        bucket,
+                        key_pattern,
+                        fn_name
+                    );
+                }
+            }
+
+            ctx.handle_kv_watch(
+                KvWatchKey {
+                    bucket: Arc::from(bucket),
+                    key_pattern: Arc::from(key_pattern),
+                },
+                Arc::from(fn_name),
+            );
+        }
+        InternalWorkerMessage::KvUnwatch { watch, fn_name } => {
+            debug!(
+                context = db_name,
+                "Received KV unwatch request: bucket='{}', key_pattern='{}', fn='{}'",
+                watch.bucket,
+                watch.key_pattern,
+                fn_name
+            );
+
+            if let Err(error) = BackgroundWorker::transaction(|| {
+                delete_kv_watch(
+                    kv_watches_table_name,
+                    &watch.bucket,
+                    &watch.key_pattern,
+                    &fn_name,
+                )
+            }) {
+                warn!(
+                    context = db_name,
+                    "Failed to remove KV watch from catalog: bucket='{}', key_pattern='{}', callback='{}': {}",
+                    watch.bucket,
+                    watch.key_pattern,
+                    fn_name,
+                    error
+                );
+            } else {
+                debug!(
+                    context = db_name,
+                    "Deleted KV watch: bucket='{}', key_pattern='{}', callback='{}'",
+                    watch.bucket,
+                    watch.key_pattern,
+                    fn_name
+                );
+            }
+
+            ctx.handle_kv_unwatch(watch, fn_name);
+        }
+        InternalWorkerMessage::KvCallbackCall { watch, entry } => {
+            debug!(
+                context = db_name,
+                "Dispatching KV watch callbacks for bucket '{}', key '{}'", watch.bucket, entry.key
+            );
+
+            ctx.handle_kv_callback(&watch, &entry, db_name, |callback, entry| {
+                BackgroundWorker::transaction(|| {
+                    call_kv_watch_function(
+                        callback,
+                        &entry.key,
+                        entry.value.as_deref(),
+                        entry.revision,
+                        entry.operation,
+                    )
+                })
+            });
+        }
+        InternalWorkerMessage::KvWatchFailed { watch, reason } => {
+            warn!(
+                context = db_name,
+                "Stopping KV watch on bucket '{}', key_pattern '{}' due to: {}",
+                watch.bucket,
+                watch.key_pattern,
+                reason
+            );
+            ctx.handle_kv_watch_failed(&watch)
+        }
     }
 }
 
@@ -7,7 +7,12 @@ use tokio::task::JoinHandle;
 use tokio_stream::StreamExt;
 
 use crate::{
-    bgw::subscriber::{pg_api::CallError, InternalWorkerMessage},
+    bgw::subscriber::{
+        message::{KvWatchEntry, KvWatchKey},
+        pg_api::CallError,
+        trie::{pattern_covers, SubjectTrie},
+        InternalWorkerMessage,
+    },
     config::{NatsConnectionOptions, NatsTlsOptions},
     warn,
 };
@@ -17,9 +22,27 @@ pub(super) struct NatsSubscription {
     funcs: HashSet<Arc<str>>,
 }
 
+/// NATS subscription on a subject, identified by an id unique for the worker.
+struct SubjectSubscription {
+    id: u64,
+    handler: JoinHandle<()>,
+    /// Whether the server confirmed the subscription, so that it receives every message
+    /// published from then on.
+    ready: bool,
+}
+
 pub(super) struct NatsConnectionState {
     client: async_nats::Client,
-    subscriptions: HashMap<Arc<str>, NatsSubscription>,
+    /// Callbacks of every subscribed subject pattern, with whether they take the subject.
+    subscriptions: SubjectTrie<HashMap<Arc<str>, bool>>,
+    /// NATS subscriptions, one for each subscribed pattern not covered by another one, and
+    /// the ones they replace until they are ready.
+    subjects: HashMap<Arc<str>, SubjectSubscription>,
+    /// NATS subscription whose messages are passed to the callbacks of each pattern, so that
+    /// a message received by several overlapping subscriptions runs every callback once.
+    owners: HashMap<Arc<str>, u64>,
+    next_subscription_id: u64,
+    watches: HashMap<KvWatchKey, NatsSubscription>,
 }
 
 impl NatsConnectionState {
@@ -27,7 +50,11 @@ impl NatsConnectionState {
         let client = Self::connect_nats(config).await?;
         Ok(Self {
             client,
-            subscriptions: HashMap::new(),
+            subscriptions: SubjectTrie::new(),
+            subjects: HashMap::new(),
+            owners: HashMap::new(),
+            next_subscription_id: 0,
+            watches: HashMap::new(),
         })
     }
 
@@ -35,21 +62,166 @@ impl NatsConnectionState {
         &mut self,
         subject: Arc<str>,
         fn_name: Arc<str>,
+        with_subject: bool,
+        rt: &tokio::runtime::Runtime,
+        sender: Sender<InternalWorkerMessage>,
+    ) {
+        let _ = self
+            .subscriptions
+            .get_or_insert_default(subject)
+            .insert(fn_name, with_subject);
+
+        self.sync_subjects(rt, sender);
+    }
+
+    pub(super) fn unsubscribe(
+        &mut self,
+        subject: Arc<str>,
+        fn_name: Arc<str>,
+        rt: &tokio::runtime::Runtime,
+        sender: Sender<InternalWorkerMessage>,
+    ) {
+        if let Some(funcs) = self.subscriptions.get_mut(&subject) {
+            let _ = funcs.remove(&fn_name);
+
+            if funcs.is_empty() {
+                let _ = self.subscriptions.remove(&subject);
+                self.sync_subjects(rt, sender);
+            }
+        }
+    }
+
+    pub(super) fn unsubscribe_subject(
+        &mut self,
+        subject: &str,
+        rt: &tokio::runtime::Runtime,
+        sender: Sender<InternalWorkerMessage>,
+    ) {
+        if self.subscriptions.remove(subject).is_some() {
+            self.sync_subjects(rt, sender);
+        }
+    }
+
+    pub(super) fn unsubscribe_all(&mut self) {
+        self.subscriptions = SubjectTrie::new();
+        self.owners.clear();
+
+        for (_, subscription) in self.subjects.drain() {
+            subscription.handler.abort();
+        }
+    }
+
+    /// Marks the NATS subscription `subscription` as confirmed by the server and moves the
+    /// patterns it covers to it.
+    pub(super) fn subscription_ready(
+        &mut self,
+        subscription: u64,
+        rt: &tokio::runtime::Runtime,
+        sender: Sender<InternalWorkerMessage>,
+    ) {
+        if let Some(s) = self
+            .subjects
+            .values_mut()
+            .find(|s| s.id == subscription && !s.ready)
+        {
+            s.ready = true;
+            self.sync_subjects(rt, sender);
+        }
+    }
+
+    /// Opens a NATS subscription for every subscribed pattern that is not covered by
+    /// another pattern, and closes the subscriptions that are no longer needed, so that
+    /// overlapping patterns like `orders.>` and `orders.eu.created` share one subscription.
+    ///
+    /// Each pattern is then assigned to a single subscription covering it. Patterns that only
+    /// partially overlap, like `orders.*.created` and `orders.eu.>`, keep a subscription each,
+    /// and a message matching both is received twice but runs the callbacks of each pattern
+    /// only for the subscription owning it.
+    ///
+    /// A pattern moves to a new subscription only once the server confirmed it, and the
+    /// subscription it leaves is closed only after that, so no message is lost while patterns
+    /// are added or removed. A message published during the move may run the callbacks of the
+    /// pattern twice, once for each subscription.
+    fn sync_subjects(
+        &mut self,
         rt: &tokio::runtime::Runtime,
         sender: Sender<InternalWorkerMessage>,
     ) {
-        match self.subscriptions.entry(subject.clone()) {
-            // Subject already exists; update or add the function handler
-            Entry::Occupied(mut s) => {
-                let _ = s.get_mut().funcs.insert(fn_name);
+        let mut patterns = self.subscriptions.patterns();
+        patterns.sort();
+
+        let required: Vec<_> = patterns
+            .iter()
+            .filter(|pattern| {
+                !patterns
+                    .iter()
+                    .any(|other| other != *pattern && pattern_covers(other, pattern))
+            })
+            .collect();
+
+        for subject in &required {
+            if let Entry::Vacant(e) = self.subjects.entry(Arc::clone(subject)) {
+                self.next_subscription_id += 1;
+                let id = self.next_subscription_id;
+
+                let _ = e.insert(SubjectSubscription {
+                    id,
+                    handler: Self::spawn_subscription_task(
+                        self.client.clone(),
+                        rt,
+                        sender.clone(),
+                        Arc::clone(subject),
+                        id,
+                    ),
+                    ready: false,
+                });
             }
-            // First time subscribing to this subject
-            Entry::Vacant(se) => {
-                // Spawn a new handler task for the function
-                let handler =
-                    Self::spawn_subscription_task(self.client.clone(), rt, sender, subject.clone());
+        }
 
-                let _ = se.insert(NatsSubscription {
+        let subjects = &self.subjects;
+        let owners = &self.owners;
+
+        let owners: HashMap<_, _> = patterns
+            .iter()
+            .filter_map(|pattern| {
+                let owner = required
+                    .iter()
+                    .filter(|subject| pattern_covers(subject, pattern))
+                    .filter_map(|subject| subjects.get(*subject))
+                    .find(|subscription| subscription.ready)
+                    .map(|subscription| subscription.id)
+                    .or_else(|| owners.get(pattern).copied())?;
+                Some((Arc::clone(pattern), owner))
+            })
+            .collect();
+
+        self.subjects.retain(|subject, subscription| {
+            let keep =
+                required.contains(&subject) || owners.values().any(|id| *id == subscription.id);
+            if !keep {
+                subscription.handler.abort();
+            }
+            keep
+        });
+
+        self.owners = owners;
+    }
+
+    pub(super) fn watch(
+        &mut self,
+        watch: KvWatchKey,
+        fn_name: Arc<str>,
+        rt: &tokio::runtime::Runtime,
+        sender: Sender<InternalWorkerMessage>,
+    ) {
+        match self.watches.entry(watch.clone()) {
+            Entry::Occupied(mut w) => {
+                let _ = w.get_mut().funcs.insert(fn_name);
+            }
+            Entry::Vacant(we) => {
+                let handler = Self::spawn_watch_task(self.client.clone(), rt, sender, watch);
+
+                let _ = we.insert(NatsSubscription {
                     handler,
                     funcs: HashSet::from([fn_name]),
                 });
@@ -57,51 +229,67 @@ impl NatsConnectionState {
         }
     }
 
-    pub(super) fn unsubscribe(&mut self, subject: Arc<str>, fn_name: Arc<str>) {
-        if let Entry::Occupied(mut e) = self.subscriptions.entry(subject.clone()) {
+    pub(super) fn unwatch(&mut self, watch: KvWatchKey, fn_name: Arc<str>) {
+        if let Entry::Occupied(mut e) = self.watches.entry(watch) {
             let _ = e.get_mut().funcs.remove(&fn_name);
 
             if e.get().funcs.is_empty() {
-                let sub = e.remove();
-                sub.handler.abort();
+                let w = e.remove();
+                w.handler.abort();
             }
         }
     }
 
-    pub(super) fn unsubscribe_subject(&mut self, subject: &str) {
-        if let Some(sub) = self.subscriptions.remove(subject) {
-            sub.handler.abort();
+    pub(super) fn unwatch_key(&mut self, watch: &KvWatchKey) {
+        if let Some(w) = self.watches.remove(watch) {
+            w.handler.abort();
         }
     }
 
-    pub(super) fn unsubscribe_all(&mut self) -> HashMap<Arc<str>, NatsSubscription> {
-        let subs = std::mem::take(&mut self.subscriptions);
-        for sub in subs.values() {
-            sub.handler.abort();
+    pub(super) fn unwatch_all(&mut self) -> HashMap<KvWatchKey, NatsSubscription> {
+        let watches = std::mem::take(&mut self.watches);
+        for w in watches.values() {
+            w.handler.abort();
         }
 
-        subs
+        watches
     }
 
+    /// Calls the callbacks of every subscribed pattern matching the subject of a message
+    /// received by the NATS subscription on `subscription` and owned by it.
     pub(super) fn run_callbacks(
         &mut self,
+        subscription: u64,
         subject: &str,
         db_name: &str,
         data: Arc<[u8]>,
-        callback: impl Fn(&str, &[u8]) -> Result<(), CallError>,
+        rt: &tokio::runtime::Runtime,
+        sender: Sender<InternalWorkerMessage>,
+        callback: impl Fn(&str, bool, &str, &[u8]) -> Result<(), CallError>,
     ) {
-        if let Some(subject) = self.subscriptions.get_mut(subject) {
-            subject.funcs.retain(|fnname| {
-                if let Err(err) = callback(fnname, &data) {
-                    match err {
-                        CallError::NotFound => {
+        let mut unused = Vec::new();
+        let owners = &self.owners;
+
+        self.subscriptions
+            .for_each_match(subject, |pattern, funcs| {
+                if owners
+                    .get(pattern)
+                    .is_none_or(|owner| *owner != subscription)
+                {
+                    return;
+                }
+
+                funcs.retain(|fnname, with_subject| {
+                    match callback(fnname, *with_subject, subject, &data) {
+                        Ok(()) => true,
+                        Err(CallError::NotFound) => {
                             warn!(
                                 context = db_name,
                                 "Function '{fnname}' was dropped, unregistering...",
                             );
                             false
                         }
-                        CallError::Other(err) => {
+                        Err(CallError::Other(err)) => {
                             warn!(
                                 context = db_name,
                                 "Error while calling subscriber function '{fnname}': {err:?}",
@@ -109,7 +297,44 @@ impl NatsConnectionState {
                             true
                         }
                     }
-                } else {
+                });
+
+                if funcs.is_empty() {
+                    unused.push(Arc::clone(pattern));
+                }
+            });
+
+        if !unused.is_empty() {
+            for pattern in unused {
+                let _ = self.subscriptions.remove(&pattern);
+            }
+
+            self.sync_subjects(rt, sender);
+        }
+    }
+
+    pub(super) fn run_watch_callbacks(
+        &mut self,
+        watch: &KvWatchKey,
+        db_name: &str,
+        entry: &KvWatchEntry,
+        callback: impl Fn(&str, &KvWatchEntry) -> Result<(), CallError>,
+    ) {
+        if let Some(w) = self.watches.get_mut(watch) {
+            w.funcs.retain(|fnname| match callback(fnname, entry) {
+                Ok(()) => true,
+                Err(CallError::NotFound) => {
+                    warn!(
+                        context = db_name,
+                        "Function '{fnname}' was dropped, unregistering...",
+                    );
+                    false
+                }
+                Err(CallError::Other(err)) => {
+                    warn!(
+                        context = db_name,
+                        "Error while calling KV watch function '{fnname}': {err:?}",
+                    );
                     true
                 }
             });
@@ -124,15 +349,25 @@ impl NatsConnectionState {
     ) -> anyhow::Result<()> {
         let client = rt.block_on(Self::connect_nats(config))?;
 
-        let mut subs = self.unsubscribe_all();
+        for (subject, subscription) in &mut self.subjects {
+            subscription.handler.abort();
+            subscription.handler = Self::spawn_subscription_task(
+                client.clone(),
+                rt,
+                sender.clone(),
+                subject.clone(),
+                subscription.id,
+            );
+        }
+
+        let mut watches = self.unwatch_all();
 
-        for (subject, sub) in &mut subs {
-            sub.handler =
-                Self::spawn_subscription_task(client.clone(), rt, sender.clone(), subject.clone());
+        for (watch, w) in &mut watches {
+            w.handler = Self::spawn_watch_task(client.clone(), rt, sender.clone(), watch.clone());
         }
 
         self.client = client;
-        self.subscriptions = subs;
+        self.watches = watches;
 
         Ok(())
     }
@@ -178,13 +413,25 @@ impl NatsConnectionState {
         rt: &tokio::runtime::Runtime,
         sender: Sender<InternalWorkerMessage>,
         subject: Arc<str>,
+        id: u64,
     ) -> JoinHandle<()> {
         rt.spawn(async move {
-            match client.subscribe(subject.to_string()).await {
+            let subscribed = async {
+                let sub = client.subscribe(subject.to_string()).await?;
+                // The server answers the flush only after it has processed the subscription.
+                client.flush().await?;
+                anyhow::Ok(sub)
+            };
+
+            match subscribed.await {
                 Ok(mut sub) => {
+                    let _ =
+                        sender.send(InternalWorkerMessage::SubscriptionReady { subscription: id });
+
                     while let Some(msg) = sub.next().await {
                         let _ = sender.send(InternalWorkerMessage::CallbackCall {
-                            subject: subject.clone(),
+                            subscription: id,
+                            subject: Arc::from(msg.subject.as_str()),
                             data: Arc::from(msg.payload.to_vec()),
                         });
                     }
@@ -198,10 +445,61 @@ impl NatsConnectionState {
             }
         })
     }
+
+    fn spawn_watch_task(
+        client: async_nats::Client,
+        rt: &tokio::runtime::Runtime,
+        sender: Sender<InternalWorkerMessage>,
+        watch: KvWatchKey,
+    ) -> JoinHandle<()> {
+        use async_nats::jetstream::kv::Operation;
+
+        rt.spawn(async move {
+            let watcher = async {
+                let store = async_nats::jetstream::new(client)
+                    .get_key_value(watch.bucket.to_string())
+                    .await?;
+                anyhow::Ok(store.watch(watch.key_pattern.to_string()).await?)
+            };
+
+            let reason = match watcher.await {
+                Ok(mut watcher) => loop {
+                    let entry = match watcher.next().await {
+                        Some(Ok(entry)) => entry,
+                        // The watcher recreates its consumer on its own, so an error
+                        // reaching this point means the watch cannot go on.
+                        Some(Err(err)) => break err.to_string(),
+                        None => break "watch stream ended".to_string(),
+                    };
+
+                    let operation = match entry.operation {
+                        Operation::Put => "put",
+                        Operation::Delete => "delete",
+                        Operation::Purge => "purge",
+                    };
+
+                    let _ = sender.send(InternalWorkerMessage::KvCallbackCall {
+                        watch: watch.clone(),
+                        entry: KvWatchEntry {
+                            value: (entry.operation == Operation::Put)
+                                .then(|| Arc::from(entry.value.to_vec())),
+                            key: entry.key,
+                            revision: entry.revision.try_into().unwrap_or(i64::MAX),
+                            operation,
+                        },
+                    });
+                },
+                Err(err) => err.to_string(),
+            };
+
+            let _ = sender.send(InternalWorkerMessage::KvWatchFailed { watch, reason });
+        })
+    }
 }
 
 impl Drop for NatsConnectionState {
     fn drop(&mut self) {
-        let _ = self.unsubscribe_all();
+        self.unsubscribe_all();
+        let _ = self.unwatch_all();
     }
 }
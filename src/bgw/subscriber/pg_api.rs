@@ -1,4 +1,4 @@
-use pgrx::{PgSqlErrorCode, PgTryBuilder, Spi};
+use pgrx::{datum::DatumWithOid, PgSqlErrorCode, PgTryBuilder, Spi};
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
@@ -24,19 +24,24 @@ pub fn fetch_status() -> PgInstanceStatus {
     }
 }
 
-pub fn fetch_subject_with_callbacks(table_name: &str) -> anyhow::Result<Vec<(String, String)>> {
+pub fn fetch_subject_with_callbacks(
+    table_name: &str,
+) -> anyhow::Result<Vec<(String, String, bool)>> {
     PgTryBuilder::new(|| {
         Spi::connect_mut(|client| {
-            let sql = format!("SELECT subject, callback FROM {table_name}");
+            let sql = format!("SELECT subject, callback, with_subject FROM {table_name}");
             let tuples = client.select(&sql, None, &[])?;
-            let subject_callbacks: Vec<(String, String)> = tuples
+            let subject_callbacks: Vec<(String, String, bool)> = tuples
                 .into_iter()
                 .filter_map(|tuple| {
                     let subject = tuple.get_by_name::<String, _>("subject");
                     let fn_oid = tuple.get_by_name::<String, _>("callback");
+                    let with_subject = tuple.get_by_name::<bool, _>("with_subject");
 
-                    match (subject, fn_oid) {
-                        (Ok(Some(subject)), Ok(Some(fn_oid))) => Some((subject, fn_oid)),
+                    match (subject, fn_oid, with_subject) {
+                        (Ok(Some(subject)), Ok(Some(fn_oid)), Ok(Some(with_subject))) => {
+                            Some((subject, fn_oid, with_subject))
+                        }
                         _ => None,
                     }
                 })
@@ -61,11 +66,18 @@ pub fn insert_subject_callback(
     table_name: &str,
     subject: &str,
     fn_name: &str,
+    with_subject: bool,
 ) -> anyhow::Result<()> {
     PgTryBuilder::new(|| {
         Spi::connect_mut(|client| {
-            let sql = format!("INSERT INTO {table_name} VALUES ($1, $2)");
-            let _ = client.update(&sql, None, &[subject.into(), fn_name.into()])?;
+            let sql = format!(
+                "INSERT INTO {table_name} (subject, callback, with_subject) VALUES ($1, $2, $3)"
+            );
+            let _ = client.update(
+                &sql,
+                None,
+                &[subject.into(), fn_name.into(), with_subject.into()],
+            )?;
 
             Ok(())
         })
@@ -107,7 +119,133 @@ pub fn delete_subject_callback(
     .execute()
 }
 
-pub fn call_function(callback: &str, data: &[u8]) -> Result<(), CallError> {
+pub fn fetch_kv_watches(table_name: &str) -> anyhow::Result<Vec<(String, String, String)>> {
+    PgTryBuilder::new(|| {
+        Spi::connect_mut(|client| {
+            let sql = format!("SELECT bucket, key_pattern, callback FROM {table_name}");
+            let tuples = client.select(&sql, None, &[])?;
+            let watches: Vec<(String, String, String)> = tuples
+                .into_iter()
+                .filter_map(|tuple| {
+                    let bucket = tuple.get_by_name::<String, _>("bucket");
+                    let key_pattern = tuple.get_by_name::<String, _>("key_pattern");
+                    let callback = tuple.get_by_name::<String, _>("callback");
+
+                    match (bucket, key_pattern, callback) {
+                        (Ok(Some(bucket)), Ok(Some(key_pattern)), Ok(Some(callback))) => {
+                            Some((bucket, key_pattern, callback))
+                        }
+                        _ => None,
+                    }
+                })
+                .collect();
+
+            Ok(watches)
+        })
+    })
+    .catch_others(|e| match e {
+        pgrx::pg_sys::panic::CaughtError::PostgresError(err) => Err(anyhow::anyhow!(
+            "Code '{}': {}. ({:?})",
+            err.sql_error_code(),
+            err.message(),
+            err.hint()
+        )),
+        _ => Err(anyhow::anyhow!("{:?}", e)),
+    })
+    .execute()
+}
+
+pub fn insert_kv_watch(
+    table_name: &str,
+    bucket: &str,
+    key_pattern: &str,
+    fn_name: &str,
+) -> anyhow::Result<()> {
+    PgTryBuilder::new(|| {
+        Spi::connect_mut(|client| {
+            let sql = format!("INSERT INTO {table_name} VALUES ($1, $2, $3)");
+            let _ = client.update(
+                &sql,
+                None,
+                &[bucket.into(), key_pattern.into(), fn_name.into()],
+            )?;
+
+            Ok(())
+        })
+    })
+    .catch_others(|e| match e {
+        pgrx::pg_sys::panic::CaughtError::PostgresError(err) => Err(anyhow::anyhow!(
+            "Code '{}': {}. ({:?})",
+            err.sql_error_code(),
+            err.message(),
+            err.hint()
+        )),
+        _ => Err(anyhow::anyhow!("{:?}", e)),
+    })
+    .execute()
+}
+
+pub fn delete_kv_watch(
+    table_name: &str,
+    bucket: &str,
+    key_pattern: &str,
+    callback: &str,
+) -> anyhow::Result<()> {
+    PgTryBuilder::new(|| {
+        Spi::connect_mut(|client| {
+            let sql = format!(
+                "DELETE FROM {table_name} WHERE bucket = $1 AND key_pattern = $2 AND callback = $3"
+            );
+            let _ = client.update(
+                &sql,
+                None,
+                &[bucket.into(), key_pattern.into(), callback.into()],
+            )?;
+
+            Ok(())
+        })
+    })
+    .catch_others(|e| match e {
+        pgrx::pg_sys::panic::CaughtError::PostgresError(err) => Err(anyhow::anyhow!(
+            "Code '{}': {}. ({:?})",
+            err.sql_error_code(),
+            err.message(),
+            err.hint()
+        )),
+        _ => Err(anyhow::anyhow!("{:?}", e)),
+    })
+    .execute()
+}
+
+/// Calls a subscription callback with the payload of a message, and with its subject
+/// when the callback was subscribed with a second `text` argument.
+pub fn call_function(
+    callback: &str,
+    with_subject: bool,
+    subject: &str,
+    data: &[u8],
+) -> Result<(), CallError> {
+    if with_subject {
+        call_function_with_args(callback, &[data.into(), subject.into()])
+    } else {
+        call_function_with_args(callback, &[data.into()])
+    }
+}
+
+pub fn call_kv_watch_function(
+    callback: &str,
+    key: &str,
+    value: Option<&[u8]>,
+    revision: i64,
+    operation: &str,
+) -> Result<(), CallError> {
+    call_function_with_args(
+        callback,
+        &[key.into(), value.into(), revision.into(), operation.into()],
+    )
+}
+
+fn check_callback_name(callback: &str) -> Result<(), CallError> {
     if !callback
         .chars()
         .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
@@ -117,11 +255,21 @@ pub fn call_function(callback: &str, data: &[u8]) -> Result<(), CallError> {
         )));
     }
 
+    Ok(())
+}
+
+fn call_function_with_args(callback: &str, args: &[DatumWithOid]) -> Result<(), CallError> {
+    check_callback_name(callback)?;
+
     PgTryBuilder::new(|| {
         Spi::connect_mut(|client| {
-            let sql = format!("SELECT {callback}($1)");
+            let params = (1..=args.len())
+                .map(|n| format!("${n}"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let sql = format!("SELECT {callback}({params})");
             let _ = client
-                .update(&sql, None, &[data.into()])
+                .update(&sql, None, args)
                 .map_err(|err| CallError::Other(err.into()))?;
             Ok(())
         })
@@ -0,0 +1,232 @@
+//! Subject trie routing the messages of the subscriber to the matching subscriptions.
+
+use std::{collections::HashMap, sync::Arc};
+
+use crate::subject::{FULL_WILDCARD, SINGLE_WILDCARD};
+
+/// Returns whether every subject matching `other` also matches `pattern`, that is whether
+/// a subscription on `pattern` receives all the messages of a subscription on `other`.
+pub fn pattern_covers(pattern: &str, other: &str) -> bool {
+    let mut other_tokens = other.split('.');
+
+    for pattern_token in pattern.split('.') {
+        match (pattern_token, other_tokens.next()) {
+            (FULL_WILDCARD, Some(_)) => return true,
+            (SINGLE_WILDCARD, Some(other_token)) if other_token != FULL_WILDCARD => {}
+            (pattern_token, Some(other_token)) if pattern_token == other_token => {}
+            _ => return false,
+        }
+    }
+
+    other_tokens.next().is_none()
+}
+
+/// Map from subject patterns to values, which finds the values of every pattern matching
+/// a subject in a single walk over the subject tokens.
+pub struct SubjectTrie<T> {
+    root: TrieNode<T>,
+}
+
+struct TrieNode<T> {
+    children: HashMap<Box<str>, TrieNode<T>>,
+    value: Option<(Arc<str>, T)>,
+}
+
+impl<T> SubjectTrie<T> {
+    pub fn new() -> Self {
+        Self {
+            root: TrieNode::new(),
+        }
+    }
+
+    /// Returns the value of `pattern`, inserting the default value if there is none.
+    pub fn get_or_insert_default(&mut self, pattern: Arc<str>) -> &mut T
+    where
+        T: Default,
+    {
+        let mut node = &mut self.root;
+
+        for token in pattern.split('.') {
+            node = node
+                .children
+                .entry(token.into())
+                .or_insert_with(TrieNode::new);
+        }
+
+        let (_, value) = node.value.get_or_insert_with(|| (pattern, T::default()));
+        value
+    }
+
+    /// Returns the value of `pattern`, compared literally.
+    pub fn get_mut(&mut self, pattern: &str) -> Option<&mut T> {
+        let mut node = &mut self.root;
+
+        for token in pattern.split('.') {
+            node = node.children.get_mut(token)?;
+        }
+
+        node.value.as_mut().map(|(_, value)| value)
+    }
+
+    /// Removes `pattern`, compared literally, and returns its value.
+    pub fn remove(&mut self, pattern: &str) -> Option<T> {
+        let tokens: Vec<_> = pattern.split('.').collect();
+
+        self.root.remove(&tokens).map(|(_, value)| value)
+    }
+
+    /// Calls `f` with the pattern and the value of every pattern matching `subject`.
+    pub fn for_each_match(&mut self, subject: &str, mut f: impl FnMut(&Arc<str>, &mut T)) {
+        let tokens: Vec<_> = subject.split('.').collect();
+
+        self.root.for_each_match(&tokens, &mut f);
+    }
+
+    /// Returns every pattern of the trie.
+    pub fn patterns(&self) -> Vec<Arc<str>> {
+        let mut patterns = Vec::new();
+        self.root.collect_patterns(&mut patterns);
+
+        patterns
+    }
+}
+
+impl<T> Default for SubjectTrie<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> TrieNode<T> {
+    fn new() -> Self {
+        Self {
+            children: HashMap::new(),
+            value: None,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.value.is_none() && self.children.is_empty()
+    }
+
+    fn remove(&mut self, tokens: &[&str]) -> Option<(Arc<str>, T)> {
+        let Some((token, rest)) = tokens.split_first() else {
+            return self.value.take();
+        };
+
+        let child = self.children.get_mut(*token)?;
+        let removed = child.remove(rest);
+
+        if child.is_empty() {
+            let _ = self.children.remove(*token);
+        }
+
+        removed
+    }
+
+    fn for_each_match(&mut self, tokens: &[&str], f: &mut impl FnMut(&Arc<str>, &mut T)) {
+        let Some((token, rest)) = tokens.split_first() else {
+            if let Some((pattern, value)) = &mut self.value {
+                f(pattern, value);
+            }
+            return;
+        };
+
+        if *token != SINGLE_WILDCARD && *token != FULL_WILDCARD {
+            if let Some(child) = self.children.get_mut(*token) {
+                child.for_each_match(rest, f);
+            }
+        }
+
+        if let Some(child) = self.children.get_mut(SINGLE_WILDCARD) {
+            child.for_each_match(rest, f);
+        }
+
+        if let Some((pattern, value)) = self
+            .children
+            .get_mut(FULL_WILDCARD)
+            .and_then(|child| child.value.as_mut())
+        {
+            f(pattern, value);
+        }
+    }
+
+    fn collect_patterns(&self, patterns: &mut Vec<Arc<str>>) {
+        if let Some((pattern, _)) = &self.value {
+            patterns.push(pattern.clone());
+        }
+
+        for child in self.children.values() {
+            child.collect_patterns(patterns);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pattern_covers() {
+        assert!(pattern_covers("orders.>", "orders.eu.created"));
+        assert!(pattern_covers("orders.>", "orders.*.created"));
+        assert!(pattern_covers("orders.>", "orders.eu.>"));
+        assert!(pattern_covers("orders.*.created", "orders.eu.created"));
+        assert!(pattern_covers("orders.eu.created", "orders.eu.created"));
+
+        assert!(!pattern_covers("orders.eu.created", "orders.>"));
+        assert!(!pattern_covers("orders.*", "orders.>"));
+        assert!(!pattern_covers("orders.*.created", "orders.eu.>"));
+        assert!(!pattern_covers("orders.>", "orders"));
+    }
+
+    #[test]
+    fn test_subject_trie() {
+        let mut trie = SubjectTrie::<Vec<&str>>::new();
+        trie.get_or_insert_default(Arc::from("orders.>"))
+            .push("all");
+        trie.get_or_insert_default(Arc::from("orders.*.created"))
+            .push("created");
+        trie.get_or_insert_default(Arc::from("orders.eu.created"))
+            .push("eu");
+        trie.get_or_insert_default(Arc::from("orders.eu.created"))
+            .push("eu_audit");
+
+        let mut matched = Vec::new();
+        trie.for_each_match("orders.eu.created", |_, values| {
+            matched.extend(values.iter().copied())
+        });
+        matched.sort();
+        assert_eq!(matched, vec!["all", "created", "eu", "eu_audit"]);
+
+        let mut matched = Vec::new();
+        trie.for_each_match("orders.us.created", |pattern, _| {
+            matched.push(pattern.to_string())
+        });
+        matched.sort();
+        assert_eq!(matched, vec!["orders.*.created", "orders.>"]);
+
+        let mut matched = 0;
+        trie.for_each_match("orders", |_, _| matched += 1);
+        assert_eq!(matched, 0);
+
+        assert_eq!(
+            trie.remove("orders.eu.created"),
+            Some(vec!["eu", "eu_audit"])
+        );
+        assert_eq!(trie.remove("orders.eu.created"), None);
+        assert!(trie.get_mut("orders.>").is_some());
+
+        let mut patterns = trie.patterns();
+        patterns.sort();
+        assert_eq!(
+            patterns,
+            vec![Arc::from("orders.*.created"), Arc::from("orders.>")]
+        );
+
+        let _ = trie.remove("orders.>");
+        let _ = trie.remove("orders.*.created");
+        assert!(trie.patterns().is_empty());
+        assert!(trie.root.is_empty());
+    }
+}
@@ -0,0 +1,102 @@
+//! Conversion of JSON values to and from the MessagePack, CBOR and Protobuf wire formats.
+
+use prost::Message;
+use prost_reflect::{DescriptorPool, DynamicMessage, MessageDescriptor};
+
+pub fn encode_msgpack(value: &serde_json::Value) -> anyhow::Result<Vec<u8>> {
+    rmp_serde::to_vec(value).map_err(|err| anyhow::anyhow!("Failed to encode MessagePack: {err}"))
+}
+
+pub fn decode_msgpack(bytes: &[u8]) -> anyhow::Result<serde_json::Value> {
+    rmp_serde::from_slice(bytes)
+        .map_err(|err| anyhow::anyhow!("Invalid MessagePack payload: {err}"))
+}
+
+pub fn encode_cbor(value: &serde_json::Value) -> anyhow::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    ciborium::into_writer(value, &mut bytes)
+        .map_err(|err| anyhow::anyhow!("Failed to encode CBOR: {err}"))?;
+
+    Ok(bytes)
+}
+
+pub fn decode_cbor(bytes: &[u8]) -> anyhow::Result<serde_json::Value> {
+    ciborium::from_reader(bytes).map_err(|err| anyhow::anyhow!("Invalid CBOR payload: {err}"))
+}
+
+/// Checks that `bytes` is a valid serialized `FileDescriptorSet`.
+pub fn validate_descriptor_set(bytes: &[u8]) -> anyhow::Result<()> {
+    let _ = DescriptorPool::decode(bytes)
+        .map_err(|err| anyhow::anyhow!("Invalid Protobuf descriptor set: {err}"))?;
+
+    Ok(())
+}
+
+/// Finds the descriptor of `message_type`, a fully qualified message name, in the first of
+/// the serialized `FileDescriptorSet`s that defines it.
+pub fn find_message_descriptor(
+    descriptor_sets: impl IntoIterator<Item = Vec<u8>>,
+    message_type: &str,
+) -> anyhow::Result<MessageDescriptor> {
+    for descriptor_set in descriptor_sets {
+        let pool = DescriptorPool::decode(descriptor_set.as_slice())
+            .map_err(|err| anyhow::anyhow!("Invalid Protobuf descriptor set: {err}"))?;
+
+        if let Some(descriptor) = pool.get_message_by_name(message_type) {
+            return Ok(descriptor);
+        }
+    }
+
+    anyhow::bail!("Protobuf message type '{message_type}' is not registered")
+}
+
+/// Encodes a JSON value, following the Protobuf JSON mapping, as a message of the given type.
+pub fn encode_protobuf(
+    descriptor: MessageDescriptor,
+    value: serde_json::Value,
+) -> anyhow::Result<Vec<u8>> {
+    let name = descriptor.full_name().to_string();
+    let message = DynamicMessage::deserialize(descriptor, value)
+        .map_err(|err| anyhow::anyhow!("Value does not match Protobuf message '{name}': {err}"))?;
+
+    Ok(message.encode_to_vec())
+}
+
+/// Decodes a message of the given type into its Protobuf JSON mapping.
+pub fn decode_protobuf(
+    descriptor: MessageDescriptor,
+    bytes: &[u8],
+) -> anyhow::Result<serde_json::Value> {
+    let name = descriptor.full_name().to_string();
+    let message = DynamicMessage::decode(descriptor, bytes)
+        .map_err(|err| anyhow::anyhow!("Invalid Protobuf message '{name}': {err}"))?;
+
+    Ok(serde_json::to_value(&message)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_msgpack_round_trip() {
+        let value = serde_json::json!({ "a": 1 });
+
+        let bytes = encode_msgpack(&value).unwrap();
+        assert_eq!(bytes, vec![0x81, 0xa1, b'a', 0x01]);
+        assert_eq!(decode_msgpack(&bytes).unwrap(), value);
+
+        assert!(decode_msgpack(&[0xc1]).is_err());
+    }
+
+    #[test]
+    fn test_cbor_round_trip() {
+        let value = serde_json::json!({ "a": 1 });
+
+        let bytes = encode_cbor(&value).unwrap();
+        assert_eq!(bytes, vec![0xa1, 0x61, b'a', 0x01]);
+        assert_eq!(decode_cbor(&bytes).unwrap(), value);
+
+        assert!(decode_cbor(&[0xff]).is_err());
+    }
+}
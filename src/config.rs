@@ -34,10 +34,11 @@ pub struct Config {
     pub nats_opt: NatsConnectionOptions,
     pub notify_subject: String,
     pub patroni_url: Option<String>,
+    pub kv_auto_create: bool,
 }
 
 pub fn fetch_config(fdw_extension_name: &str) -> Config {
-    let mut options = HashMap::new();
+    let options = HashMap::new();
 
     let Some(fdw_server_name) = fetch_fdw_server_name(fdw_extension_name) else {
         crate::warn!("Failed to get FDW server name for {fdw_extension_name}");
@@ -50,59 +51,77 @@ pub fn fetch_config(fdw_extension_name: &str) -> Config {
     };
 
     // SAFETY:
-    //
-    // 1. We pass a correct arguments to `GetForeignServerByName` and check if the result is null.
-    // 2. We ensure that the `options_list` is not null before iterating over it.
-    // 3. We ensure that the `defname` and `arg` fields are not null before accessing them.
-    // 4. Node casting is safe according to Postgres documentation
-    unsafe {
+    // We pass a correct arguments to `GetForeignServerByName` and check if the result is null.
+    let options = unsafe {
         let server = pgrx::pg_sys::GetForeignServerByName(fdw_server_name.as_ptr(), true);
 
         if server.is_null() {
             return parse_config(&options);
         }
 
-        let options_list = (*server).options;
-        if !options_list.is_null() {
-            let list: pgrx::PgList<pgrx::pg_sys::DefElem> = pgrx::PgList::from_pg(options_list);
+        parse_options_list((*server).options)
+    };
 
-            for def_elem in list.iter_ptr() {
-                if def_elem.is_null() || (*def_elem).defname.is_null() {
-                    continue;
-                }
+    parse_config(&options)
+}
 
-                let key = std::ffi::CStr::from_ptr((*def_elem).defname)
-                    .to_string_lossy()
-                    .to_string();
+/// Collects the `DefElem` options of a foreign server or foreign table into a map.
+///
+/// # Safety
+/// `options_list` must be null or a valid Postgres `List` of `DefElem` nodes.
+pub unsafe fn parse_options_list(
+    options_list: *mut pgrx::pg_sys::List,
+) -> HashMap<Cow<'static, str>, Cow<'static, str>> {
+    let mut options = HashMap::new();
+
+    if options_list.is_null() {
+        return options;
+    }
+
+    // SAFETY:
+    //
+    // 1. We ensure that the `options_list` is not null before iterating over it.
+    // 2. We ensure that the `defname` and `arg` fields are not null before accessing them.
+    // 3. Node casting is safe according to Postgres documentation
+    unsafe {
+        let list: pgrx::PgList<pgrx::pg_sys::DefElem> = pgrx::PgList::from_pg(options_list);
 
-                if (*def_elem).arg.is_null() {
-                    continue;
-                }
+        for def_elem in list.iter_ptr() {
+            if def_elem.is_null() || (*def_elem).defname.is_null() {
+                continue;
+            }
 
-                let node = (*def_elem).arg;
+            let key = std::ffi::CStr::from_ptr((*def_elem).defname)
+                .to_string_lossy()
+                .to_string();
 
-                if (*node).type_ != pgrx::pg_sys::NodeTag::T_String {
-                    continue;
-                }
+            if (*def_elem).arg.is_null() {
+                continue;
+            }
 
-                #[cfg(any(feature = "pg13", feature = "pg14"))]
-                let val = (*(node as *mut pgrx::pg_sys::Value)).val.str_;
+            let node = (*def_elem).arg;
 
-                #[cfg(not(any(feature = "pg13", feature = "pg14")))]
-                let val = (*(node as *mut pgrx::pg_sys::String)).sval;
+            if (*node).type_ != pgrx::pg_sys::NodeTag::T_String {
+                continue;
+            }
 
-                if val.is_null() {
-                    continue;
-                }
+            #[cfg(any(feature = "pg13", feature = "pg14"))]
+            let val = (*(node as *mut pgrx::pg_sys::Value)).val.str_;
 
-                let value = std::ffi::CStr::from_ptr(val).to_string_lossy().to_string();
+            #[cfg(not(any(feature = "pg13", feature = "pg14")))]
+            let val = (*(node as *mut pgrx::pg_sys::String)).sval;
 
-                let _ = options.insert(key.into(), value.into());
+            if val.is_null() {
+                continue;
             }
+
+            let value = std::ffi::CStr::from_ptr(val).to_string_lossy().to_string();
+
+            let _ = options.insert(key.into(), value.into());
         }
-    };
+    }
 
-    parse_config(&options)
+    options
 }
 
 pub fn parse_config(options: &HashMap<Cow<'_, str>, Cow<'_, str>>) -> Config {
@@ -144,6 +163,11 @@ pub fn parse_config(options: &HashMap<Cow<'_, str>, Cow<'_, str>>) -> Config {
 
     let patroni_url = options.get("patroni_url").map(|v| v.to_string());
 
+    let kv_auto_create = options
+        .get("kv_auto_create")
+        .and_then(|v| v.parse::<bool>().ok())
+        .unwrap_or(true);
+
     Config {
         nats_opt: NatsConnectionOptions {
             host,
@@ -153,6 +177,7 @@ pub fn parse_config(options: &HashMap<Cow<'_, str>, Cow<'_, str>>) -> Config {
         },
         notify_subject,
         patroni_url,
+        kv_auto_create,
     }
 }
 
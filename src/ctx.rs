@@ -1,4 +1,7 @@
-use std::cell::RefCell;
+use std::{
+    cell::RefCell,
+    sync::{Arc, Mutex},
+};
 
 use crate::{config::fetch_config, constants::FDW_EXTENSION_NAME, nats_client::NatsClient};
 
@@ -9,6 +12,21 @@ thread_local! {
 pub struct Context {
     pub nats_connection: NatsClient,
     pub rt: tokio::runtime::Runtime,
+    /// Ack tokens of messages fetched with `auto_ack`, acknowledged when the transaction ends.
+    pub pending_acks: Vec<String>,
+    pub async_publishes: AsyncPublishes,
+}
+
+/// Publishes queued by `nats_publish_*_async`, awaited at the latest when the transaction
+/// commits.
+#[derive(Default)]
+pub struct AsyncPublishes {
+    pub tasks: Vec<tokio::task::JoinHandle<()>>,
+    /// Failures of completed publishes that have not been reported yet, as the subject and
+    /// the reason. Failures not tied to one message have no subject.
+    pub errors: Arc<Mutex<Vec<(Option<String>, String)>>>,
+    /// Whether the check at commit is registered for the current transaction.
+    pub registered: bool,
 }
 
 // The extension is useless without tokio runtime. It has to panic if the runtime cannot be initialized.
@@ -21,5 +39,7 @@ fn create_context() -> Context {
             .enable_all()
             .build()
             .expect("Failed to initialize Tokio runtime"),
+        pending_acks: Vec::new(),
+        async_publishes: AsyncPublishes::default(),
     }
 }
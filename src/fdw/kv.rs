@@ -0,0 +1,283 @@
+use std::time::Duration;
+
+use async_nats::jetstream::consumer::pull::Ordered;
+use futures::StreamExt;
+
+use crate::{
+    ctx::CTX,
+    fdw::{
+        pushdown::{Qual, QualValue},
+        Cell, TableModify, TableScan,
+    },
+    nats_client::{NatsClient, KV_OPERATION_HEADER},
+    utils::unix_nanos_to_pg_timestamp,
+};
+
+/// How long a scan waits for the next revision before it considers the bucket exhausted.
+const KV_SCAN_IDLE_TIMEOUT: Duration = Duration::from_secs(5);
+
+const ALL_KEYS: &str = ">";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KvColumn {
+    Key,
+    Value,
+    Revision,
+    Created,
+}
+
+impl KvColumn {
+    pub fn from_name(name: &str) -> anyhow::Result<Self> {
+        match name {
+            "key" => Ok(Self::Key),
+            "value" => Ok(Self::Value),
+            "revision" => Ok(Self::Revision),
+            "created" => Ok(Self::Created),
+            _ => Err(anyhow::anyhow!(
+                "Unknown column '{name}' for a KV foreign table, expected one of: key, value, revision, created"
+            )),
+        }
+    }
+}
+
+/// Scan over the latest revisions of the keys of a KV bucket.
+pub struct KvScan {
+    bucket: String,
+    key_filter: String,
+    columns: Vec<Option<KvColumn>>,
+    prefix: String,
+    messages: Option<Ordered>,
+    done: bool,
+}
+
+impl KvScan {
+    pub fn new(bucket: String, columns: &[Option<&str>], quals: &[Qual]) -> anyhow::Result<Self> {
+        let columns = columns
+            .iter()
+            .map(|c| c.map(KvColumn::from_name).transpose())
+            .collect::<anyhow::Result<_>>()?;
+
+        let key_filter = quals
+            .iter()
+            .filter_map(
+                |qual| match (qual.column.as_str(), qual.op.as_str(), &qual.value) {
+                    ("key", "=", QualValue::Text(key)) => Some(key.clone()),
+                    ("key", "~~", QualValue::Text(pattern)) => like_to_key_filter(pattern),
+                    _ => None,
+                },
+            )
+            .next()
+            .unwrap_or_else(|| ALL_KEYS.to_string());
+
+        Ok(Self {
+            bucket,
+            key_filter,
+            columns,
+            prefix: String::new(),
+            messages: None,
+            done: false,
+        })
+    }
+
+    async fn next_message(
+        &mut self,
+        nats: &mut NatsClient,
+    ) -> anyhow::Result<Option<async_nats::jetstream::Message>> {
+        if self.messages.is_none() {
+            let (prefix, pending, messages) =
+                nats.read_bucket(&self.bucket, &self.key_filter).await?;
+
+            if pending == 0 {
+                self.done = true;
+                return Ok(None);
+            }
+
+            self.prefix = prefix;
+            self.messages = Some(messages);
+        }
+
+        let Some(messages) = self.messages.as_mut() else {
+            return Ok(None);
+        };
+
+        match tokio::time::timeout(KV_SCAN_IDLE_TIMEOUT, messages.next()).await {
+            Ok(Some(message)) => Ok(Some(message?)),
+            Ok(None) | Err(_) => Ok(None),
+        }
+    }
+
+    fn to_row(&self, message: async_nats::jetstream::Message) -> anyhow::Result<Vec<Cell>> {
+        let info = message.info().map_err(|err| anyhow::anyhow!("{err}"))?;
+        let revision = info.stream_sequence;
+        let created = unix_nanos_to_pg_timestamp(info.published.unix_timestamp_nanos());
+
+        let message = message.message;
+        let key = message
+            .subject
+            .strip_prefix(self.prefix.as_str())
+            .unwrap_or(&message.subject)
+            .to_string();
+
+        Ok(self
+            .columns
+            .iter()
+            .map(|column| match column {
+                Some(KvColumn::Key) => Cell::Text(key.clone()),
+                Some(KvColumn::Value) => Cell::Bytes(message.payload.to_vec()),
+                Some(KvColumn::Revision) => Cell::Int(i64::try_from(revision).unwrap_or(i64::MAX)),
+                Some(KvColumn::Created) => Cell::Timestamp(created),
+                None => Cell::Null,
+            })
+            .collect())
+    }
+}
+
+impl TableScan for KvScan {
+    fn next_row(&mut self) -> anyhow::Result<Option<Vec<Cell>>> {
+        loop {
+            if self.done {
+                return Ok(None);
+            }
+
+            let message = CTX.with_borrow_mut(|ctx| {
+                ctx.rt.block_on(self.next_message(&mut ctx.nats_connection))
+            })?;
+
+            let Some(message) = message else {
+                self.end();
+                return Ok(None);
+            };
+
+            let pending = message
+                .info()
+                .map_err(|err| anyhow::anyhow!("{err}"))?
+                .pending;
+
+            if pending == 0 {
+                self.end();
+            }
+
+            // Deleted and purged keys are kept in the bucket as markers until compacted
+            if is_deleted(&message) {
+                continue;
+            }
+
+            return self.to_row(message).map(Some);
+        }
+    }
+
+    fn rescan(&mut self) {
+        self.messages = None;
+        self.done = false;
+    }
+
+    fn end(&mut self) {
+        self.messages = None;
+        self.done = true;
+    }
+}
+
+/// Writes rows of a KV foreign table as puts and deletes of the bucket keys.
+pub struct KvModify {
+    bucket: String,
+    columns: Vec<Option<KvColumn>>,
+}
+
+impl KvModify {
+    pub fn new(bucket: String, columns: &[Option<&str>]) -> anyhow::Result<Self> {
+        let columns = columns
+            .iter()
+            .map(|c| c.map(KvColumn::from_name).transpose())
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self { bucket, columns })
+    }
+
+    fn take(&self, row: &mut [Cell], column: KvColumn) -> Cell {
+        self.columns
+            .iter()
+            .zip(row)
+            .find(|(c, _)| **c == Some(column))
+            .map(|(_, cell)| std::mem::replace(cell, Cell::Null))
+            .unwrap_or(Cell::Null)
+    }
+
+    fn put(&self, key: String, value: Cell) -> anyhow::Result<()> {
+        if matches!(value, Cell::Null) {
+            anyhow::bail!("Column 'value' of a KV foreign table must not be NULL");
+        }
+
+        let _ = CTX.with_borrow_mut(|ctx| {
+            ctx.rt
+                .block_on(ctx.nats_connection.put_value(&self.bucket, key, value))
+        })?;
+
+        Ok(())
+    }
+}
+
+impl TableModify for KvModify {
+    fn insert(&mut self, mut row: Vec<Cell>) -> anyhow::Result<()> {
+        let Cell::Text(key) = self.take(&mut row, KvColumn::Key) else {
+            anyhow::bail!("Column 'key' of a KV foreign table must not be NULL");
+        };
+
+        let value = self.take(&mut row, KvColumn::Value);
+        self.put(key, value)
+    }
+
+    fn update(
+        &mut self,
+        row_id: Cell,
+        mut row: Vec<Cell>,
+        _assigned: &[bool],
+    ) -> anyhow::Result<()> {
+        let Cell::Text(key) = row_id else {
+            anyhow::bail!("Row of a KV foreign table has no key");
+        };
+
+        if let Cell::Text(new_key) = self.take(&mut row, KvColumn::Key) {
+            anyhow::ensure!(
+                new_key == key,
+                "Changing the key of a KV foreign table row is not supported"
+            );
+        }
+
+        let value = self.take(&mut row, KvColumn::Value);
+        self.put(key, value)
+    }
+
+    fn delete(&mut self, row_id: Cell) -> anyhow::Result<()> {
+        let Cell::Text(key) = row_id else {
+            anyhow::bail!("Row of a KV foreign table has no key");
+        };
+
+        CTX.with_borrow_mut(|ctx| {
+            ctx.rt
+                .block_on(ctx.nats_connection.delete_value(&self.bucket, key))
+        })
+    }
+}
+
+fn is_deleted(message: &async_nats::jetstream::Message) -> bool {
+    message
+        .headers
+        .as_ref()
+        .and_then(|h| h.get(KV_OPERATION_HEADER))
+        .is_some_and(|op| matches!(op.as_str(), "DEL" | "PURGE"))
+}
+
+/// Translates a `LIKE` pattern into a key filter matching a superset of the keys it matches.
+///
+/// A pattern without wildcards is an exact key. Otherwise only the literal prefix of the
+/// pattern is used: a prefix ending at a token boundary becomes `prefix.>`, anything else
+/// falls back to all keys.
+fn like_to_key_filter(pattern: &str) -> Option<String> {
+    let Some(wildcard) = pattern.find(['%', '_', '\\']) else {
+        return Some(pattern.to_string());
+    };
+
+    let prefix = pattern.get(..wildcard)?;
+
+    (prefix.len() > 1 && prefix.ends_with('.')).then(|| format!("{prefix}{ALL_KEYS}"))
+}
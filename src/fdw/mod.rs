@@ -0,0 +1,934 @@
+//! Scan and modify support for `pgnats_fdw` foreign tables.
+//!
+//! A foreign table is backed by a JetStream resource chosen by its options,
+//! e.g. `OPTIONS (stream 'ORDERS', filter_subject 'orders.>')`. Simple
+//! `column <op> constant` restrictions are pushed down to NATS where the
+//! backing resource supports it; all restrictions are still rechecked by Postgres.
+//!
+//! Writable tables identify the modified rows by a row identity column (e.g. `key`
+//! of a KV table), which is passed from the scan to the modify callbacks as a junk column.
+
+#[cfg(feature = "kv")]
+mod kv;
+#[cfg(feature = "object_store")]
+mod object_store;
+mod options;
+mod pushdown;
+mod stream;
+
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    ffi::{c_int, CString},
+};
+
+use pgrx::{extension_sql, pg_guard, pg_sys, FromDatum, IntoDatum, PgMemoryContexts, PgTupleDesc};
+
+use crate::{config::parse_options_list, error, utils::ToBytes};
+
+use options::TableOptions;
+
+/// Planner's estimate of rows returned by a foreign table scan.
+const DEFAULT_ROWS_ESTIMATE: f64 = 1000.0;
+/// Planner's cost of starting a foreign table scan (creating a consumer on the server).
+const STARTUP_COST: f64 = 100.0;
+
+extension_sql!(
+    r#"
+    CREATE FUNCTION pgnats_fdw_handler() RETURNS fdw_handler
+    AS 'MODULE_PATHNAME', 'pgnats_fdw_handler'
+    LANGUAGE C STRICT;
+    "#,
+    name = "create_fdw_handler",
+);
+
+/// Value of a single column produced by a [`TableScan`].
+#[derive(Debug)]
+pub enum Cell {
+    Null,
+    Int(i64),
+    Text(String),
+    Bytes(Vec<u8>),
+    Json(serde_json::Value),
+    /// Postgres timestamp in microseconds since 2000-01-01.
+    Timestamp(i64),
+}
+
+impl Cell {
+    /// Converts the value into a datum of the column type `type_oid`.
+    fn into_column_datum(
+        self,
+        type_oid: pg_sys::Oid,
+        column: &str,
+    ) -> anyhow::Result<Option<pg_sys::Datum>> {
+        let datum = match (self, type_oid) {
+            (Cell::Null, _) => None,
+            (Cell::Int(v), pg_sys::INT8OID) => v.into_datum(),
+            (Cell::Int(v), pg_sys::INT4OID) => i32::try_from(v)?.into_datum(),
+            (Cell::Int(v), pg_sys::TEXTOID | pg_sys::VARCHAROID) => v.to_string().into_datum(),
+            (Cell::Text(v), pg_sys::TEXTOID | pg_sys::VARCHAROID) => v.into_datum(),
+            (Cell::Timestamp(v), pg_sys::TIMESTAMPTZOID | pg_sys::TIMESTAMPOID) => {
+                Some(pg_sys::Datum::from(v))
+            }
+            (Cell::Json(v), pg_sys::JSONBOID) => pgrx::JsonB(v).into_datum(),
+            (Cell::Json(v), pg_sys::JSONOID) => pgrx::Json(v).into_datum(),
+            (Cell::Json(v), pg_sys::TEXTOID | pg_sys::VARCHAROID) => v.to_string().into_datum(),
+            (Cell::Bytes(v), pg_sys::BYTEAOID) => v.into_datum(),
+            (Cell::Bytes(v), pg_sys::TEXTOID | pg_sys::VARCHAROID) => String::from_utf8(v)
+                .map_err(|_| anyhow::anyhow!("Column '{column}' contains invalid UTF-8"))?
+                .into_datum(),
+            (Cell::Bytes(v), pg_sys::JSONBOID) => {
+                pgrx::JsonB(serde_json::from_slice(&v).map_err(|err| {
+                    anyhow::anyhow!("Column '{column}' contains invalid JSON: {err}")
+                })?)
+                .into_datum()
+            }
+            (Cell::Bytes(v), pg_sys::JSONOID) => {
+                pgrx::Json(serde_json::from_slice(&v).map_err(|err| {
+                    anyhow::anyhow!("Column '{column}' contains invalid JSON: {err}")
+                })?)
+                .into_datum()
+            }
+            (cell, _) => {
+                anyhow::bail!("Column '{column}' has an unsupported type for value {cell:?}")
+            }
+        };
+
+        Ok(datum)
+    }
+}
+
+impl Cell {
+    /// Converts a datum of the column type `type_oid` into a value.
+    ///
+    /// # Safety
+    /// `datum` must be a valid datum of the type `type_oid` unless `is_null` is set.
+    unsafe fn from_column_datum(
+        datum: pg_sys::Datum,
+        is_null: bool,
+        type_oid: pg_sys::Oid,
+        column: &str,
+    ) -> anyhow::Result<Self> {
+        if is_null {
+            return Ok(Cell::Null);
+        }
+
+        // SAFETY: the caller guarantees `datum` is a non-null datum of `type_oid`.
+        let cell = unsafe {
+            match type_oid {
+                pg_sys::INT2OID => {
+                    i16::from_polymorphic_datum(datum, false, type_oid).map(|v| Cell::Int(v.into()))
+                }
+                pg_sys::INT4OID => {
+                    i32::from_polymorphic_datum(datum, false, type_oid).map(|v| Cell::Int(v.into()))
+                }
+                pg_sys::INT8OID => {
+                    i64::from_polymorphic_datum(datum, false, type_oid).map(Cell::Int)
+                }
+                pg_sys::TEXTOID | pg_sys::VARCHAROID => {
+                    String::from_polymorphic_datum(datum, false, type_oid).map(Cell::Text)
+                }
+                pg_sys::BYTEAOID => {
+                    Vec::<u8>::from_polymorphic_datum(datum, false, type_oid).map(Cell::Bytes)
+                }
+                pg_sys::JSONBOID => pgrx::JsonB::from_polymorphic_datum(datum, false, type_oid)
+                    .map(|v| Cell::Json(v.0)),
+                pg_sys::JSONOID => pgrx::Json::from_polymorphic_datum(datum, false, type_oid)
+                    .map(|v| Cell::Json(v.0)),
+                pg_sys::TIMESTAMPTZOID | pg_sys::TIMESTAMPOID => {
+                    i64::from_polymorphic_datum(datum, false, pg_sys::INT8OID).map(Cell::Timestamp)
+                }
+                _ => anyhow::bail!("Column '{column}' has an unsupported type"),
+            }
+        };
+
+        Ok(cell.unwrap_or(Cell::Null))
+    }
+}
+
+impl ToBytes for Cell {
+    fn to_bytes(self) -> anyhow::Result<Vec<u8>> {
+        match self {
+            Cell::Null => anyhow::bail!("NULL value can not be stored in NATS"),
+            Cell::Int(v) => Ok(v.to_string().into_bytes()),
+            Cell::Text(v) => v.to_bytes(),
+            Cell::Bytes(v) => v.to_bytes(),
+            Cell::Json(v) => v.to_bytes(),
+            Cell::Timestamp(_) => anyhow::bail!("Timestamp value can not be stored in NATS"),
+        }
+    }
+}
+
+/// Source of rows for a foreign table scan.
+pub trait TableScan {
+    /// Returns the next row with one cell per column of the table, dropped columns included.
+    fn next_row(&mut self) -> anyhow::Result<Option<Vec<Cell>>>;
+
+    /// Restarts the scan from the beginning.
+    fn rescan(&mut self);
+
+    /// Releases NATS resources held by the scan.
+    fn end(&mut self);
+}
+
+/// Sink of rows written to a foreign table.
+pub trait TableModify {
+    /// Stores a new row with one cell per column of the table, dropped columns included.
+    fn insert(&mut self, row: Vec<Cell>) -> anyhow::Result<()>;
+
+    /// Replaces the row identified by `row_id`. `assigned` tells for each column whether
+    /// the `UPDATE` sets it, cells of columns it does not set may be null.
+    fn update(&mut self, row_id: Cell, row: Vec<Cell>, assigned: &[bool]) -> anyhow::Result<()>;
+
+    /// Removes the row identified by `row_id`.
+    fn delete(&mut self, row_id: Cell) -> anyhow::Result<()>;
+}
+
+struct ModifyState {
+    columns: Vec<Option<(String, pg_sys::Oid)>>,
+    /// Type and position of the row identity junk column in the plan slot.
+    row_id: Option<(pg_sys::Oid, pg_sys::AttrNumber)>,
+    /// Columns set by an `UPDATE`, empty for other commands.
+    assigned: Vec<bool>,
+    modify: Box<dyn TableModify>,
+}
+
+struct ScanState {
+    columns: Vec<Option<(String, pg_sys::Oid)>>,
+    scan: Box<dyn TableScan>,
+}
+
+/// Checks options of a foreign table created with `pgnats_fdw`.
+pub fn validate_table_options(options: &[String]) -> anyhow::Result<()> {
+    let options = options
+        .iter()
+        .filter_map(|opt| opt.split_once('='))
+        .map(|(k, v)| (k.into(), v.into()))
+        .collect();
+
+    TableOptions::parse(&options).map(|_| ())
+}
+
+#[doc(hidden)]
+#[unsafe(no_mangle)]
+pub extern "C-unwind" fn pg_finfo_pgnats_fdw_handler() -> &'static pg_sys::Pg_finfo_record {
+    const V1_API: pg_sys::Pg_finfo_record = pg_sys::Pg_finfo_record { api_version: 1 };
+    &V1_API
+}
+
+#[doc(hidden)]
+#[pg_guard]
+#[unsafe(no_mangle)]
+pub extern "C-unwind" fn pgnats_fdw_handler(_fcinfo: pg_sys::FunctionCallInfo) -> pg_sys::Datum {
+    // SAFETY:
+    // The routine is allocated with `palloc0` in the current memory context as Postgres
+    // expects from an FDW handler, and every callback matches the `FdwRoutine` signature.
+    unsafe {
+        let routine =
+            pg_sys::palloc0(std::mem::size_of::<pg_sys::FdwRoutine>()) as *mut pg_sys::FdwRoutine;
+
+        (*routine).type_ = pg_sys::NodeTag::T_FdwRoutine;
+
+        (*routine).GetForeignRelSize = Some(get_foreign_rel_size);
+        (*routine).GetForeignPaths = Some(get_foreign_paths);
+        (*routine).GetForeignPlan = Some(get_foreign_plan);
+        (*routine).BeginForeignScan = Some(begin_foreign_scan);
+        (*routine).IterateForeignScan = Some(iterate_foreign_scan);
+        (*routine).ReScanForeignScan = Some(re_scan_foreign_scan);
+        (*routine).EndForeignScan = Some(end_foreign_scan);
+
+        (*routine).AddForeignUpdateTargets = Some(add_foreign_update_targets);
+        (*routine).BeginForeignModify = Some(begin_foreign_modify);
+        (*routine).ExecForeignInsert = Some(exec_foreign_insert);
+        (*routine).ExecForeignUpdate = Some(exec_foreign_update);
+        (*routine).ExecForeignDelete = Some(exec_foreign_delete);
+
+        pg_sys::Datum::from(routine)
+    }
+}
+
+#[pg_guard]
+extern "C-unwind" fn get_foreign_rel_size(
+    _root: *mut pg_sys::PlannerInfo,
+    baserel: *mut pg_sys::RelOptInfo,
+    _foreigntableid: pg_sys::Oid,
+) {
+    // SAFETY: Postgres passes a valid `RelOptInfo` of the foreign table being planned.
+    unsafe {
+        (*baserel).rows = DEFAULT_ROWS_ESTIMATE;
+    }
+}
+
+#[pg_guard]
+extern "C-unwind" fn get_foreign_paths(
+    root: *mut pg_sys::PlannerInfo,
+    baserel: *mut pg_sys::RelOptInfo,
+    _foreigntableid: pg_sys::Oid,
+) {
+    // SAFETY: Postgres passes valid planner structures, the created path is owned by the planner.
+    unsafe {
+        let rows = (*baserel).rows;
+        let path = create_foreign_path(root, baserel, rows, STARTUP_COST, STARTUP_COST + rows);
+
+        pg_sys::add_path(baserel, path.cast());
+    }
+}
+
+#[pg_guard]
+extern "C-unwind" fn get_foreign_plan(
+    _root: *mut pg_sys::PlannerInfo,
+    baserel: *mut pg_sys::RelOptInfo,
+    foreigntableid: pg_sys::Oid,
+    _best_path: *mut pg_sys::ForeignPath,
+    tlist: *mut pg_sys::List,
+    scan_clauses: *mut pg_sys::List,
+    outer_plan: *mut pg_sys::Plan,
+) -> *mut pg_sys::ForeignScan {
+    // SAFETY: Postgres passes valid planner structures. All clauses are kept in the plan
+    // qualifiers, so pushed down clauses are rechecked locally.
+    unsafe {
+        let scan_clauses = pg_sys::extract_actual_clauses(scan_clauses, false);
+        let plan = pushdown::ScanPlan {
+            quals: pushdown::extract_quals(baserel, foreigntableid),
+            attrs: pushdown::extract_attrs(baserel, scan_clauses),
+        };
+
+        pg_sys::make_foreignscan(
+            tlist,
+            scan_clauses,
+            (*baserel).relid,
+            std::ptr::null_mut(),
+            pushdown::into_fdw_private(&plan),
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            outer_plan,
+        )
+    }
+}
+
+#[pg_guard]
+extern "C-unwind" fn begin_foreign_scan(node: *mut pg_sys::ForeignScanState, eflags: c_int) {
+    if eflags & pg_sys::EXEC_FLAG_EXPLAIN_ONLY as c_int != 0 {
+        return;
+    }
+
+    // SAFETY: Postgres passes a valid scan state with an opened foreign relation and the
+    // plan built by `get_foreign_plan`.
+    let state = unsafe {
+        let rel = (*node).ss.ss_currentRelation;
+        let table = pg_sys::GetForeignTable((*rel).rd_id);
+        let options = parse_options_list((*table).options);
+        let plan = (*node).ss.ps.plan as *mut pg_sys::ForeignScan;
+        let plan = pushdown::from_fdw_private((*plan).fdw_private);
+
+        // Columns not read by the query are left null, so their values are never fetched
+        let columns: Vec<_> = PgTupleDesc::from_pg_unchecked((*rel).rd_att)
+            .iter()
+            .map(|attr| {
+                (!attr.is_dropped() && plan.uses_attr(attr.attnum))
+                    .then(|| (attr.name().to_string(), attr.atttypid))
+            })
+            .collect();
+
+        create_scan_state(&options, columns, &plan.quals)
+    };
+
+    match state {
+        Ok(state) => {
+            // SAFETY: the state is dropped together with the executor's memory context,
+            // even when the query is aborted before `end_foreign_scan` is called.
+            unsafe {
+                (*node).fdw_state = PgMemoryContexts::CurrentMemoryContext
+                    .leak_and_drop_on_delete(state)
+                    .cast();
+            }
+        }
+        Err(err) => error!("{err}"),
+    }
+}
+
+#[pg_guard]
+extern "C-unwind" fn iterate_foreign_scan(
+    node: *mut pg_sys::ForeignScanState,
+) -> *mut pg_sys::TupleTableSlot {
+    // SAFETY: `fdw_state` is either null or the `ScanState` set by `begin_foreign_scan`, and
+    // the scan slot has one value and null flag per attribute of the relation.
+    unsafe {
+        let slot = (*node).ss.ss_ScanTupleSlot;
+        exec_clear_tuple(slot);
+
+        let Some(state) = ((*node).fdw_state as *mut ScanState).as_mut() else {
+            error!("Foreign scan is not initialized");
+            return slot;
+        };
+
+        match fill_slot(state, slot) {
+            Ok(true) => {
+                let _ = pg_sys::ExecStoreVirtualTuple(slot);
+            }
+            Ok(false) => {}
+            Err(err) => error!("{err}"),
+        }
+
+        slot
+    }
+}
+
+#[pg_guard]
+extern "C-unwind" fn re_scan_foreign_scan(node: *mut pg_sys::ForeignScanState) {
+    // SAFETY: `fdw_state` is either null or the `ScanState` set by `begin_foreign_scan`.
+    unsafe {
+        if let Some(state) = ((*node).fdw_state as *mut ScanState).as_mut() {
+            state.scan.rescan();
+        }
+    }
+}
+
+#[pg_guard]
+extern "C-unwind" fn end_foreign_scan(node: *mut pg_sys::ForeignScanState) {
+    // SAFETY: `fdw_state` is either null or the `ScanState` set by `begin_foreign_scan`.
+    unsafe {
+        if let Some(state) = ((*node).fdw_state as *mut ScanState).as_mut() {
+            state.scan.end();
+        }
+    }
+}
+
+#[cfg(not(feature = "pg13"))]
+#[pg_guard]
+extern "C-unwind" fn add_foreign_update_targets(
+    root: *mut pg_sys::PlannerInfo,
+    rtindex: pg_sys::Index,
+    _target_rte: *mut pg_sys::RangeTblEntry,
+    target_relation: pg_sys::Relation,
+) {
+    // SAFETY: Postgres passes valid planner structures and the opened target relation.
+    let result = unsafe {
+        row_id_attribute(target_relation).and_then(|(name, attr)| {
+            #[cfg(not(any(feature = "pg14", feature = "pg15")))]
+            let varno = c_int::try_from(rtindex)?;
+
+            #[cfg(any(feature = "pg14", feature = "pg15"))]
+            let varno = rtindex;
+
+            let var = pg_sys::makeVar(
+                varno,
+                attr.attnum,
+                attr.atttypid,
+                attr.atttypmod,
+                attr.attcollation,
+                0,
+            );
+
+            pg_sys::add_row_identity_var(root, var, rtindex, pg_sys::pstrdup(name.as_ptr()));
+
+            Ok(())
+        })
+    };
+
+    if let Err(err) = result {
+        error!("{err}");
+    }
+}
+
+#[cfg(feature = "pg13")]
+#[pg_guard]
+extern "C-unwind" fn add_foreign_update_targets(
+    parsetree: *mut pg_sys::Query,
+    _target_rte: *mut pg_sys::RangeTblEntry,
+    target_relation: pg_sys::Relation,
+) {
+    // SAFETY: Postgres passes a valid query tree and the opened target relation.
+    let result = unsafe {
+        row_id_attribute(target_relation).and_then(|(name, attr)| {
+            let varno = pg_sys::Index::try_from((*parsetree).resultRelation)?;
+            let resno =
+                pgrx::PgList::<pg_sys::TargetEntry>::from_pg((*parsetree).targetList).len() + 1;
+
+            let var = pg_sys::makeVar(
+                varno,
+                attr.attnum,
+                attr.atttypid,
+                attr.atttypmod,
+                attr.attcollation,
+                0,
+            );
+
+            let entry = pg_sys::makeTargetEntry(
+                var.cast(),
+                pg_sys::AttrNumber::try_from(resno)?,
+                pg_sys::pstrdup(name.as_ptr()),
+                true,
+            );
+
+            (*parsetree).targetList = pg_sys::lappend((*parsetree).targetList, entry.cast());
+
+            Ok(())
+        })
+    };
+
+    if let Err(err) = result {
+        error!("{err}");
+    }
+}
+
+#[pg_guard]
+extern "C-unwind" fn begin_foreign_modify(
+    mtstate: *mut pg_sys::ModifyTableState,
+    rinfo: *mut pg_sys::ResultRelInfo,
+    _fdw_private: *mut pg_sys::List,
+    subplan_index: c_int,
+    eflags: c_int,
+) {
+    if eflags & pg_sys::EXEC_FLAG_EXPLAIN_ONLY as c_int != 0 {
+        return;
+    }
+
+    // SAFETY: Postgres passes a valid modify state with an opened result relation.
+    let state = unsafe {
+        let rel = (*rinfo).ri_RelationDesc;
+        let table = pg_sys::GetForeignTable((*rel).rd_id);
+        let options = parse_options_list((*table).options);
+
+        let tupdesc = PgTupleDesc::from_pg_unchecked((*rel).rd_att);
+        let columns: Vec<_> = tupdesc
+            .iter()
+            .map(|attr| (!attr.is_dropped()).then(|| (attr.name().to_string(), attr.atttypid)))
+            .collect();
+
+        let operation = (*mtstate).operation;
+        let row_id = if operation == pg_sys::CmdType::CMD_UPDATE
+            || operation == pg_sys::CmdType::CMD_DELETE
+        {
+            row_id_junk_column(mtstate, subplan_index, rel).map(Some)
+        } else {
+            Ok(None)
+        };
+
+        let assigned = if operation == pg_sys::CmdType::CMD_UPDATE {
+            let updated = pg_sys::ExecGetUpdatedCols(rinfo, (*mtstate).ps.state);
+            tupdesc
+                .iter()
+                .map(|attr| {
+                    let member =
+                        i32::from(attr.attnum) - pg_sys::FirstLowInvalidHeapAttributeNumber;
+                    pg_sys::bms_is_member(member, updated)
+                })
+                .collect()
+        } else {
+            vec![]
+        };
+
+        row_id.and_then(|row_id| create_modify_state(&options, columns, row_id, assigned))
+    };
+
+    match state {
+        Ok(state) => {
+            // SAFETY: the state is dropped together with the executor's memory context.
+            unsafe {
+                (*rinfo).ri_FdwState = PgMemoryContexts::CurrentMemoryContext
+                    .leak_and_drop_on_delete(state)
+                    .cast();
+            }
+        }
+        Err(err) => error!("{err}"),
+    }
+}
+
+#[pg_guard]
+extern "C-unwind" fn exec_foreign_insert(
+    _estate: *mut pg_sys::EState,
+    rinfo: *mut pg_sys::ResultRelInfo,
+    slot: *mut pg_sys::TupleTableSlot,
+    _plan_slot: *mut pg_sys::TupleTableSlot,
+) -> *mut pg_sys::TupleTableSlot {
+    // SAFETY: `ri_FdwState` is either null or the `ModifyState` set by `begin_foreign_modify`,
+    // and `slot` holds a row of the result relation.
+    let result = unsafe {
+        modify_state(rinfo).and_then(|state| {
+            let row = read_slot(slot, &state.columns)?;
+            state.modify.insert(row)
+        })
+    };
+
+    if let Err(err) = result {
+        error!("{err}");
+    }
+
+    slot
+}
+
+#[pg_guard]
+extern "C-unwind" fn exec_foreign_update(
+    _estate: *mut pg_sys::EState,
+    rinfo: *mut pg_sys::ResultRelInfo,
+    slot: *mut pg_sys::TupleTableSlot,
+    plan_slot: *mut pg_sys::TupleTableSlot,
+) -> *mut pg_sys::TupleTableSlot {
+    // SAFETY: `ri_FdwState` is either null or the `ModifyState` set by `begin_foreign_modify`,
+    // `slot` holds the new row and `plan_slot` holds the row identity junk column.
+    let result = unsafe {
+        modify_state(rinfo).and_then(|state| {
+            let row_id = read_row_id(plan_slot, state.row_id)?;
+            let row = read_slot(slot, &state.columns)?;
+            state.modify.update(row_id, row, &state.assigned)
+        })
+    };
+
+    if let Err(err) = result {
+        error!("{err}");
+    }
+
+    slot
+}
+
+#[pg_guard]
+extern "C-unwind" fn exec_foreign_delete(
+    _estate: *mut pg_sys::EState,
+    rinfo: *mut pg_sys::ResultRelInfo,
+    slot: *mut pg_sys::TupleTableSlot,
+    plan_slot: *mut pg_sys::TupleTableSlot,
+) -> *mut pg_sys::TupleTableSlot {
+    // SAFETY: `ri_FdwState` is either null or the `ModifyState` set by `begin_foreign_modify`,
+    // and `plan_slot` holds the row identity junk column.
+    let result = unsafe {
+        modify_state(rinfo).and_then(|state| {
+            let row_id = read_row_id(plan_slot, state.row_id)?;
+            state.modify.delete(row_id)
+        })
+    };
+
+    if let Err(err) = result {
+        error!("{err}");
+    }
+
+    slot
+}
+
+fn create_scan_state(
+    options: &HashMap<Cow<'_, str>, Cow<'_, str>>,
+    columns: Vec<Option<(String, pg_sys::Oid)>>,
+    quals: &[pushdown::Qual],
+) -> anyhow::Result<ScanState> {
+    let names: Vec<_> = columns
+        .iter()
+        .map(|c| c.as_ref().map(|(name, _)| name.as_str()))
+        .collect();
+
+    let scan: Box<dyn TableScan> = match TableOptions::parse(options)? {
+        TableOptions::Stream {
+            stream,
+            filter_subject,
+        } => Box::new(stream::StreamScan::new(
+            stream,
+            filter_subject,
+            &names,
+            quals,
+        )?),
+        #[cfg(feature = "kv")]
+        TableOptions::Kv { bucket } => Box::new(kv::KvScan::new(bucket, &names, quals)?),
+        #[cfg(feature = "object_store")]
+        TableOptions::ObjectStore { store } => {
+            Box::new(object_store::ObjectStoreScan::new(store, &names, quals)?)
+        }
+    };
+
+    Ok(ScanState { columns, scan })
+}
+
+fn create_modify_state(
+    options: &HashMap<Cow<'_, str>, Cow<'_, str>>,
+    columns: Vec<Option<(String, pg_sys::Oid)>>,
+    row_id: Option<(pg_sys::Oid, pg_sys::AttrNumber)>,
+    assigned: Vec<bool>,
+) -> anyhow::Result<ModifyState> {
+    let names: Vec<_> = columns
+        .iter()
+        .map(|c| c.as_ref().map(|(name, _)| name.as_str()))
+        .collect();
+
+    let modify: Box<dyn TableModify> = match TableOptions::parse(options)? {
+        TableOptions::Stream { .. } => anyhow::bail!("Stream foreign tables are read-only"),
+        #[cfg(feature = "kv")]
+        TableOptions::Kv { bucket } => Box::new(kv::KvModify::new(bucket, &names)?),
+        #[cfg(feature = "object_store")]
+        TableOptions::ObjectStore { store } => {
+            Box::new(object_store::ObjectStoreModify::new(store, &names)?)
+        }
+    };
+
+    Ok(ModifyState {
+        columns,
+        row_id,
+        assigned,
+        modify,
+    })
+}
+
+/// Returns the name and the attribute of the row identity column of a writable foreign table.
+///
+/// # Safety
+/// `rel` must be an opened foreign table relation.
+unsafe fn row_id_attribute(
+    rel: pg_sys::Relation,
+) -> anyhow::Result<(CString, pg_sys::FormData_pg_attribute)> {
+    // SAFETY: the caller passes an opened foreign table relation.
+    let (options, tupdesc) = unsafe {
+        let table = pg_sys::GetForeignTable((*rel).rd_id);
+        (
+            parse_options_list((*table).options),
+            PgTupleDesc::from_pg_unchecked((*rel).rd_att),
+        )
+    };
+
+    let Some(name) = TableOptions::parse(&options)?.row_id_column() else {
+        anyhow::bail!("Foreign table is read-only");
+    };
+
+    let Some(attr) = tupdesc
+        .iter()
+        .find(|attr| !attr.is_dropped() && attr.name() == name)
+    else {
+        anyhow::bail!("Foreign table must have the '{name}' column to be modified");
+    };
+
+    Ok((CString::new(name)?, *attr))
+}
+
+/// Finds the row identity junk column added by `add_foreign_update_targets` in the subplan.
+///
+/// # Safety
+/// `mtstate` must be a valid modify state of the opened foreign table `rel`.
+unsafe fn row_id_junk_column(
+    mtstate: *mut pg_sys::ModifyTableState,
+    subplan_index: c_int,
+    rel: pg_sys::Relation,
+) -> anyhow::Result<(pg_sys::Oid, pg_sys::AttrNumber)> {
+    // SAFETY: the caller passes a valid modify state and relation.
+    unsafe {
+        let (name, attr) = row_id_attribute(rel)?;
+
+        #[cfg(feature = "pg13")]
+        let subplan = (*(*(*mtstate).mt_plans.add(usize::try_from(subplan_index)?))).plan;
+
+        #[cfg(not(feature = "pg13"))]
+        let subplan = {
+            let _ = subplan_index;
+            (*(*mtstate).ps.lefttree).plan
+        };
+
+        let attno = pg_sys::ExecFindJunkAttributeInTlist((*subplan).targetlist, name.as_ptr());
+        anyhow::ensure!(
+            attno > 0,
+            "Row identity column {name:?} is missing from the modify plan"
+        );
+
+        Ok((attr.atttypid, attno))
+    }
+}
+
+/// # Safety
+/// `rinfo` must be a result relation initialized by `begin_foreign_modify`.
+unsafe fn modify_state<'a>(
+    rinfo: *mut pg_sys::ResultRelInfo,
+) -> anyhow::Result<&'a mut ModifyState> {
+    // SAFETY: `ri_FdwState` is either null or the `ModifyState` set by `begin_foreign_modify`.
+    unsafe { ((*rinfo).ri_FdwState as *mut ModifyState).as_mut() }
+        .ok_or_else(|| anyhow::anyhow!("Foreign modify is not initialized"))
+}
+
+/// Reads all columns of `slot` into cells.
+///
+/// # Safety
+/// `slot` must be a valid slot holding a row with the given columns.
+unsafe fn read_slot(
+    slot: *mut pg_sys::TupleTableSlot,
+    columns: &[Option<(String, pg_sys::Oid)>],
+) -> anyhow::Result<Vec<Cell>> {
+    // SAFETY: all attributes are deformed before `tts_values` and `tts_isnull` are read.
+    unsafe {
+        let natts = (*(*slot).tts_tupleDescriptor).natts;
+        slot_getsomeattrs(slot, natts);
+
+        let natts = usize::try_from(natts)?;
+        let values = std::slice::from_raw_parts((*slot).tts_values, natts);
+        let nulls = std::slice::from_raw_parts((*slot).tts_isnull, natts);
+
+        values
+            .iter()
+            .zip(nulls)
+            .zip(columns)
+            .map(|((value, isnull), column)| match column {
+                Some((name, type_oid)) => Cell::from_column_datum(*value, *isnull, *type_oid, name),
+                None => Ok(Cell::Null),
+            })
+            .collect()
+    }
+}
+
+/// Reads the row identity junk column from the plan slot.
+///
+/// # Safety
+/// `plan_slot` must be a valid slot produced by the modify subplan.
+unsafe fn read_row_id(
+    plan_slot: *mut pg_sys::TupleTableSlot,
+    row_id: Option<(pg_sys::Oid, pg_sys::AttrNumber)>,
+) -> anyhow::Result<Cell> {
+    let Some((type_oid, attno)) = row_id else {
+        anyhow::bail!("Foreign modify has no row identity column");
+    };
+
+    // SAFETY: the attributes up to `attno` are deformed before being read.
+    unsafe {
+        slot_getsomeattrs(plan_slot, attno.into());
+
+        let index = usize::try_from(attno - 1)?;
+        let value = *(*plan_slot).tts_values.add(index);
+        let isnull = *(*plan_slot).tts_isnull.add(index);
+
+        Cell::from_column_datum(value, isnull, type_oid, "row identity")
+    }
+}
+
+/// Stores the next row of the scan into `slot`, returns `false` once the scan is exhausted.
+///
+/// # Safety
+/// `slot` must be a valid slot with one value per column of the scanned relation.
+unsafe fn fill_slot(
+    state: &mut ScanState,
+    slot: *mut pg_sys::TupleTableSlot,
+) -> anyhow::Result<bool> {
+    let Some(row) = state.scan.next_row()? else {
+        return Ok(false);
+    };
+
+    // SAFETY: `tts_values` and `tts_isnull` hold exactly `natts` elements.
+    let (values, nulls) = unsafe {
+        let natts = usize::try_from((*(*slot).tts_tupleDescriptor).natts)?;
+        (
+            std::slice::from_raw_parts_mut((*slot).tts_values, natts),
+            std::slice::from_raw_parts_mut((*slot).tts_isnull, natts),
+        )
+    };
+
+    for (((value, isnull), column), cell) in values
+        .iter_mut()
+        .zip(nulls.iter_mut())
+        .zip(&state.columns)
+        .zip(row)
+    {
+        let datum = match column {
+            Some((name, type_oid)) => cell.into_column_datum(*type_oid, name)?,
+            None => None,
+        };
+
+        *isnull = datum.is_none();
+        *value = datum.unwrap_or(pg_sys::Datum::from(0));
+    }
+
+    Ok(true)
+}
+
+/// Equivalent of the `slot_getsomeattrs` inline function of Postgres.
+///
+/// # Safety
+/// `slot` must be a valid tuple table slot with at least `attnum` attributes.
+unsafe fn slot_getsomeattrs(slot: *mut pg_sys::TupleTableSlot, attnum: c_int) {
+    // SAFETY: attributes are deformed only when they are not valid yet.
+    unsafe {
+        if c_int::from((*slot).tts_nvalid) < attnum {
+            pg_sys::slot_getsomeattrs_int(slot, attnum);
+        }
+    }
+}
+
+/// Equivalent of the `ExecClearTuple` inline function of Postgres.
+///
+/// # Safety
+/// `slot` must be a valid tuple table slot.
+unsafe fn exec_clear_tuple(slot: *mut pg_sys::TupleTableSlot) {
+    // SAFETY: every slot has a valid `tts_ops` table.
+    unsafe {
+        if let Some(clear) = (*(*slot).tts_ops).clear {
+            clear(slot);
+        }
+    }
+}
+
+#[cfg(any(feature = "pg13", feature = "pg14", feature = "pg15", feature = "pg16"))]
+unsafe fn create_foreign_path(
+    root: *mut pg_sys::PlannerInfo,
+    baserel: *mut pg_sys::RelOptInfo,
+    rows: f64,
+    startup_cost: f64,
+    total_cost: f64,
+) -> *mut pg_sys::ForeignPath {
+    // SAFETY: the caller passes valid planner structures.
+    unsafe {
+        pg_sys::create_foreignscan_path(
+            root,
+            baserel,
+            std::ptr::null_mut(),
+            rows,
+            startup_cost,
+            total_cost,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        )
+    }
+}
+
+#[cfg(feature = "pg17")]
+unsafe fn create_foreign_path(
+    root: *mut pg_sys::PlannerInfo,
+    baserel: *mut pg_sys::RelOptInfo,
+    rows: f64,
+    startup_cost: f64,
+    total_cost: f64,
+) -> *mut pg_sys::ForeignPath {
+    // SAFETY: the caller passes valid planner structures.
+    unsafe {
+        pg_sys::create_foreignscan_path(
+            root,
+            baserel,
+            std::ptr::null_mut(),
+            rows,
+            startup_cost,
+            total_cost,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        )
+    }
+}
+
+#[cfg(feature = "pg18")]
+unsafe fn create_foreign_path(
+    root: *mut pg_sys::PlannerInfo,
+    baserel: *mut pg_sys::RelOptInfo,
+    rows: f64,
+    startup_cost: f64,
+    total_cost: f64,
+) -> *mut pg_sys::ForeignPath {
+    // SAFETY: the caller passes valid planner structures.
+    unsafe {
+        pg_sys::create_foreignscan_path(
+            root,
+            baserel,
+            std::ptr::null_mut(),
+            rows,
+            0,
+            startup_cost,
+            total_cost,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        )
+    }
+}
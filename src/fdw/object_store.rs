@@ -0,0 +1,354 @@
+use std::collections::HashMap;
+
+use async_nats::jetstream::object_store::{InfoErrorKind, List, ObjectInfo};
+use futures::StreamExt;
+
+use crate::{
+    ctx::CTX,
+    fdw::{
+        pushdown::{Qual, QualValue},
+        Cell, TableModify, TableScan,
+    },
+    nats_client::{FileMeta, NatsClient},
+    utils::unix_nanos_to_pg_timestamp,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ObjectStoreColumn {
+    Name,
+    Size,
+    Digest,
+    Modified,
+    Metadata,
+    Content,
+}
+
+impl ObjectStoreColumn {
+    pub fn from_name(name: &str) -> anyhow::Result<Self> {
+        match name {
+            "name" => Ok(Self::Name),
+            "size" => Ok(Self::Size),
+            "digest" => Ok(Self::Digest),
+            "modified" => Ok(Self::Modified),
+            "metadata" => Ok(Self::Metadata),
+            "content" => Ok(Self::Content),
+            _ => Err(anyhow::anyhow!(
+                "Unknown column '{name}' for an object store foreign table, expected one of: name, size, digest, modified, metadata, content"
+            )),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Name => "name",
+            Self::Size => "size",
+            Self::Digest => "digest",
+            Self::Modified => "modified",
+            Self::Metadata => "metadata",
+            Self::Content => "content",
+        }
+    }
+}
+
+/// Columns computed by the object store, which can not be written.
+const READ_ONLY_COLUMNS: [ObjectStoreColumn; 3] = [
+    ObjectStoreColumn::Size,
+    ObjectStoreColumn::Digest,
+    ObjectStoreColumn::Modified,
+];
+
+/// Scan over the files of an object store.
+///
+/// File content is downloaded only when the `content` column is read by the query.
+pub struct ObjectStoreScan {
+    store: String,
+    name: Option<String>,
+    columns: Vec<Option<ObjectStoreColumn>>,
+    files: Option<List>,
+    done: bool,
+}
+
+impl ObjectStoreScan {
+    pub fn new(store: String, columns: &[Option<&str>], quals: &[Qual]) -> anyhow::Result<Self> {
+        let columns = columns
+            .iter()
+            .map(|c| c.map(ObjectStoreColumn::from_name).transpose())
+            .collect::<anyhow::Result<_>>()?;
+
+        let name = quals.iter().find_map(|qual| {
+            match (qual.column.as_str(), qual.op.as_str(), &qual.value) {
+                ("name", "=", QualValue::Text(name)) => Some(name.clone()),
+                _ => None,
+            }
+        });
+
+        Ok(Self {
+            store,
+            name,
+            columns,
+            files: None,
+            done: false,
+        })
+    }
+
+    async fn next_file(&mut self, nats: &mut NatsClient) -> anyhow::Result<Option<ObjectInfo>> {
+        if let Some(name) = &self.name {
+            self.done = true;
+
+            return match nats.get_file_info(&self.store, name).await {
+                Ok(info) => Ok((!info.deleted).then_some(info)),
+                Err(err)
+                    if err
+                        .downcast_ref::<async_nats::jetstream::object_store::InfoError>()
+                        .is_some_and(|err| err.kind() == InfoErrorKind::NotFound) =>
+                {
+                    Ok(None)
+                }
+                Err(err) => Err(err),
+            };
+        }
+
+        if self.files.is_none() {
+            self.files = Some(nats.list_files(&self.store).await?);
+        }
+
+        let Some(files) = self.files.as_mut() else {
+            return Ok(None);
+        };
+
+        files.next().await.transpose().map_err(Into::into)
+    }
+
+    async fn to_row(&self, info: ObjectInfo, nats: &mut NatsClient) -> anyhow::Result<Vec<Cell>> {
+        let mut row = Vec::with_capacity(self.columns.len());
+
+        for column in &self.columns {
+            let cell = match column {
+                Some(ObjectStoreColumn::Name) => Cell::Text(info.name.clone()),
+                Some(ObjectStoreColumn::Size) => {
+                    Cell::Int(i64::try_from(info.size).unwrap_or(i64::MAX))
+                }
+                Some(ObjectStoreColumn::Digest) => {
+                    info.digest.clone().map(Cell::Text).unwrap_or(Cell::Null)
+                }
+                Some(ObjectStoreColumn::Modified) => info
+                    .modified
+                    .map(|v| Cell::Timestamp(unix_nanos_to_pg_timestamp(v.unix_timestamp_nanos())))
+                    .unwrap_or(Cell::Null),
+                Some(ObjectStoreColumn::Metadata) => {
+                    Cell::Json(serde_json::to_value(&info.metadata)?)
+                }
+                Some(ObjectStoreColumn::Content) => {
+                    Cell::Bytes(nats.get_file(&self.store, &info.name).await?)
+                }
+                None => Cell::Null,
+            };
+
+            row.push(cell);
+        }
+
+        Ok(row)
+    }
+}
+
+impl TableScan for ObjectStoreScan {
+    fn next_row(&mut self) -> anyhow::Result<Option<Vec<Cell>>> {
+        if self.done {
+            return Ok(None);
+        }
+
+        CTX.with_borrow_mut(|ctx| {
+            let nats = &mut ctx.nats_connection;
+
+            ctx.rt.block_on(async {
+                let Some(info) = self.next_file(nats).await? else {
+                    self.end();
+                    return Ok(None);
+                };
+
+                self.to_row(info, nats).await.map(Some)
+            })
+        })
+    }
+
+    fn rescan(&mut self) {
+        self.files = None;
+        self.done = false;
+    }
+
+    fn end(&mut self) {
+        self.files = None;
+        self.done = true;
+    }
+}
+
+/// Writes rows of an object store foreign table as uploads and deletes of files.
+pub struct ObjectStoreModify {
+    store: String,
+    columns: Vec<Option<ObjectStoreColumn>>,
+}
+
+impl ObjectStoreModify {
+    pub fn new(store: String, columns: &[Option<&str>]) -> anyhow::Result<Self> {
+        let columns = columns
+            .iter()
+            .map(|c| c.map(ObjectStoreColumn::from_name).transpose())
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self { store, columns })
+    }
+
+    fn take(&self, row: &mut [Cell], column: ObjectStoreColumn) -> Cell {
+        self.columns
+            .iter()
+            .zip(row)
+            .find(|(c, _)| **c == Some(column))
+            .map(|(_, cell)| std::mem::replace(cell, Cell::Null))
+            .unwrap_or(Cell::Null)
+    }
+
+    fn is_assigned(&self, assigned: &[bool], column: ObjectStoreColumn) -> bool {
+        self.columns
+            .iter()
+            .zip(assigned)
+            .any(|(c, assigned)| *c == Some(column) && *assigned)
+    }
+
+    fn upload(&self, name: String, content: Cell, meta: FileMeta) -> anyhow::Result<()> {
+        let Cell::Bytes(content) = content else {
+            anyhow::bail!("Column 'content' of an object store foreign table must not be NULL");
+        };
+
+        let _ = CTX.with_borrow_mut(|ctx| {
+            ctx.rt.block_on(ctx.nats_connection.put_file_with_meta(
+                &self.store,
+                name,
+                content,
+                meta,
+            ))
+        })?;
+
+        Ok(())
+    }
+}
+
+impl TableModify for ObjectStoreModify {
+    fn insert(&mut self, mut row: Vec<Cell>) -> anyhow::Result<()> {
+        let Cell::Text(name) = self.take(&mut row, ObjectStoreColumn::Name) else {
+            anyhow::bail!("Column 'name' of an object store foreign table must not be NULL");
+        };
+
+        for column in READ_ONLY_COLUMNS {
+            if !matches!(self.take(&mut row, column), Cell::Null) {
+                anyhow::bail!(
+                    "Column '{}' of an object store foreign table is read-only",
+                    column.name()
+                );
+            }
+        }
+
+        let meta = FileMeta {
+            metadata: metadata_from_cell(self.take(&mut row, ObjectStoreColumn::Metadata))?,
+            ..Default::default()
+        };
+
+        let content = self.take(&mut row, ObjectStoreColumn::Content);
+        self.upload(name, content, meta)
+    }
+
+    fn update(
+        &mut self,
+        row_id: Cell,
+        mut row: Vec<Cell>,
+        assigned: &[bool],
+    ) -> anyhow::Result<()> {
+        let Cell::Text(name) = row_id else {
+            anyhow::bail!("Row of an object store foreign table has no name");
+        };
+
+        if let Cell::Text(new_name) = self.take(&mut row, ObjectStoreColumn::Name) {
+            anyhow::ensure!(
+                new_name == name,
+                "Renaming files of an object store foreign table is not supported"
+            );
+        }
+
+        for column in READ_ONLY_COLUMNS {
+            if self.is_assigned(assigned, column) {
+                anyhow::bail!(
+                    "Column '{}' of an object store foreign table is read-only",
+                    column.name()
+                );
+            }
+        }
+
+        let metadata = self
+            .is_assigned(assigned, ObjectStoreColumn::Metadata)
+            .then(|| metadata_from_cell(self.take(&mut row, ObjectStoreColumn::Metadata)))
+            .transpose()?;
+
+        if self.is_assigned(assigned, ObjectStoreColumn::Content) {
+            // Uploading replaces the whole file, so the fields the UPDATE does not set
+            // are carried over from the current version.
+            let current = CTX.with_borrow_mut(|ctx| {
+                ctx.rt
+                    .block_on(ctx.nats_connection.get_file_info(&self.store, &name))
+            })?;
+
+            let meta = FileMeta {
+                description: current.description,
+                metadata: metadata.unwrap_or(current.metadata),
+                headers: current.headers,
+            };
+
+            let content = self.take(&mut row, ObjectStoreColumn::Content);
+            return self.upload(name, content, meta);
+        }
+
+        let Some(metadata) = metadata else {
+            return Ok(());
+        };
+
+        let _ = CTX.with_borrow_mut(|ctx| {
+            ctx.rt.block_on(ctx.nats_connection.update_file_meta(
+                &self.store,
+                name,
+                None,
+                None,
+                Some(metadata),
+                None,
+            ))
+        })?;
+
+        Ok(())
+    }
+
+    fn delete(&mut self, row_id: Cell) -> anyhow::Result<()> {
+        let Cell::Text(name) = row_id else {
+            anyhow::bail!("Row of an object store foreign table has no name");
+        };
+
+        CTX.with_borrow_mut(|ctx| {
+            ctx.rt
+                .block_on(ctx.nats_connection.delete_file(&self.store, name))
+        })
+    }
+}
+
+/// Converts a `metadata` cell into file metadata, NULL clears it.
+fn metadata_from_cell(cell: Cell) -> anyhow::Result<HashMap<String, String>> {
+    let value = match cell {
+        Cell::Null => return Ok(HashMap::new()),
+        Cell::Json(value) => value,
+        Cell::Text(text) => serde_json::from_str(&text)?,
+        cell => anyhow::bail!(
+            "Column 'metadata' of an object store foreign table has an unsupported value {cell:?}"
+        ),
+    };
+
+    serde_json::from_value(value).map_err(|_| {
+        anyhow::anyhow!(
+            "Column 'metadata' of an object store foreign table must be a JSON object with string values"
+        )
+    })
+}
@@ -0,0 +1,112 @@
+use std::{borrow::Cow, collections::HashMap};
+
+pub const STREAM_OPTION: &str = "stream";
+pub const FILTER_SUBJECT_OPTION: &str = "filter_subject";
+#[cfg(feature = "kv")]
+pub const BUCKET_OPTION: &str = "bucket";
+#[cfg(feature = "object_store")]
+pub const OBJECT_STORE_OPTION: &str = "object_store";
+
+/// Options selecting the backing resource of a table, at least one must be specified.
+const RESOURCE_OPTIONS: &[&str] = &[
+    STREAM_OPTION,
+    #[cfg(feature = "kv")]
+    BUCKET_OPTION,
+    #[cfg(feature = "object_store")]
+    OBJECT_STORE_OPTION,
+];
+
+/// Options of a `pgnats_fdw` foreign table, describing what the table is backed by.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TableOptions {
+    /// Messages stored in a JetStream stream.
+    Stream {
+        stream: String,
+        filter_subject: Option<String>,
+    },
+    /// Latest revisions of the keys of a KV bucket.
+    #[cfg(feature = "kv")]
+    Kv { bucket: String },
+    /// Files of an object store.
+    #[cfg(feature = "object_store")]
+    ObjectStore { store: String },
+}
+
+impl TableOptions {
+    pub fn parse(options: &HashMap<Cow<'_, str>, Cow<'_, str>>) -> anyhow::Result<Self> {
+        if let Some(stream) = options.get(STREAM_OPTION) {
+            check_known_options(options, &[STREAM_OPTION, FILTER_SUBJECT_OPTION])?;
+
+            anyhow::ensure!(
+                !stream.is_empty(),
+                "Option '{STREAM_OPTION}' must not be empty"
+            );
+
+            return Ok(Self::Stream {
+                stream: stream.to_string(),
+                filter_subject: options
+                    .get(FILTER_SUBJECT_OPTION)
+                    .map(|v| v.to_string())
+                    .filter(|v| !v.is_empty()),
+            });
+        }
+
+        #[cfg(feature = "kv")]
+        if let Some(bucket) = options.get(BUCKET_OPTION) {
+            check_known_options(options, &[BUCKET_OPTION])?;
+
+            anyhow::ensure!(
+                !bucket.is_empty(),
+                "Option '{BUCKET_OPTION}' must not be empty"
+            );
+
+            return Ok(Self::Kv {
+                bucket: bucket.to_string(),
+            });
+        }
+
+        #[cfg(feature = "object_store")]
+        if let Some(store) = options.get(OBJECT_STORE_OPTION) {
+            check_known_options(options, &[OBJECT_STORE_OPTION])?;
+
+            anyhow::ensure!(
+                !store.is_empty(),
+                "Option '{OBJECT_STORE_OPTION}' must not be empty"
+            );
+
+            return Ok(Self::ObjectStore {
+                store: store.to_string(),
+            });
+        }
+
+        Err(anyhow::anyhow!(
+            "Foreign table must specify one of the options: {}",
+            RESOURCE_OPTIONS.join(", ")
+        ))
+    }
+
+    /// Column identifying rows modified by `UPDATE` and `DELETE`, `None` for read-only tables.
+    pub fn row_id_column(&self) -> Option<&'static str> {
+        match self {
+            Self::Stream { .. } => None,
+            #[cfg(feature = "kv")]
+            Self::Kv { .. } => Some("key"),
+            #[cfg(feature = "object_store")]
+            Self::ObjectStore { .. } => Some("name"),
+        }
+    }
+}
+
+fn check_known_options(
+    options: &HashMap<Cow<'_, str>, Cow<'_, str>>,
+    known: &[&str],
+) -> anyhow::Result<()> {
+    if let Some(unknown) = options.keys().find(|k| !known.contains(&k.as_ref())) {
+        anyhow::bail!(
+            "Unknown foreign table option '{unknown}', expected one of: {}",
+            known.join(", ")
+        );
+    }
+
+    Ok(())
+}
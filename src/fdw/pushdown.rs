@@ -0,0 +1,237 @@
+use std::ffi::{CStr, CString};
+
+use pgrx::{pg_sys, FromDatum, PgList};
+use serde::{Deserialize, Serialize};
+
+/// Constant operand of a restriction clause that can be pushed down to NATS.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum QualValue {
+    Int(i64),
+    Text(String),
+    /// Postgres timestamp in microseconds since 2000-01-01.
+    Timestamp(i64),
+}
+
+/// Restriction clause of the form `column <op> constant`.
+///
+/// Pushed down clauses are still rechecked by Postgres, so a scan is free to
+/// use them only as a hint narrowing down what is fetched from NATS.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Qual {
+    pub column: String,
+    pub op: String,
+    pub value: QualValue,
+}
+
+/// Part of a foreign scan plan passed from the planner to the executor in `fdw_private`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScanPlan {
+    pub quals: Vec<Qual>,
+    /// Attribute numbers read by the query, `None` when the whole row is needed.
+    pub attrs: Option<Vec<i32>>,
+}
+
+impl ScanPlan {
+    /// Returns `true` if the attribute `attnum` is read by the query.
+    pub fn uses_attr(&self, attnum: i16) -> bool {
+        self.attrs
+            .as_ref()
+            .is_none_or(|attrs| attrs.contains(&i32::from(attnum)))
+    }
+}
+
+/// Extracts `column <op> constant` clauses from the restrictions of a foreign relation.
+///
+/// # Safety
+/// `baserel` must be a valid `RelOptInfo` of the foreign table `relid`.
+pub unsafe fn extract_quals(baserel: *mut pg_sys::RelOptInfo, relid: pg_sys::Oid) -> Vec<Qual> {
+    // SAFETY: `baserestrictinfo` is a (possibly empty) list of `RestrictInfo` nodes.
+    unsafe {
+        let scan_relid = (*baserel).relid;
+        let list = PgList::<pg_sys::RestrictInfo>::from_pg((*baserel).baserestrictinfo);
+
+        list.iter_ptr()
+            .filter(|info| !info.is_null())
+            .filter_map(|info| extract_qual((*info).clause.cast(), scan_relid, relid))
+            .collect()
+    }
+}
+
+/// Collects attribute numbers of the foreign relation used by its target list and `clauses`.
+///
+/// # Safety
+/// `baserel` must be a valid `RelOptInfo` and `clauses` a list of its expressions.
+pub unsafe fn extract_attrs(
+    baserel: *mut pg_sys::RelOptInfo,
+    clauses: *mut pg_sys::List,
+) -> Option<Vec<i32>> {
+    let mut attrs: *mut pg_sys::Bitmapset = std::ptr::null_mut();
+    let mut result = vec![];
+
+    // SAFETY: the target list and clauses are valid expression trees of `baserel`.
+    unsafe {
+        let relid = (*baserel).relid;
+        pg_sys::pull_varattnos((*(*baserel).reltarget).exprs.cast(), relid, &mut attrs);
+        pg_sys::pull_varattnos(clauses.cast(), relid, &mut attrs);
+
+        let mut member = pg_sys::bms_next_member(attrs, -1);
+        while member >= 0 {
+            let attnum = member + pg_sys::FirstLowInvalidHeapAttributeNumber;
+
+            // Whole-row reference
+            if attnum == 0 {
+                return None;
+            }
+
+            result.push(attnum);
+            member = pg_sys::bms_next_member(attrs, member);
+        }
+    }
+
+    Some(result)
+}
+
+/// Serializes a scan plan into a `fdw_private` list of a `ForeignScan` plan.
+///
+/// # Safety
+/// Must be called inside a Postgres memory context, the list is `palloc`ed.
+pub unsafe fn into_fdw_private(plan: &ScanPlan) -> *mut pg_sys::List {
+    let json = serde_json::to_string(plan)
+        .ok()
+        .and_then(|v| CString::new(v).ok())
+        .unwrap_or_else(|| c"{}".to_owned());
+
+    // SAFETY: `json` is a valid null-terminated string copied into Postgres memory.
+    unsafe {
+        let value = pg_sys::makeString(pg_sys::pstrdup(json.as_ptr()));
+        pg_sys::lappend(std::ptr::null_mut(), value.cast())
+    }
+}
+
+/// Restores a scan plan stored by [`into_fdw_private`].
+///
+/// # Safety
+/// `list` must be null or the `fdw_private` list built by [`into_fdw_private`].
+pub unsafe fn from_fdw_private(list: *mut pg_sys::List) -> ScanPlan {
+    // SAFETY: the first list element is a `String` node holding the serialized plan.
+    let json = unsafe {
+        let list = PgList::<pg_sys::Node>::from_pg(list);
+
+        let Some(node) = list.get_ptr(0) else {
+            return ScanPlan::default();
+        };
+
+        #[cfg(any(feature = "pg13", feature = "pg14"))]
+        let val = (*(node as *mut pg_sys::Value)).val.str_;
+
+        #[cfg(not(any(feature = "pg13", feature = "pg14")))]
+        let val = (*(node as *mut pg_sys::String)).sval;
+
+        if val.is_null() {
+            return ScanPlan::default();
+        }
+
+        CStr::from_ptr(val).to_string_lossy()
+    };
+
+    serde_json::from_str(&json).unwrap_or_default()
+}
+
+unsafe fn extract_qual(
+    expr: *mut pg_sys::Node,
+    scan_relid: pg_sys::Index,
+    relid: pg_sys::Oid,
+) -> Option<Qual> {
+    // SAFETY: nodes are checked with `is_a` before being cast to a concrete type.
+    unsafe {
+        if expr.is_null() || !pgrx::is_a(expr, pg_sys::NodeTag::T_OpExpr) {
+            return None;
+        }
+
+        let op_expr = expr as *mut pg_sys::OpExpr;
+        let args = PgList::<pg_sys::Node>::from_pg((*op_expr).args);
+
+        if args.len() != 2 {
+            return None;
+        }
+
+        let (left, right) = (args.get_ptr(0)?, args.get_ptr(1)?);
+
+        let opname = pg_sys::get_opname((*op_expr).opno);
+        if opname.is_null() {
+            return None;
+        }
+        let opname = CStr::from_ptr(opname).to_str().ok()?;
+
+        let (var, constant, op) = if pgrx::is_a(left, pg_sys::NodeTag::T_Var)
+            && pgrx::is_a(right, pg_sys::NodeTag::T_Const)
+        {
+            (
+                left as *mut pg_sys::Var,
+                right as *mut pg_sys::Const,
+                opname,
+            )
+        } else if pgrx::is_a(left, pg_sys::NodeTag::T_Const)
+            && pgrx::is_a(right, pg_sys::NodeTag::T_Var)
+        {
+            (
+                right as *mut pg_sys::Var,
+                left as *mut pg_sys::Const,
+                commute(opname)?,
+            )
+        } else {
+            return None;
+        };
+
+        if i64::from((*var).varno) != i64::from(scan_relid)
+            || (*var).varlevelsup != 0
+            || (*var).varattno <= 0
+            || (*constant).constisnull
+        {
+            return None;
+        }
+
+        let column = pg_sys::get_attname(relid, (*var).varattno, true);
+        if column.is_null() {
+            return None;
+        }
+        let column = CStr::from_ptr(column).to_string_lossy().to_string();
+
+        let datum = (*constant).constvalue;
+        let value = match (*constant).consttype {
+            pg_sys::INT2OID => {
+                QualValue::Int(i16::from_polymorphic_datum(datum, false, pg_sys::INT2OID)?.into())
+            }
+            pg_sys::INT4OID => {
+                QualValue::Int(i32::from_polymorphic_datum(datum, false, pg_sys::INT4OID)?.into())
+            }
+            pg_sys::INT8OID => {
+                QualValue::Int(i64::from_polymorphic_datum(datum, false, pg_sys::INT8OID)?)
+            }
+            pg_sys::TEXTOID | pg_sys::VARCHAROID => QualValue::Text(
+                String::from_polymorphic_datum(datum, false, (*constant).consttype)?,
+            ),
+            pg_sys::TIMESTAMPTZOID => {
+                QualValue::Timestamp(i64::from_polymorphic_datum(datum, false, pg_sys::INT8OID)?)
+            }
+            _ => return None,
+        };
+
+        Some(Qual {
+            column,
+            op: op.to_string(),
+            value,
+        })
+    }
+}
+
+fn commute(op: &str) -> Option<&'static str> {
+    match op {
+        "=" => Some("="),
+        "<" => Some(">"),
+        "<=" => Some(">="),
+        ">" => Some("<"),
+        ">=" => Some("<="),
+        _ => None,
+    }
+}
@@ -0,0 +1,236 @@
+use std::time::Duration;
+
+use async_nats::jetstream::consumer::{pull::Ordered, DeliverPolicy};
+use futures::StreamExt;
+
+use crate::{
+    ctx::CTX,
+    fdw::{
+        pushdown::{Qual, QualValue},
+        Cell, TableScan,
+    },
+    nats_client::NatsClient,
+    utils::{headers_to_json, pg_timestamp_to_unix_nanos, unix_nanos_to_pg_timestamp},
+};
+
+/// How long a scan waits for the next message it expects before it fails, so that a stalled
+/// server cannot pass a truncated scan off as a complete one.
+const STREAM_SCAN_IDLE_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum StreamColumn {
+    Seq,
+    Subject,
+    Time,
+    Headers,
+    Payload,
+}
+
+impl StreamColumn {
+    fn from_name(name: &str) -> anyhow::Result<Self> {
+        match name {
+            "seq" => Ok(Self::Seq),
+            "subject" => Ok(Self::Subject),
+            "time" => Ok(Self::Time),
+            "headers" => Ok(Self::Headers),
+            "payload" => Ok(Self::Payload),
+            _ => Err(anyhow::anyhow!(
+                "Unknown column '{name}' for a stream foreign table, expected one of: seq, subject, time, headers, payload"
+            )),
+        }
+    }
+}
+
+/// Scan over the messages of a JetStream stream through an ephemeral ordered consumer.
+pub struct StreamScan {
+    stream: String,
+    filter_subject: Option<String>,
+    deliver_policy: DeliverPolicy,
+    last_seq: Option<u64>,
+    columns: Vec<Option<StreamColumn>>,
+    messages: Option<Ordered>,
+    empty: bool,
+    done: bool,
+}
+
+impl StreamScan {
+    pub fn new(
+        stream: String,
+        filter_subject: Option<String>,
+        columns: &[Option<&str>],
+        quals: &[Qual],
+    ) -> anyhow::Result<Self> {
+        let columns = columns
+            .iter()
+            .map(|c| c.map(StreamColumn::from_name).transpose())
+            .collect::<anyhow::Result<_>>()?;
+
+        let mut start_seq: Option<u64> = None;
+        let mut last_seq: Option<u64> = None;
+        let mut start_time: Option<i64> = None;
+        let mut subject: Option<String> = None;
+
+        for qual in quals {
+            match (qual.column.as_str(), qual.op.as_str(), &qual.value) {
+                ("seq", op, QualValue::Int(v)) => {
+                    let v = u64::try_from(*v).unwrap_or(0);
+
+                    match op {
+                        ">" => start_seq = start_seq.max(Some(v.saturating_add(1))),
+                        ">=" => start_seq = start_seq.max(Some(v)),
+                        "<" => last_seq = min_some(last_seq, v.saturating_sub(1)),
+                        "<=" => last_seq = min_some(last_seq, v),
+                        "=" => {
+                            start_seq = start_seq.max(Some(v));
+                            last_seq = min_some(last_seq, v);
+                        }
+                        _ => {}
+                    }
+                }
+                ("time", ">" | ">=", QualValue::Timestamp(v)) => {
+                    start_time = start_time.max(Some(*v));
+                }
+                ("subject", "=", QualValue::Text(v)) if filter_subject.is_none() => {
+                    if !v.contains(['*', '>']) {
+                        subject = Some(v.clone());
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let deliver_policy = if let Some(start_sequence) = start_seq.filter(|v| *v > 0) {
+            DeliverPolicy::ByStartSequence { start_sequence }
+        } else if let Some(start_time) = start_time.and_then(|v| {
+            time::OffsetDateTime::from_unix_timestamp_nanos(pg_timestamp_to_unix_nanos(v)).ok()
+        }) {
+            DeliverPolicy::ByStartTime { start_time }
+        } else {
+            DeliverPolicy::All
+        };
+
+        // Contradicting bounds on `seq` cannot match any message
+        let empty = last_seq == Some(0) || start_seq.zip(last_seq).is_some_and(|(s, l)| s > l);
+
+        Ok(Self {
+            stream,
+            filter_subject: filter_subject.or(subject),
+            deliver_policy,
+            empty,
+            done: empty,
+            last_seq,
+            columns,
+            messages: None,
+        })
+    }
+
+    async fn next_message(
+        &mut self,
+        nats: &mut NatsClient,
+    ) -> anyhow::Result<Option<async_nats::jetstream::Message>> {
+        if self.messages.is_none() {
+            let (pending, messages) = nats
+                .read_stream(
+                    &self.stream,
+                    self.filter_subject.clone(),
+                    self.deliver_policy,
+                )
+                .await?;
+
+            if pending == 0 {
+                self.done = true;
+                return Ok(None);
+            }
+
+            self.messages = Some(messages);
+        }
+
+        let Some(messages) = self.messages.as_mut() else {
+            return Ok(None);
+        };
+
+        // The scan ends on the last pending message, so any message missing before that
+        // would silently truncate the result.
+        match tokio::time::timeout(STREAM_SCAN_IDLE_TIMEOUT, messages.next()).await {
+            Ok(Some(message)) => Ok(Some(message?)),
+            Ok(None) => Err(anyhow::anyhow!(
+                "Stream '{}' closed before the scan reached its last message",
+                self.stream
+            )),
+            Err(_) => Err(anyhow::anyhow!(
+                "Timed out after {} seconds waiting for the next message of stream '{}'",
+                STREAM_SCAN_IDLE_TIMEOUT.as_secs(),
+                self.stream
+            )),
+        }
+    }
+
+    fn to_row(&self, message: async_nats::jetstream::Message) -> anyhow::Result<Vec<Cell>> {
+        let info = message.info().map_err(|err| anyhow::anyhow!("{err}"))?;
+        let seq = info.stream_sequence;
+        let time = unix_nanos_to_pg_timestamp(info.published.unix_timestamp_nanos());
+
+        let message = message.message;
+
+        Ok(self
+            .columns
+            .iter()
+            .map(|column| match column {
+                Some(StreamColumn::Seq) => Cell::Int(i64::try_from(seq).unwrap_or(i64::MAX)),
+                Some(StreamColumn::Subject) => Cell::Text(message.subject.to_string()),
+                Some(StreamColumn::Time) => Cell::Timestamp(time),
+                Some(StreamColumn::Headers) => message
+                    .headers
+                    .as_ref()
+                    .map(|h| Cell::Json(headers_to_json(h)))
+                    .unwrap_or(Cell::Null),
+                Some(StreamColumn::Payload) => Cell::Bytes(message.payload.to_vec()),
+                None => Cell::Null,
+            })
+            .collect())
+    }
+}
+
+impl TableScan for StreamScan {
+    fn next_row(&mut self) -> anyhow::Result<Option<Vec<Cell>>> {
+        if self.done {
+            return Ok(None);
+        }
+
+        let message = CTX
+            .with_borrow_mut(|ctx| ctx.rt.block_on(self.next_message(&mut ctx.nats_connection)))?;
+
+        let Some(message) = message else {
+            self.end();
+            return Ok(None);
+        };
+
+        let info = message.info().map_err(|err| anyhow::anyhow!("{err}"))?;
+        let (seq, pending) = (info.stream_sequence, info.pending);
+
+        if self.last_seq.is_some_and(|last| seq > last) {
+            self.end();
+            return Ok(None);
+        }
+
+        if pending == 0 || self.last_seq == Some(seq) {
+            self.end();
+        }
+
+        self.to_row(message).map(Some)
+    }
+
+    fn rescan(&mut self) {
+        self.messages = None;
+        self.done = self.empty;
+    }
+
+    fn end(&mut self) {
+        self.messages = None;
+        self.done = true;
+    }
+}
+
+fn min_some(current: Option<u64>, v: u64) -> Option<u64> {
+    Some(current.map_or(v, |c| c.min(v)))
+}
@@ -25,8 +25,11 @@
 
 mod pg_tests;
 
+mod codec;
+mod fdw;
 mod init;
 mod log;
+mod subject;
 mod utils;
 
 pub mod api;
@@ -2,21 +2,388 @@ use std::{collections::HashMap, io::Cursor, time::Duration};
 
 use async_nats::{
     jetstream::{
-        kv::Store,
-        object_store::{ObjectInfo, ObjectStore},
-        Context,
+        consumer::{
+            self,
+            pull::{Ordered, OrderedConfig},
+            DeliverPolicy,
+        },
+        kv::{CreateErrorKind, Entry, Status, Store, UpdateErrorKind},
+        message::StreamMessage,
+        object_store::{List, Object, ObjectInfo, ObjectMetadata, ObjectStore, UpdateMetadata},
+        stream::{self, StorageType},
+        AckKind, Context,
     },
-    Client, Request,
+    Client, HeaderMap, Request,
 };
 
 use futures::StreamExt;
-use tokio::io::{AsyncReadExt, BufReader};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, BufReader, ReadBuf},
+    sync::mpsc,
+};
 
 use crate::{
     config::{Config, NatsTlsOptions},
+    subject::validate_subject,
     utils::{extract_headers, FromBytes, ToBytes},
 };
 
+/// Header marking deleted and purged revisions of a KV key.
+pub const KV_OPERATION_HEADER: &str = "KV-Operation";
+
+/// Prefix of the reply subjects used to acknowledge JetStream messages.
+const ACK_SUBJECT_PREFIX: &str = "$JS.ACK.";
+
+/// Header setting the time to live of a single message.
+const MESSAGE_TTL_HEADER: &str = "Nats-TTL";
+
+/// Error of a conditional KV write rejected because of the current state of the key.
+#[derive(Debug)]
+pub struct KvConflict(pub String);
+
+impl std::fmt::Display for KvConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for KvConflict {}
+
+/// Error of a file whose content does not match the SHA-256 digest stored with it.
+#[derive(Debug)]
+pub struct DigestMismatch(pub String);
+
+impl std::fmt::Display for DigestMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Content of file '{}' does not match its digest", self.0)
+    }
+}
+
+impl std::error::Error for DigestMismatch {}
+
+/// Settings of a KV bucket created explicitly. Unset fields keep the server defaults.
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct KvBucketOptions {
+    /// Number of revisions kept per key.
+    pub history: Option<i64>,
+    /// Maximum age of a value, in seconds.
+    pub ttl: Option<f64>,
+    /// How long purge and expiry markers are kept, in seconds. Enables per-key TTLs.
+    pub limit_markers: Option<f64>,
+    /// Maximum total size of the bucket, in bytes.
+    pub max_bytes: Option<i64>,
+    /// Either `file` or `memory`.
+    pub storage: Option<String>,
+    /// Number of replicas in a clustered JetStream.
+    pub replicas: Option<usize>,
+    /// Whether the stream backing the bucket is compressed.
+    pub compression: Option<bool>,
+}
+
+impl KvBucketOptions {
+    fn into_config(self, bucket: String) -> anyhow::Result<async_nats::jetstream::kv::Config> {
+        let mut config = async_nats::jetstream::kv::Config {
+            bucket,
+            ..Default::default()
+        };
+
+        if let Some(history) = self.history {
+            config.history = history;
+        }
+
+        if let Some(ttl) = self.ttl {
+            config.max_age = Duration::try_from_secs_f64(ttl)
+                .map_err(|err| anyhow::anyhow!("Invalid ttl {ttl}: {err}"))?;
+        }
+
+        if let Some(limit_markers) = self.limit_markers {
+            config.limit_markers =
+                Some(Duration::try_from_secs_f64(limit_markers).map_err(|err| {
+                    anyhow::anyhow!("Invalid limit_markers {limit_markers}: {err}")
+                })?);
+        }
+
+        if let Some(max_bytes) = self.max_bytes {
+            config.max_bytes = max_bytes;
+        }
+
+        if let Some(storage) = self.storage {
+            config.storage = parse_storage(&storage)?;
+        }
+
+        if let Some(replicas) = self.replicas {
+            config.num_replicas = replicas;
+        }
+
+        if let Some(compression) = self.compression {
+            config.compression = compression;
+        }
+
+        Ok(config)
+    }
+}
+
+/// Settings of an object store created explicitly. Unset fields keep the server defaults.
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ObjectStoreOptions {
+    /// Description of the object store.
+    pub description: Option<String>,
+    /// Maximum age of a file, in seconds.
+    pub ttl: Option<f64>,
+    /// Maximum total size of the object store, in bytes.
+    pub max_bytes: Option<i64>,
+    /// Either `file` or `memory`.
+    pub storage: Option<String>,
+    /// Number of replicas in a clustered JetStream.
+    pub replicas: Option<usize>,
+    /// Whether the stream backing the object store is compressed.
+    pub compression: Option<bool>,
+}
+
+impl ObjectStoreOptions {
+    fn into_config(
+        self,
+        bucket: String,
+    ) -> anyhow::Result<async_nats::jetstream::object_store::Config> {
+        let mut config = async_nats::jetstream::object_store::Config {
+            bucket,
+            description: self.description,
+            ..Default::default()
+        };
+
+        if let Some(ttl) = self.ttl {
+            config.max_age = Duration::try_from_secs_f64(ttl)
+                .map_err(|err| anyhow::anyhow!("Invalid ttl {ttl}: {err}"))?;
+        }
+
+        if let Some(max_bytes) = self.max_bytes {
+            config.max_bytes = max_bytes;
+        }
+
+        if let Some(storage) = self.storage {
+            config.storage = parse_storage(&storage)?;
+        }
+
+        if let Some(replicas) = self.replicas {
+            config.num_replicas = replicas;
+        }
+
+        if let Some(compression) = self.compression {
+            config.compression = compression;
+        }
+
+        Ok(config)
+    }
+}
+
+fn parse_storage(storage: &str) -> anyhow::Result<StorageType> {
+    match storage {
+        "file" => Ok(StorageType::File),
+        "memory" => Ok(StorageType::Memory),
+        other => anyhow::bail!("Unknown storage '{other}', expected 'file' or 'memory'"),
+    }
+}
+
+/// Checks a SHA-256 hash against a digest in the `SHA-256=<base64>` format of object infos.
+fn digest_matches(digest: Option<&str>, hash: &[u8]) -> anyhow::Result<bool> {
+    use base64::Engine;
+
+    let digest = digest
+        .and_then(|digest| digest.strip_prefix("SHA-256="))
+        .ok_or_else(|| anyhow::anyhow!("File has no SHA-256 digest"))?;
+    let expected = base64::engine::general_purpose::URL_SAFE
+        .decode(digest)
+        .or_else(|_| base64::engine::general_purpose::STANDARD.decode(digest))
+        .map_err(|err| anyhow::anyhow!("Invalid file digest '{digest}': {err}"))?;
+
+    Ok(expected == hash)
+}
+
+/// Checks a KV key the way `Store::put` does, for writes that bypass it.
+fn is_valid_key(key: &str) -> bool {
+    !key.is_empty()
+        && !key.starts_with('.')
+        && !key.ends_with('.')
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '/' | '_' | '=' | '.'))
+}
+
+/// Name of the stream backing the object store `bucket`.
+pub fn object_store_stream_name(bucket: &str) -> String {
+    format!("OBJ_{bucket}")
+}
+
+/// Reply to a request, with the status of the response.
+pub struct Reply {
+    pub subject: String,
+    pub headers: Option<HeaderMap>,
+    pub payload: Vec<u8>,
+    pub status: u16,
+}
+
+/// How long a publish with `flush` waits for the server to confirm the message.
+pub const PUBLISH_FLUSH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Status of a reply to a request without status, like an HTTP `200 OK`.
+const REPLY_STATUS_OK: u16 = 200;
+
+/// Status of the reply to a request on a subject without subscribers.
+const REPLY_STATUS_NO_RESPONDERS: u16 = 503;
+
+/// A message stored in a JetStream stream.
+pub struct StoredMessage {
+    pub seq: u64,
+    pub subject: String,
+    pub headers: Option<HeaderMap>,
+    pub payload: Vec<u8>,
+    pub time: time::OffsetDateTime,
+}
+
+impl From<StreamMessage> for StoredMessage {
+    fn from(message: StreamMessage) -> Self {
+        Self {
+            seq: message.sequence,
+            subject: message.subject.to_string(),
+            headers: (!message.headers.is_empty()).then_some(message.headers),
+            payload: message.payload.to_vec(),
+            time: message.time,
+        }
+    }
+}
+
+impl TryFrom<async_nats::jetstream::Message> for StoredMessage {
+    type Error = anyhow::Error;
+
+    fn try_from(message: async_nats::jetstream::Message) -> anyhow::Result<Self> {
+        let info = message.info().map_err(|err| anyhow::anyhow!("{err}"))?;
+        let (seq, time) = (info.stream_sequence, info.published);
+        let message = message.message;
+
+        Ok(Self {
+            seq,
+            subject: message.subject.to_string(),
+            headers: message.headers,
+            payload: message.payload.to_vec(),
+            time,
+        })
+    }
+}
+
+/// Descriptive fields of an object store file, set when it is uploaded.
+#[derive(Default)]
+pub struct FileMeta {
+    pub description: Option<String>,
+    pub metadata: HashMap<String, String>,
+    pub headers: Option<HeaderMap>,
+}
+
+/// Reads the chunks sent through a channel, so that content produced outside of the
+/// runtime can be uploaded without buffering it whole.
+///
+/// An empty chunk marks the end of the content. Dropping the sender before it fails the
+/// read, so that an interrupted producer never results in a truncated upload.
+pub struct ChunkReader {
+    chunks: mpsc::Receiver<Vec<u8>>,
+    current: Cursor<Vec<u8>>,
+    finished: bool,
+}
+
+impl ChunkReader {
+    pub fn channel(capacity: usize) -> (mpsc::Sender<Vec<u8>>, Self) {
+        let (sender, chunks) = mpsc::channel(capacity);
+
+        (
+            sender,
+            Self {
+                chunks,
+                current: Cursor::new(Vec::new()),
+                finished: false,
+            },
+        )
+    }
+}
+
+impl AsyncRead for ChunkReader {
+    fn poll_read(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> std::task::Poll<std::io::Result<()>> {
+        use std::task::Poll;
+
+        if buf.remaining() == 0 {
+            return Poll::Ready(Ok(()));
+        }
+
+        loop {
+            let read = std::io::Read::read(&mut self.current, buf.initialize_unfilled())?;
+
+            if read > 0 {
+                buf.advance(read);
+                return Poll::Ready(Ok(()));
+            }
+
+            if self.finished {
+                return Poll::Ready(Ok(()));
+            }
+
+            match std::task::ready!(self.chunks.poll_recv(cx)) {
+                Some(chunk) if chunk.is_empty() => self.finished = true,
+                Some(chunk) => self.current = Cursor::new(chunk),
+                None => {
+                    return Poll::Ready(Err(std::io::Error::new(
+                        std::io::ErrorKind::UnexpectedEof,
+                        "content ended before it was complete",
+                    )));
+                }
+            }
+        }
+    }
+}
+
+/// A message pulled from a durable consumer, acknowledged later through its ack token.
+pub struct FetchedMessage {
+    pub message: StoredMessage,
+    pub delivered: i64,
+    pub ack_token: String,
+}
+
+impl TryFrom<async_nats::jetstream::Message> for FetchedMessage {
+    type Error = anyhow::Error;
+
+    fn try_from(message: async_nats::jetstream::Message) -> anyhow::Result<Self> {
+        let ack_token = message
+            .reply
+            .as_ref()
+            .map(|reply| reply.to_string())
+            .ok_or_else(|| anyhow::anyhow!("Message has no ack token"))?;
+        let delivered = message
+            .info()
+            .map_err(|err| anyhow::anyhow!("{err}"))?
+            .delivered;
+
+        Ok(Self {
+            message: StoredMessage::try_from(message)?,
+            delivered,
+            ack_token,
+        })
+    }
+}
+
+fn stream_config(name: String, config: serde_json::Value) -> anyhow::Result<stream::Config> {
+    let serde_json::Value::Object(mut config) = config else {
+        anyhow::bail!("Stream config must be a JSON object");
+    };
+
+    let _ = config.insert("name".to_string(), serde_json::Value::String(name));
+
+    serde_json::from_value(serde_json::Value::Object(config))
+        .map_err(|err| anyhow::anyhow!("Invalid stream config: {err}"))
+}
+
 pub struct NatsClient {
     connection: Option<Client>,
     jetstream: Option<Context>,
@@ -46,9 +413,10 @@ impl NatsClient {
         headers: Option<serde_json::Value>,
     ) -> anyhow::Result<()> {
         let subject = subject.to_string();
+        validate_subject(&subject, false)?;
         let message: Vec<u8> = message.to_bytes()?;
+        let headers = headers.map(extract_headers).transpose()?;
         let conn = self.get_connection().await?;
-        let headers = headers.map(extract_headers);
 
         if let Some(reply) = reply {
             let reply = reply.to_string();
@@ -70,13 +438,15 @@ impl NatsClient {
         Ok(())
     }
 
-    pub async fn request(
+    /// Sends a request and decodes the payload of the reply as `T`.
+    pub async fn request<T: FromBytes>(
         &mut self,
         subject: impl ToString,
         message: impl ToBytes,
         timeout: Option<u64>,
-    ) -> anyhow::Result<Vec<u8>> {
+    ) -> anyhow::Result<T> {
         let subject = subject.to_string();
+        validate_subject(&subject, false)?;
         let message: Vec<u8> = message.to_bytes()?;
 
         let request = Request::new().payload(message.into());
@@ -90,10 +460,148 @@ impl NatsClient {
         let result = self
             .get_connection()
             .await?
-            .send_request(subject, request)
+            .send_request(subject.clone(), request)
+            .await?;
+
+        T::from_bytes(result.payload.to_vec())
+            .map_err(|err| anyhow::anyhow!("Invalid reply to request on '{subject}': {err}"))
+    }
+
+    /// Returns a handle to the connection for publishes running on the runtime.
+    pub async fn get_client(&mut self) -> anyhow::Result<Client> {
+        Ok(self.get_connection().await?.clone())
+    }
+
+    /// Waits until the messages published so far have been written to the server.
+    pub async fn flush(&mut self) -> anyhow::Result<()> {
+        self.get_connection().await?.flush().await?;
+
+        Ok(())
+    }
+
+    /// Waits until the server has received every message published so far.
+    ///
+    /// The connection is flushed, then a request is sent to a subject nobody listens on.
+    /// The server processes the messages of a connection in order, so its "no responders"
+    /// reply confirms that the preceding messages have arrived, like a PING/PONG exchange.
+    pub async fn flush_confirmed(&mut self, timeout: Duration) -> anyhow::Result<()> {
+        let conn = self.get_connection().await?;
+
+        let confirmation = async {
+            conn.flush().await?;
+
+            match conn.request(conn.new_inbox(), Vec::new().into()).await {
+                Ok(_) => Ok(()),
+                Err(err) if err.kind() == async_nats::client::RequestErrorKind::NoResponders => {
+                    Ok(())
+                }
+                Err(err) => Err(anyhow::Error::from(err)),
+            }
+        };
+
+        tokio::time::timeout(timeout, confirmation)
+            .await
+            .map_err(|_| anyhow::anyhow!("Server did not confirm the flush within {timeout:?}"))?
+    }
+
+    /// Sends a request with headers and returns the whole reply. A request on a subject
+    /// without subscribers returns an empty reply with status `503` instead of failing.
+    pub async fn request_with_headers(
+        &mut self,
+        subject: impl ToString,
+        message: impl ToBytes,
+        headers: Option<serde_json::Value>,
+        timeout: Option<u64>,
+    ) -> anyhow::Result<Reply> {
+        let subject = subject.to_string();
+        validate_subject(&subject, false)?;
+        let message: Vec<u8> = message.to_bytes()?;
+
+        let mut request = Request::new().payload(message.into());
+
+        if let Some(timeout) = timeout {
+            request = request.timeout(Some(Duration::from_millis(timeout)));
+        }
+
+        if let Some(headers) = headers {
+            request = request.headers(extract_headers(headers)?);
+        }
+
+        let result = self
+            .get_connection()
+            .await?
+            .send_request(subject.clone(), request)
+            .await;
+
+        match result {
+            Ok(reply) => Ok(Reply {
+                subject: reply.subject.to_string(),
+                headers: reply.headers,
+                payload: reply.payload.to_vec(),
+                status: reply
+                    .status
+                    .map_or(REPLY_STATUS_OK, |status| status.as_u16()),
+            }),
+            Err(err) if err.kind() == async_nats::client::RequestErrorKind::NoResponders => {
+                Ok(Reply {
+                    subject,
+                    headers: None,
+                    payload: Vec::new(),
+                    status: REPLY_STATUS_NO_RESPONDERS,
+                })
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Publishes a request and collects the replies of every responder until `timeout`
+    /// elapses, `max_replies` replies are received or, with `sentinel`, an empty reply
+    /// arrives.
+    pub async fn request_many(
+        &mut self,
+        subject: impl ToString,
+        message: impl ToBytes,
+        timeout: Duration,
+        max_replies: Option<usize>,
+        sentinel: bool,
+    ) -> anyhow::Result<Vec<Reply>> {
+        let subject = subject.to_string();
+        validate_subject(&subject, false)?;
+        let message: Vec<u8> = message.to_bytes()?;
+        let conn = self.get_connection().await?;
+
+        let inbox = conn.new_inbox();
+        let mut replies_sub = conn.subscribe(inbox.clone()).await?;
+        conn.publish_with_reply(subject, inbox, message.into())
             .await?;
+        conn.flush().await?;
+
+        let deadline = tokio::time::Instant::now() + timeout;
+        let mut replies = Vec::new();
+
+        while max_replies.is_none_or(|max| replies.len() < max) {
+            let Ok(Some(reply)) = tokio::time::timeout_at(deadline, replies_sub.next()).await
+            else {
+                break;
+            };
+
+            if reply.status == Some(async_nats::StatusCode::NO_RESPONDERS)
+                || (sentinel && reply.payload.is_empty())
+            {
+                break;
+            }
+
+            replies.push(Reply {
+                subject: reply.subject.to_string(),
+                headers: reply.headers,
+                payload: reply.payload.to_vec(),
+                status: reply
+                    .status
+                    .map_or(REPLY_STATUS_OK, |status| status.as_u16()),
+            });
+        }
 
-        Ok(result.payload.to_vec())
+        Ok(replies)
     }
 
     pub async fn publish_stream(
@@ -103,8 +611,9 @@ impl NatsClient {
         headers: Option<serde_json::Value>,
     ) -> anyhow::Result<()> {
         let subject = subject.to_string();
+        validate_subject(&subject, false)?;
         let message: Vec<u8> = message.to_bytes()?;
-        let headers = headers.map(extract_headers);
+        let headers = headers.map(extract_headers).transpose()?;
         let js = self.get_jetstream().await?;
 
         if let Some(headers) = headers {
@@ -118,6 +627,241 @@ impl NatsClient {
         Ok(())
     }
 
+    /// Creates an ephemeral ordered consumer on `stream` and returns the number of
+    /// messages pending for it along with the stream of those messages.
+    pub async fn read_stream(
+        &mut self,
+        stream: impl AsRef<str>,
+        filter_subject: Option<String>,
+        deliver_policy: DeliverPolicy,
+    ) -> anyhow::Result<(u64, Ordered)> {
+        let js = self.get_jetstream().await?;
+        let stream = js.get_stream(stream).await?;
+
+        let consumer = stream
+            .create_consumer(OrderedConfig {
+                filter_subject: filter_subject.unwrap_or_default(),
+                deliver_policy,
+                ..Default::default()
+            })
+            .await?;
+
+        let pending = consumer.cached_info().num_pending;
+
+        Ok((pending, consumer.messages().await?))
+    }
+
+    /// Creates a stream from a configuration in the JetStream API JSON format.
+    pub async fn create_stream(
+        &mut self,
+        name: impl ToString,
+        config: serde_json::Value,
+    ) -> anyhow::Result<stream::Info> {
+        let config = stream_config(name.to_string(), config)?;
+        let js = self.get_jetstream().await?;
+        let stream = js.create_stream(config).await?;
+
+        Ok(stream.cached_info().clone())
+    }
+
+    /// Updates a stream, changing only the settings present in `config`.
+    pub async fn update_stream(
+        &mut self,
+        name: impl AsRef<str>,
+        config: serde_json::Value,
+    ) -> anyhow::Result<stream::Info> {
+        let js = self.get_jetstream().await?;
+        let stream = js.get_stream(name.as_ref()).await?;
+
+        let mut current = serde_json::to_value(&stream.cached_info().config)?;
+        if let (Some(current), serde_json::Value::Object(changes)) =
+            (current.as_object_mut(), config)
+        {
+            current.extend(changes);
+        } else {
+            anyhow::bail!("Stream config must be a JSON object");
+        }
+
+        let config = stream_config(name.as_ref().to_string(), current)?;
+
+        Ok(js.update_stream(config).await?)
+    }
+
+    pub async fn delete_stream(&mut self, name: impl AsRef<str>) -> anyhow::Result<()> {
+        let js = self.get_jetstream().await?;
+        let _ = js.delete_stream(name).await?;
+
+        Ok(())
+    }
+
+    pub async fn get_stream_info(&mut self, name: impl AsRef<str>) -> anyhow::Result<stream::Info> {
+        let js = self.get_jetstream().await?;
+        let stream = js.get_stream(name).await?;
+
+        Ok(stream.cached_info().clone())
+    }
+
+    pub async fn list_streams(&mut self) -> anyhow::Result<Vec<stream::Info>> {
+        let js = self.get_jetstream().await?;
+        let mut streams = js.streams();
+        let mut infos = Vec::new();
+
+        while let Some(info) = streams.next().await {
+            infos.push(info?);
+        }
+
+        Ok(infos)
+    }
+
+    /// Fetches the message stored in `stream` at sequence `seq`.
+    pub async fn get_stream_message(
+        &mut self,
+        stream: impl AsRef<str>,
+        seq: u64,
+    ) -> anyhow::Result<StoredMessage> {
+        let js = self.get_jetstream().await?;
+        let stream = js.get_stream(stream).await?;
+
+        Ok(stream.get_raw_message(seq).await?.into())
+    }
+
+    /// Fetches the last message stored in `stream` for `subject`.
+    pub async fn get_last_stream_message(
+        &mut self,
+        stream: impl AsRef<str>,
+        subject: impl AsRef<str>,
+    ) -> anyhow::Result<StoredMessage> {
+        let js = self.get_jetstream().await?;
+        let stream = js.get_stream(stream).await?;
+
+        Ok(stream
+            .get_last_raw_message_by_subject(subject.as_ref())
+            .await?
+            .into())
+    }
+
+    /// Creates a durable consumer on `stream` from a configuration in the JetStream API
+    /// JSON format.
+    pub async fn create_consumer(
+        &mut self,
+        stream: impl AsRef<str>,
+        name: impl ToString,
+        config: serde_json::Value,
+    ) -> anyhow::Result<consumer::Info> {
+        let serde_json::Value::Object(mut config) = config else {
+            anyhow::bail!("Consumer config must be a JSON object");
+        };
+
+        let _ = config.insert(
+            "durable_name".to_string(),
+            serde_json::Value::String(name.to_string()),
+        );
+
+        let config: consumer::Config = serde_json::from_value(serde_json::Value::Object(config))
+            .map_err(|err| anyhow::anyhow!("Invalid consumer config: {err}"))?;
+
+        let js = self.get_jetstream().await?;
+        let stream = js.get_stream(stream).await?;
+        let consumer = stream.create_consumer(config).await?;
+
+        Ok(consumer.cached_info().clone())
+    }
+
+    pub async fn delete_consumer(
+        &mut self,
+        stream: impl AsRef<str>,
+        name: impl AsRef<str>,
+    ) -> anyhow::Result<()> {
+        let js = self.get_jetstream().await?;
+        let stream = js.get_stream(stream).await?;
+        let _ = stream.delete_consumer(name.as_ref()).await?;
+
+        Ok(())
+    }
+
+    pub async fn get_consumer_info(
+        &mut self,
+        stream: impl AsRef<str>,
+        name: impl AsRef<str>,
+    ) -> anyhow::Result<consumer::Info> {
+        let js = self.get_jetstream().await?;
+        let stream = js.get_stream(stream).await?;
+
+        Ok(stream.consumer_info(name.as_ref()).await?)
+    }
+
+    pub async fn list_consumers(
+        &mut self,
+        stream: impl AsRef<str>,
+    ) -> anyhow::Result<Vec<consumer::Info>> {
+        let js = self.get_jetstream().await?;
+        let stream = js.get_stream(stream).await?;
+        let mut consumers = stream.consumers();
+        let mut infos = Vec::new();
+
+        while let Some(info) = consumers.next().await {
+            infos.push(info?);
+        }
+
+        Ok(infos)
+    }
+
+    /// Pulls up to `batch` messages from a durable pull consumer, waiting at most `expires`
+    /// for them to arrive.
+    pub async fn fetch_messages(
+        &mut self,
+        stream: impl AsRef<str>,
+        consumer: impl AsRef<str>,
+        batch: usize,
+        expires: Duration,
+    ) -> anyhow::Result<Vec<FetchedMessage>> {
+        let js = self.get_jetstream().await?;
+        let stream = js.get_stream(stream).await?;
+        let consumer: consumer::PullConsumer = stream.get_consumer(consumer.as_ref()).await?;
+
+        let mut fetched = consumer
+            .fetch()
+            .max_messages(batch)
+            .expires(expires)
+            .messages()
+            .await
+            .map_err(|err| anyhow::anyhow!("{err}"))?;
+        let mut messages = Vec::new();
+
+        while let Some(message) = fetched.next().await {
+            let message = message.map_err(|err| anyhow::anyhow!("{err}"))?;
+            messages.push(FetchedMessage::try_from(message)?);
+        }
+
+        Ok(messages)
+    }
+
+    /// Acknowledges fetched messages by publishing `kind` to their ack tokens.
+    pub async fn acknowledge(
+        &mut self,
+        tokens: impl IntoIterator<Item = String>,
+        kind: AckKind,
+    ) -> anyhow::Result<()> {
+        let tokens: Vec<_> = tokens.into_iter().collect();
+
+        if let Some(token) = tokens
+            .iter()
+            .find(|token| !token.starts_with(ACK_SUBJECT_PREFIX))
+        {
+            anyhow::bail!("Invalid ack token '{token}'");
+        }
+
+        let conn = self.get_connection().await?;
+
+        for token in tokens {
+            conn.publish(token, kind.into()).await?;
+        }
+
+        conn.flush().await?;
+
+        Ok(())
+    }
+
     pub async fn invalidate_connection(&mut self) {
         let connection = { self.connection.take() };
 
@@ -143,9 +887,9 @@ impl NatsClient {
 
         if changed {
             self.invalidate_connection().await;
-
-            self.current_config = Some(new_config);
         }
+
+        self.current_config = Some(new_config);
     }
 
     pub async fn put_value(
@@ -161,6 +905,85 @@ impl NatsClient {
         Ok(version)
     }
 
+    /// Stores the value with a time to live, after which the server removes the key.
+    /// The bucket must be created with `limit_markers` for the server to accept it.
+    pub async fn put_value_with_ttl(
+        &mut self,
+        bucket: impl ToString,
+        key: impl AsRef<str>,
+        data: impl ToBytes,
+        ttl: Duration,
+    ) -> anyhow::Result<u64> {
+        anyhow::ensure!(ttl.as_secs() >= 1, "TTL must be at least one second");
+        anyhow::ensure!(
+            ttl.subsec_nanos() == 0,
+            "TTL must be a whole number of seconds"
+        );
+        anyhow::ensure!(is_valid_key(key.as_ref()), "Invalid key '{}'", key.as_ref());
+
+        let data: Vec<u8> = data.to_bytes()?;
+        let subject = {
+            let bucket = self.get_or_create_bucket(bucket).await?;
+            let prefix = bucket.put_prefix.as_ref().unwrap_or(&bucket.prefix);
+            format!("{prefix}{}", key.as_ref())
+        };
+
+        let mut headers = async_nats::HeaderMap::new();
+        headers.insert(MESSAGE_TTL_HEADER, format!("{}s", ttl.as_secs()).as_str());
+
+        let ack = self
+            .get_jetstream()
+            .await?
+            .publish_with_headers(subject, headers, data.into())
+            .await?
+            .await?;
+
+        Ok(ack.sequence)
+    }
+
+    pub async fn create_value(
+        &mut self,
+        bucket: impl ToString,
+        key: impl AsRef<str>,
+        data: impl ToBytes,
+    ) -> anyhow::Result<u64> {
+        let bucket = self.get_or_create_bucket(bucket).await?;
+        let data: Vec<u8> = data.to_bytes()?;
+
+        bucket
+            .create(key.as_ref(), data.into())
+            .await
+            .map_err(|err| match err.kind() {
+                CreateErrorKind::AlreadyExists => {
+                    KvConflict(format!("Key '{}' already exists", key.as_ref())).into()
+                }
+                _ => err.into(),
+            })
+    }
+
+    pub async fn update_value(
+        &mut self,
+        bucket: impl ToString,
+        key: impl AsRef<str>,
+        data: impl ToBytes,
+        revision: u64,
+    ) -> anyhow::Result<u64> {
+        let bucket = self.get_or_create_bucket(bucket).await?;
+        let data: Vec<u8> = data.to_bytes()?;
+
+        bucket
+            .update(key.as_ref(), data.into(), revision)
+            .await
+            .map_err(|err| match err.kind() {
+                UpdateErrorKind::WrongLastRevision => KvConflict(format!(
+                    "Key '{}' does not have the expected revision {revision}",
+                    key.as_ref()
+                ))
+                .into(),
+                _ => err.into(),
+            })
+    }
+
     pub async fn get_value<T: FromBytes>(
         &mut self,
         bucket: impl ToString,
@@ -187,6 +1010,154 @@ impl NatsClient {
         Ok(())
     }
 
+    pub async fn create_bucket(
+        &mut self,
+        bucket: impl ToString,
+        options: KvBucketOptions,
+    ) -> anyhow::Result<()> {
+        let bucket = bucket.to_string();
+        let config = options.into_config(bucket.clone())?;
+        let store = self.get_jetstream().await?.create_key_value(config).await?;
+
+        let _ = self.cached_buckets.insert(bucket, store);
+
+        Ok(())
+    }
+
+    pub async fn delete_bucket(&mut self, bucket: impl ToString) -> anyhow::Result<()> {
+        let bucket = bucket.to_string();
+        let _ = self.cached_buckets.remove(&bucket);
+
+        let _ = self
+            .get_jetstream()
+            .await?
+            .delete_key_value(&bucket)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_bucket_status(&mut self, bucket: impl ToString) -> anyhow::Result<Status> {
+        let bucket = bucket.to_string();
+        let store = self.get_jetstream().await?.get_key_value(&bucket).await?;
+
+        Ok(store.status().await?)
+    }
+
+    /// Removes every revision of the key, leaving a single purge marker.
+    pub async fn purge_value(
+        &mut self,
+        bucket: impl ToString,
+        key: impl AsRef<str>,
+    ) -> anyhow::Result<()> {
+        let bucket = self.get_or_create_bucket(bucket).await?;
+        bucket.purge(key).await?;
+
+        Ok(())
+    }
+
+    /// Creates an ephemeral ordered consumer over the latest revisions of the keys of
+    /// `bucket` matching `key_filter`. Returns the key prefix of the bucket subjects, the
+    /// number of pending revisions and the stream of those revisions.
+    pub async fn read_bucket(
+        &mut self,
+        bucket: impl ToString,
+        key_filter: impl AsRef<str>,
+    ) -> anyhow::Result<(String, u64, Ordered)> {
+        self.read_bucket_with_options(bucket, key_filter, false)
+            .await
+    }
+
+    /// Returns the keys of `bucket` matching `key_filter`, deleted and purged keys excluded.
+    pub async fn get_keys(
+        &mut self,
+        bucket: impl ToString,
+        key_filter: impl AsRef<str>,
+    ) -> anyhow::Result<Vec<String>> {
+        let (prefix, pending, mut messages) = self
+            .read_bucket_with_options(bucket, key_filter, true)
+            .await?;
+
+        let mut keys = vec![];
+        if pending == 0 {
+            return Ok(keys);
+        }
+
+        while let Some(message) = messages.next().await {
+            let message = message?;
+            let info = message.info().map_err(|err| anyhow::anyhow!("{err}"))?;
+            let done = info.pending == 0;
+
+            let deleted = message
+                .headers
+                .as_ref()
+                .and_then(|h| h.get(KV_OPERATION_HEADER))
+                .is_some_and(|op| matches!(op.as_str(), "DEL" | "PURGE"));
+
+            if !deleted {
+                if let Some(key) = message.subject.strip_prefix(prefix.as_str()) {
+                    keys.push(key.to_string());
+                }
+            }
+
+            if done {
+                break;
+            }
+        }
+
+        Ok(keys)
+    }
+
+    /// Returns every revision of `key` still kept in `bucket`, oldest first.
+    pub async fn get_history(
+        &mut self,
+        bucket: impl ToString,
+        key: impl AsRef<str>,
+    ) -> anyhow::Result<Vec<Entry>> {
+        let bucket = self.get_or_create_bucket(bucket).await?;
+        let mut history = bucket.history(key).await?;
+
+        let mut entries = vec![];
+        while let Some(entry) = history.next().await {
+            entries.push(entry?);
+        }
+
+        Ok(entries)
+    }
+
+    pub async fn get_entry_for_revision(
+        &mut self,
+        bucket: impl ToString,
+        key: impl Into<String>,
+        revision: u64,
+    ) -> anyhow::Result<Option<Entry>> {
+        let bucket = self.get_or_create_bucket(bucket).await?;
+        Ok(bucket.entry_for_revision(key, revision).await?)
+    }
+
+    async fn read_bucket_with_options(
+        &mut self,
+        bucket: impl ToString,
+        key_filter: impl AsRef<str>,
+        headers_only: bool,
+    ) -> anyhow::Result<(String, u64, Ordered)> {
+        let bucket = self.get_or_create_bucket(bucket).await?;
+
+        let consumer = bucket
+            .stream
+            .create_consumer(OrderedConfig {
+                filter_subject: format!("{}{}", bucket.prefix, key_filter.as_ref()),
+                deliver_policy: DeliverPolicy::LastPerSubject,
+                headers_only,
+                ..Default::default()
+            })
+            .await?;
+
+        let pending = consumer.cached_info().num_pending;
+
+        Ok((bucket.prefix.clone(), pending, consumer.messages().await?))
+    }
+
     pub async fn get_server_info(&mut self) -> anyhow::Result<async_nats::ServerInfo> {
         let connection = self.get_connection().await?;
         Ok(connection.server_info())
@@ -206,6 +1177,57 @@ impl NatsClient {
         Ok(content)
     }
 
+    /// Downloads a file like [`Self::get_file`], failing with [`DigestMismatch`] if its
+    /// content does not match the digest stored with it.
+    pub async fn get_verified_file(
+        &mut self,
+        store: impl ToString,
+        name: impl AsRef<str> + Send,
+    ) -> anyhow::Result<Vec<u8>> {
+        let name = name.as_ref().to_string();
+        let mut file = self.get_file_reader(store, &name).await?;
+
+        let mut content = Vec::with_capacity(file.info().size);
+        match file.read_to_end(&mut content).await {
+            Ok(_) => {}
+            Err(err) if err.kind() == std::io::ErrorKind::InvalidData => {
+                return Err(DigestMismatch(name).into());
+            }
+            Err(err) => return Err(err.into()),
+        }
+
+        let hash = ring::digest::digest(&ring::digest::SHA256, &content);
+        if !digest_matches(file.info().digest.as_deref(), hash.as_ref())? {
+            return Err(DigestMismatch(name).into());
+        }
+
+        Ok(content)
+    }
+
+    /// Reads a file chunk by chunk and checks its content against the SHA-256 digest
+    /// stored with it.
+    pub async fn verify_file(
+        &mut self,
+        store: impl ToString,
+        name: impl AsRef<str> + Send,
+    ) -> anyhow::Result<bool> {
+        let mut file = self.get_file_reader(store, name).await?;
+        let mut context = ring::digest::Context::new(&ring::digest::SHA256);
+        let mut buffer = vec![0; 128 * 1024];
+
+        loop {
+            match file.read(&mut buffer).await {
+                Ok(0) => break,
+                Ok(read) => context.update(buffer.get(..read).unwrap_or_default()),
+                // The reader checks the digest itself once the whole file has been read.
+                Err(err) if err.kind() == std::io::ErrorKind::InvalidData => return Ok(false),
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        digest_matches(file.info().digest.as_deref(), context.finish().as_ref())
+    }
+
     pub async fn put_file(
         &mut self,
         store: impl ToString,
@@ -219,6 +1241,165 @@ impl NatsClient {
         Ok(())
     }
 
+    /// Uploads a file to `store` with a description, metadata and headers.
+    pub async fn put_file_with_meta(
+        &mut self,
+        store: impl ToString,
+        name: impl ToString,
+        content: Vec<u8>,
+        meta: FileMeta,
+    ) -> anyhow::Result<ObjectInfo> {
+        let store = self.get_or_create_object_store(store).await?;
+        let mut reader = BufReader::new(Cursor::new(content));
+        let meta = ObjectMetadata {
+            name: name.to_string(),
+            description: meta.description,
+            metadata: meta.metadata,
+            headers: meta.headers,
+            ..Default::default()
+        };
+
+        Ok(store.put(meta, &mut reader).await?)
+    }
+
+    /// Renames a file of `store` or changes its description, metadata or headers without
+    /// uploading it again. Fields that are `None` keep their current value.
+    pub async fn update_file_meta(
+        &mut self,
+        store: impl ToString,
+        name: impl AsRef<str>,
+        new_name: Option<String>,
+        description: Option<String>,
+        metadata: Option<HashMap<String, String>>,
+        headers: Option<HeaderMap>,
+    ) -> anyhow::Result<ObjectInfo> {
+        let store = self.get_or_create_object_store(store).await?;
+        let current = store.info(name.as_ref()).await?;
+
+        let update = UpdateMetadata {
+            name: new_name.unwrap_or(current.name),
+            description: description.or(current.description),
+            metadata: metadata.unwrap_or(current.metadata),
+            headers: headers.or(current.headers),
+        };
+
+        Ok(store.update_metadata(name.as_ref(), update).await?)
+    }
+
+    /// Adds a link named `name` to `store` pointing to the file `target` of `target_store`.
+    pub async fn link_file(
+        &mut self,
+        store: impl ToString,
+        name: impl AsRef<str>,
+        target_store: impl ToString,
+        target: impl AsRef<str>,
+    ) -> anyhow::Result<ObjectInfo> {
+        let target = self
+            .get_or_create_object_store(target_store)
+            .await?
+            .info(target.as_ref())
+            .await?;
+        let store = self.get_or_create_object_store(store).await?;
+
+        Ok(store.add_link(name.as_ref(), &target).await?)
+    }
+
+    /// Adds a link named `name` to `store` pointing to the whole object store `target_store`.
+    pub async fn link_object_store(
+        &mut self,
+        store: impl ToString,
+        name: impl ToString,
+        target_store: impl ToString,
+    ) -> anyhow::Result<ObjectInfo> {
+        let store = self.get_or_create_object_store(store).await?;
+
+        Ok(store
+            .add_bucket_link(name.to_string(), target_store.to_string())
+            .await?)
+    }
+
+    pub async fn create_object_store(
+        &mut self,
+        store: impl ToString,
+        options: ObjectStoreOptions,
+    ) -> anyhow::Result<()> {
+        let store = store.to_string();
+        let config = options.into_config(store.clone())?;
+        let object_store = self
+            .get_jetstream()
+            .await?
+            .create_object_store(config)
+            .await?;
+
+        let _ = self.cached_object_stores.insert(store, object_store);
+
+        Ok(())
+    }
+
+    pub async fn delete_object_store(&mut self, store: impl ToString) -> anyhow::Result<()> {
+        let store = store.to_string();
+        let _ = self.cached_object_stores.remove(&store);
+
+        self.get_jetstream()
+            .await?
+            .delete_object_store(&store)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Seals an object store, so that its files can no longer be added, changed or deleted.
+    pub async fn seal_object_store(&mut self, store: impl AsRef<str>) -> anyhow::Result<()> {
+        let js = self.get_jetstream().await?;
+        let stream = js
+            .get_stream(object_store_stream_name(store.as_ref()))
+            .await?;
+        let mut config = stream.cached_info().config.clone();
+        config.sealed = true;
+
+        let _ = js.update_stream(config).await?;
+
+        Ok(())
+    }
+
+    /// Returns the info of the stream backing an object store.
+    pub async fn get_object_store_status(
+        &mut self,
+        store: impl AsRef<str>,
+    ) -> anyhow::Result<stream::Info> {
+        self.get_stream_info(object_store_stream_name(store.as_ref()))
+            .await
+    }
+
+    /// Uploads a file to `store`, reading its content from `reader` chunk by chunk.
+    pub async fn put_file_from_reader(
+        &mut self,
+        store: impl ToString,
+        name: impl AsRef<str>,
+        reader: &mut (impl AsyncRead + Unpin),
+    ) -> anyhow::Result<ObjectInfo> {
+        let store = self.get_or_create_object_store(store).await?;
+
+        Ok(store.put(name.as_ref(), reader).await?)
+    }
+
+    /// Opens a file of `store` for reading chunk by chunk.
+    pub async fn get_file_reader(
+        &mut self,
+        store: impl ToString,
+        name: impl AsRef<str> + Send,
+    ) -> anyhow::Result<Object> {
+        let store = self.get_or_create_object_store(store).await?;
+
+        Ok(store.get(name).await?)
+    }
+
+    /// Returns a handle to `store` for uploads running on the runtime while the caller
+    /// produces the content.
+    pub async fn get_object_store(&mut self, store: impl ToString) -> anyhow::Result<ObjectStore> {
+        Ok(self.get_or_create_object_store(store).await?.clone())
+    }
+
     pub async fn delete_file(
         &mut self,
         store: impl ToString,
@@ -237,10 +1418,15 @@ impl NatsClient {
         store.info(name).await.map_err(|e| e.into())
     }
 
-    pub async fn get_file_list(&mut self, store: impl ToString) -> anyhow::Result<Vec<ObjectInfo>> {
+    /// Returns a stream over the infos of the files in `store`, skipping deleted ones.
+    pub async fn list_files(&mut self, store: impl ToString) -> anyhow::Result<List> {
         let store = self.get_or_create_object_store(store).await?;
+        Ok(store.list().await?)
+    }
+
+    pub async fn get_file_list(&mut self, store: impl ToString) -> anyhow::Result<Vec<ObjectInfo>> {
         let mut vec = vec![];
-        let mut list = store.list().await?;
+        let mut list = self.list_files(store).await?;
 
         while let Some(object) = list.next().await {
             vec.push(object?);
@@ -280,18 +1466,27 @@ impl NatsClient {
         let bucket = bucket.to_string();
 
         if !self.cached_buckets.contains_key(&bucket) {
+            let auto_create = self
+                .current_config
+                .get_or_insert_with(self.config_fetcher)
+                .kv_auto_create;
+
             let new_store = {
                 let jetstream = self.get_jetstream().await?;
 
-                if let Ok(store) = jetstream.get_key_value(&bucket).await {
-                    store
-                } else {
-                    jetstream
-                        .create_key_value(async_nats::jetstream::kv::Config {
-                            bucket: bucket.clone(),
-                            ..Default::default()
-                        })
-                        .await?
+                match jetstream.get_key_value(&bucket).await {
+                    Ok(store) => store,
+                    Err(err) if !auto_create => {
+                        anyhow::bail!("KV bucket '{bucket}' is not available: {err}")
+                    }
+                    Err(_) => {
+                        jetstream
+                            .create_key_value(async_nats::jetstream::kv::Config {
+                                bucket: bucket.clone(),
+                                ..Default::default()
+                            })
+                            .await?
+                    }
                 }
             };
 
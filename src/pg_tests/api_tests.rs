@@ -1,7 +1,7 @@
 #[cfg(any(test, feature = "pg_test"))]
 #[pgrx::prelude::pg_schema]
 mod tests {
-    use pgrx::pg_test;
+    use pgrx::{pg_test, PgSqlErrorCode, PgTryBuilder};
 
     use crate::api;
 
@@ -13,19 +13,19 @@ mod tests {
         let subject = "test.test_nats_publish";
         let message = "Hello, World! 🦀".to_string();
 
-        let res = api::nats_publish_text(subject, message, None, None);
+        let res = api::nats_publish_text(subject, message, None, None, false);
         assert!(res.is_ok(), "nats_publish occurs error: {:?}", res);
 
         let message = "Hello, World! 🦀".to_string().into_bytes();
-        let res = api::nats_publish_binary(subject, message, None, None);
+        let res = api::nats_publish_binary(subject, message, None, None, false);
         assert!(res.is_ok(), "nats_publish occurs error: {:?}", res);
 
         let message = pgrx::Json(serde_json::json!({"key": "value"}));
-        let res = api::nats_publish_json(subject, message, None, None);
+        let res = api::nats_publish_json(subject, message, None, None, false);
         assert!(res.is_ok(), "nats_publish occurs error: {:?}", res);
 
         let message = pgrx::JsonB(serde_json::json!({"key": "value"}));
-        let res = api::nats_publish_jsonb(subject, message, None, None);
+        let res = api::nats_publish_jsonb(subject, message, None, None, false);
         assert!(res.is_ok(), "nats_publish occurs error: {:?}", res);
     }
 
@@ -59,15 +59,20 @@ mod tests {
         let reply_to = "test.reply";
         let message = b"payload".to_vec();
 
-        let res = api::nats_publish_binary(subject, message.clone(), Some(reply_to), None);
+        let res = api::nats_publish_binary(subject, message.clone(), Some(reply_to), None, false);
         assert!(res.is_ok(), "publish with reply failed: {:?}", res);
 
         let headers = json!({
             "x-id": ["123"],
             "x-type": ["unit-test"]
         });
-        let res =
-            api::nats_publish_binary(subject, message.clone(), None, Some(JsonB(headers.clone())));
+        let res = api::nats_publish_binary(
+            subject,
+            message.clone(),
+            None,
+            Some(JsonB(headers.clone())),
+            false,
+        );
         assert!(res.is_ok(), "publish with headers failed: {:?}", res);
 
         let res = api::nats_publish_binary(
@@ -75,6 +80,7 @@ mod tests {
             message.clone(),
             Some(reply_to),
             Some(JsonB(headers)),
+            false,
         );
         assert!(
             res.is_ok(),
@@ -83,6 +89,59 @@ mod tests {
         );
     }
 
+    #[pg_test]
+    fn test_pgnats_publish_flush() {
+        let subject = "test.test_nats_publish_flush";
+
+        let res = api::nats_publish_text(subject, "message".to_string(), None, None, true);
+        assert!(res.is_ok(), "publish with flush failed: {:?}", res);
+
+        api::nats_publish_text(subject, "message".to_string(), None, None, false).unwrap();
+        let res = api::nats_flush(1000);
+        assert!(res.is_ok(), "nats_flush failed: {:?}", res);
+
+        assert!(api::nats_flush(0).is_err());
+    }
+
+    #[pg_test]
+    fn test_pgnats_publish_async() {
+        let subject = "test.test_nats_publish_async";
+
+        api::nats_publish_text_async(subject, "message".to_string(), None).unwrap();
+        api::nats_publish_jsonb_async(
+            subject,
+            pgrx::JsonB(serde_json::json!({ "key": "value" })),
+            Some(pgrx::JsonB(serde_json::json!({ "Trace-Id": "42" }))),
+        )
+        .unwrap();
+        assert_eq!(api::nats_pending_errors().count(), 0);
+
+        // Above the default `max_payload` of the server.
+        api::nats_publish_binary_async(subject, vec![0; 2 * 1024 * 1024], None).unwrap();
+
+        let errors: Vec<_> = api::nats_pending_errors().collect();
+        assert_eq!(errors.len(), 1, "unexpected errors: {errors:?}");
+        assert_eq!(errors[0].0.as_deref(), Some(subject));
+        assert!(!errors[0].1.is_empty());
+
+        assert_eq!(api::nats_pending_errors().count(), 0);
+    }
+
+    #[pg_test]
+    fn test_pgnats_publish_async_discarded_on_abort() {
+        let subject = "test.test_nats_publish_async_discarded_on_abort";
+
+        api::nats_publish_binary_async(subject, vec![0; 2 * 1024 * 1024], None).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(100));
+
+        // What the rollback of the transaction runs.
+        api::discard_async_publishes();
+        assert_eq!(api::nats_pending_errors().count(), 0);
+
+        api::nats_publish_text_async(subject, "message".to_string(), None).unwrap();
+        assert_eq!(api::nats_pending_errors().count(), 0);
+    }
+
     #[pg_test]
     fn test_pgnats_request() {
         use std::sync::mpsc::channel;
@@ -109,8 +168,14 @@ mod tests {
 
             while let Some(message) = subscriber.next().await {
                 if let Some(reply) = message.reply {
+                    let payload = if message.payload.as_ref() == b"invalid" {
+                        vec![0xff].into()
+                    } else {
+                        message.payload
+                    };
+
                     client
-                        .publish(reply, message.payload)
+                        .publish(reply, payload)
                         .await
                         .expect("failed to send reply");
                 }
@@ -122,7 +187,7 @@ mod tests {
         let request_text = "Test request".to_string();
         let res = api::nats_request_text("test.test_nats_request", request_text.clone(), None);
         assert!(res.is_ok(), "nats_request_text failed: {:?}", res);
-        assert_eq!(res.unwrap().as_slice(), request_text.as_bytes());
+        assert_eq!(res.unwrap(), request_text);
 
         let request_binary = b"Binary request".to_vec();
         let res = api::nats_request_binary("test.test_nats_request", request_binary.clone(), None);
@@ -132,14 +197,365 @@ mod tests {
         let request_json = pgrx::Json(serde_json::json!({"action": "ping"}));
         let res = api::nats_request_json("test.test_nats_request", request_json, None);
         assert!(res.is_ok(), "nats_request_json failed: {:?}", res);
+        assert_eq!(res.unwrap().0, serde_json::json!({"action": "ping"}));
 
         let request_jsonb = pgrx::JsonB(serde_json::json!({"action": "ping"}));
         let res = api::nats_request_jsonb("test.test_nats_request", request_jsonb, None);
         assert!(res.is_ok(), "nats_request_jsonb failed: {:?}", res);
+        assert_eq!(res.unwrap().0, serde_json::json!({"action": "ping"}));
+
+        let res = api::nats_request_text("test.test_nats_request", "invalid".to_string(), None);
+        assert!(res.is_err(), "invalid UTF-8 reply was accepted: {:?}", res);
+
+        handle.abort();
+    }
+
+    #[pg_test]
+    fn test_pgnats_request_many() {
+        use std::sync::mpsc::channel;
+
+        use futures::StreamExt;
+
+        let rt = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let (sdr, rcv) = channel();
+
+        let handles: Vec<_> = ["first", "second"]
+            .into_iter()
+            .map(|instance| {
+                let sdr = sdr.clone();
+
+                rt.spawn(async move {
+                    let client = async_nats::connect(format!("{NATS_HOST}:{NATS_PORT}"))
+                        .await
+                        .expect("failed to connect to NATS server");
+
+                    let mut subscriber = client
+                        .subscribe("test.test_nats_request_many".to_string())
+                        .await
+                        .expect("failed to subscribe");
+
+                    sdr.send(()).unwrap();
+
+                    while let Some(message) = subscriber.next().await {
+                        if let Some(reply) = message.reply {
+                            let payload = if message.payload.as_ref() == b"sentinel" {
+                                Vec::new()
+                            } else {
+                                instance.as_bytes().to_vec()
+                            };
+
+                            client
+                                .publish(reply, payload.into())
+                                .await
+                                .expect("failed to send reply");
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        rcv.recv().unwrap();
+        rcv.recv().unwrap();
+
+        let mut replies: Vec<_> = api::nats_request_many(
+            "test.test_nats_request_many",
+            b"ping".to_vec(),
+            500,
+            None,
+            false,
+        )
+        .unwrap()
+        .map(|(payload, _)| payload)
+        .collect();
+        replies.sort();
+        assert_eq!(replies, vec![b"first".to_vec(), b"second".to_vec()]);
+
+        let count = api::nats_request_many(
+            "test.test_nats_request_many",
+            b"ping".to_vec(),
+            500,
+            Some(1),
+            false,
+        )
+        .unwrap()
+        .count();
+        assert_eq!(count, 1);
+
+        let count = api::nats_request_many(
+            "test.test_nats_request_many",
+            b"sentinel".to_vec(),
+            500,
+            None,
+            true,
+        )
+        .unwrap()
+        .count();
+        assert_eq!(count, 0);
+
+        let count = api::nats_request_many(
+            "test.test_nats_request_many_no_responders",
+            b"ping".to_vec(),
+            500,
+            None,
+            false,
+        )
+        .unwrap()
+        .count();
+        assert_eq!(count, 0);
+
+        for handle in handles {
+            handle.abort();
+        }
+    }
+
+    #[pg_test]
+    fn test_pgnats_subject_helpers() {
+        assert!(api::nats_subject_is_valid("orders.eu.created", false));
+        assert!(!api::nats_subject_is_valid("orders.*.created", false));
+        assert!(api::nats_subject_is_valid("orders.*.created", true));
+        assert!(!api::nats_subject_is_valid("orders. eu", true));
+
+        assert!(api::nats_subject_matches("orders.eu.created", "orders.*.created").unwrap());
+        assert!(api::nats_subject_matches("orders.eu.created", "orders.>").unwrap());
+        assert!(!api::nats_subject_matches("orders", "orders.>").unwrap());
+        assert!(api::nats_subject_matches("orders..created", "orders.>").is_err());
+
+        let subject = api::nats_subject_format(
+            "orders.{region}.{id}",
+            pgrx::JsonB(serde_json::json!({ "region": "eu", "id": 42 })),
+        )
+        .unwrap();
+        assert_eq!(subject, "orders.eu.42");
+
+        let res = api::nats_subject_format(
+            "orders.{region}",
+            pgrx::JsonB(serde_json::json!({ "region": "eu.*" })),
+        );
+        assert!(res.is_err());
+
+        let subject = pgrx::Spi::get_one::<String>("SELECT 'orders.eu.created'::nats_subject")
+            .unwrap()
+            .unwrap();
+        assert_eq!(subject, "orders.eu.created");
+
+        let res = api::nats_publish_text("orders..created", "text".to_string(), None, None, false);
+        assert!(res.is_err());
+    }
+
+    #[pg_test(
+        error = "value for domain nats_subject violates check constraint \"nats_subject_check\""
+    )]
+    fn test_pgnats_subject_domain_rejects_invalid() {
+        pgrx::Spi::run("SELECT 'orders.*'::nats_subject").unwrap();
+    }
+
+    #[pg_test]
+    fn test_pgnats_codecs() {
+        let value = serde_json::json!({ "a": 1 });
+
+        let msgpack = api::nats_msgpack_encode(pgrx::JsonB(value.clone())).unwrap();
+        assert_eq!(msgpack, vec![0x81, 0xa1, b'a', 0x01]);
+        assert_eq!(api::nats_msgpack_decode(&msgpack).unwrap().0, value);
+
+        let cbor = api::nats_cbor_encode(pgrx::JsonB(value.clone())).unwrap();
+        assert_eq!(cbor, vec![0xa1, 0x61, b'a', 0x01]);
+        assert_eq!(api::nats_cbor_decode(&cbor).unwrap().0, value);
+
+        let res = api::nats_publish_msgpack(
+            "test.test_nats_publish_msgpack",
+            pgrx::JsonB(value.clone()),
+            None,
+            None,
+            false,
+        );
+        assert!(res.is_ok(), "nats_publish_msgpack occurs error: {:?}", res);
+
+        let res = api::nats_publish_cbor(
+            "test.test_nats_publish_cbor",
+            pgrx::JsonB(value),
+            None,
+            None,
+            false,
+        );
+        assert!(res.is_ok(), "nats_publish_cbor occurs error: {:?}", res);
+    }
+
+    #[pg_test]
+    fn test_pgnats_protobuf() {
+        fn field(tag: u8, bytes: &[u8]) -> Vec<u8> {
+            let mut field = vec![tag << 3 | 2, u8::try_from(bytes.len()).unwrap()];
+            field.extend_from_slice(bytes);
+            field
+        }
+
+        fn varint(tag: u8, value: u8) -> Vec<u8> {
+            vec![tag << 3, value]
+        }
+
+        // message Point { int32 x = 1; string label = 2; } in package "test"
+        let x = [field(1, b"x"), varint(3, 1), varint(4, 1), varint(5, 5)].concat();
+        let label = [field(1, b"label"), varint(3, 2), varint(4, 1), varint(5, 9)].concat();
+        let message = [field(1, b"Point"), field(2, &x), field(2, &label)].concat();
+        let file = [
+            field(1, b"test.proto"),
+            field(2, b"test"),
+            field(4, &message),
+            field(12, b"proto3"),
+        ]
+        .concat();
+        let descriptor_set = field(1, &file);
+
+        assert!(api::nats_protobuf_register("invalid", b"\xff").is_err());
+        api::nats_protobuf_register("test", &descriptor_set).unwrap();
+
+        let value = serde_json::json!({ "x": 3, "label": "a" });
+        let encoded = api::nats_protobuf_encode("test.Point", pgrx::JsonB(value.clone())).unwrap();
+        assert_eq!(encoded, vec![0x08, 0x03, 0x12, 0x01, b'a']);
+
+        let decoded = api::nats_protobuf_decode("test.Point", &encoded).unwrap();
+        assert_eq!(decoded.0, value);
+
+        assert!(api::nats_protobuf_encode("test.Missing", pgrx::JsonB(value.clone())).is_err());
+        assert!(api::nats_protobuf_encode(
+            "test.Point",
+            pgrx::JsonB(serde_json::json!({ "y": 1 }))
+        )
+        .is_err());
+
+        let res = api::nats_publish_protobuf(
+            "test.test_nats_publish_protobuf",
+            "test.Point",
+            pgrx::JsonB(value),
+            None,
+            None,
+            false,
+        );
+        assert!(res.is_ok(), "nats_publish_protobuf occurs error: {:?}", res);
+    }
+
+    #[pg_test]
+    fn test_pgnats_request_with_headers() {
+        use std::sync::mpsc::channel;
+
+        use futures::StreamExt;
+
+        let rt = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let (sdr, rcv) = channel();
+
+        let handle = rt.spawn(async move {
+            let client = async_nats::connect(format!("{NATS_HOST}:{NATS_PORT}"))
+                .await
+                .expect("failed to connect to NATS server");
+
+            let mut subscriber = client
+                .subscribe("test.test_nats_request_with_headers".to_string())
+                .await
+                .expect("failed to subscribe");
+
+            sdr.send(()).unwrap();
+
+            while let Some(message) = subscriber.next().await {
+                if let Some(reply) = message.reply {
+                    client
+                        .publish_with_headers(
+                            reply,
+                            message.headers.unwrap_or_default(),
+                            message.payload,
+                        )
+                        .await
+                        .expect("failed to send reply");
+                }
+            }
+        });
+
+        rcv.recv().unwrap();
+
+        let headers = pgrx::JsonB(serde_json::json!({ "Trace-Id": "42" }));
+        let (payload, headers, subject, status) = api::nats_request_text_with_headers(
+            "test.test_nats_request_with_headers",
+            "ping".to_string(),
+            Some(headers),
+            Some(1000),
+        )
+        .unwrap()
+        .next()
+        .unwrap();
+        assert_eq!(payload, b"ping");
+        assert_eq!(
+            headers.map(|h| h.0),
+            Some(serde_json::json!({ "Trace-Id": "42" }))
+        );
+        assert!(subject.starts_with("_INBOX."));
+        assert_eq!(status, 200);
+
+        let headers = pgrx::JsonB(serde_json::json!({
+            "Trace-Id": 42,
+            "Sampled": true,
+            "Tag": ["first", "second"],
+        }));
+        let (_, headers, _, _) = api::nats_request_text_with_headers(
+            "test.test_nats_request_with_headers",
+            "ping".to_string(),
+            Some(headers),
+            Some(1000),
+        )
+        .unwrap()
+        .next()
+        .unwrap();
+        assert_eq!(
+            headers.map(|h| h.0),
+            Some(serde_json::json!({
+                "Trace-Id": "42",
+                "Sampled": "true",
+                "Tag": ["first", "second"],
+            }))
+        );
+
+        let (payload, headers, _, status) = api::nats_request_text_with_headers(
+            "test.test_nats_request_no_responders",
+            "ping".to_string(),
+            None,
+            Some(1000),
+        )
+        .unwrap()
+        .next()
+        .unwrap();
+        assert!(payload.is_empty());
+        assert!(headers.is_none());
+        assert_eq!(status, 503);
 
         handle.abort();
     }
 
+    #[pg_test]
+    fn test_pgnats_publish_invalid_headers() {
+        let subject = "test.test_nats_publish_invalid_headers";
+
+        for headers in [
+            serde_json::json!(["Trace-Id", "42"]),
+            serde_json::json!({ "Trace-Id": null }),
+            serde_json::json!({ "Trace-Id": { "value": 42 } }),
+            serde_json::json!({ "Trace-Id": [] }),
+            serde_json::json!({ "Trace Id": "42" }),
+            serde_json::json!({ "Trace-Id": "42\r\nOther: 1" }),
+        ] {
+            let res = api::nats_publish_text(
+                subject,
+                "text".to_string(),
+                None,
+                Some(pgrx::JsonB(headers.clone())),
+                false,
+            );
+            assert!(res.is_err(), "headers {headers} were accepted");
+        }
+    }
+
     #[cfg(feature = "kv")]
     #[pg_test]
     fn test_pgnats_put_and_get_binary() {
@@ -226,6 +642,35 @@ mod tests {
         assert_eq!(json_value, returned_json.map(|v| v.0).unwrap());
     }
 
+    #[cfg(feature = "kv")]
+    #[pg_test]
+    fn test_pgnats_put_and_get_msgpack_and_cbor() {
+        let bucket = "test_default".to_string();
+        let value = serde_json::json!({ "key": "value", "list": [1, 2.5, true, null] });
+
+        let _ = api::nats_put_msgpack(bucket.clone(), "msgpack_key", pgrx::JsonB(value.clone()))
+            .unwrap();
+        let raw = api::nats_get_binary(bucket.clone(), "msgpack_key").unwrap();
+        assert_eq!(
+            raw.as_deref()
+                .map(crate::codec::decode_msgpack)
+                .unwrap()
+                .unwrap(),
+            value
+        );
+        let returned = api::nats_get_msgpack(bucket.clone(), "msgpack_key").unwrap();
+        assert_eq!(returned.map(|v| v.0), Some(value.clone()));
+
+        let _ = api::nats_put_cbor(bucket.clone(), "cbor_key", pgrx::JsonB(value.clone())).unwrap();
+        let returned = api::nats_get_cbor(bucket.clone(), "cbor_key").unwrap();
+        assert_eq!(returned.map(|v| v.0), Some(value));
+
+        assert!(api::nats_get_msgpack(bucket.clone(), "cbor_missing_key")
+            .unwrap()
+            .is_none());
+        assert!(api::nats_get_cbor(bucket, "msgpack_key").is_err());
+    }
+
     #[cfg(feature = "kv")]
     #[pg_test]
     fn test_pgnats_delete_value() {
@@ -250,6 +695,359 @@ mod tests {
         assert_eq!(None, value);
     }
 
+    #[cfg(feature = "kv")]
+    #[pg_test]
+    fn test_pgnats_kv_create_and_update() {
+        let bucket = "test_default".to_string();
+        let key = "cas_key";
+
+        let res = api::nats_delete_value(bucket.clone(), key);
+        assert!(res.is_ok(), "nats_delete_value occurs error: {:?}", res);
+
+        let res = api::nats_kv_create_text(bucket.clone(), key, "first");
+        assert!(res.is_ok(), "nats_kv_create_text occurs error: {:?}", res);
+        let revision = res.unwrap();
+
+        let res = api::nats_kv_update_text(bucket.clone(), key, "second", revision);
+        assert!(res.is_ok(), "nats_kv_update_text occurs error: {:?}", res);
+        assert!(res.unwrap() > revision);
+
+        let value = api::nats_get_text(bucket, key).unwrap();
+        assert_eq!(value.as_deref(), Some("second"));
+    }
+
+    #[cfg(feature = "kv")]
+    #[pg_test(error = "[PGNATS]: Key 'cas_existing_key' already exists")]
+    fn test_pgnats_kv_create_existing() {
+        let bucket = "test_default".to_string();
+        let key = "cas_existing_key";
+
+        let res = api::nats_put_text(bucket.clone(), key, "value");
+        assert!(res.is_ok(), "nats_put_text occurs error: {:?}", res);
+
+        let _ = api::nats_kv_create_text(bucket, key, "value");
+    }
+
+    #[cfg(feature = "kv")]
+    #[pg_test]
+    fn test_pgnats_kv_update_stale_revision() {
+        let bucket = "test_default".to_string();
+        let key = "cas_stale_key";
+
+        let revision = api::nats_put_text(bucket.clone(), key, "value").unwrap();
+        api::nats_put_text(bucket.clone(), key, "newer").unwrap();
+
+        let conflict = PgTryBuilder::new(|| {
+            let _ = api::nats_kv_update_text(bucket.clone(), key, "value", revision);
+            false
+        })
+        .catch_when(PgSqlErrorCode::ERRCODE_T_R_SERIALIZATION_FAILURE, |_| true)
+        .execute();
+
+        assert!(
+            conflict,
+            "stale revision {revision} was not rejected with SQLSTATE 40001"
+        );
+    }
+
+    #[cfg(feature = "kv")]
+    #[pg_test]
+    fn test_pgnats_kv_keys_and_history() {
+        let bucket = "test_kv_history".to_string();
+
+        api::nats_put_text(bucket.clone(), "history.a", "first").unwrap();
+        let revision = api::nats_put_text(bucket.clone(), "history.a", "second").unwrap();
+        api::nats_put_text(bucket.clone(), "history.b", "value").unwrap();
+        api::nats_put_text(bucket.clone(), "history.c", "value").unwrap();
+        api::nats_delete_value(bucket.clone(), "history.c").unwrap();
+
+        let mut keys: Vec<_> = api::nats_kv_keys(bucket.clone(), Some("history.*"))
+            .unwrap()
+            .collect();
+        keys.sort();
+        assert_eq!(keys, vec!["history.a", "history.b"]);
+
+        let value = api::nats_kv_get_revision(bucket.clone(), "history.a", revision).unwrap();
+        assert_eq!(value.as_deref(), Some(b"second".as_slice()));
+
+        let operations: Vec<_> = api::nats_kv_history(bucket, "history.c")
+            .unwrap()
+            .map(|(_, operation, _, _)| operation)
+            .collect();
+        assert_eq!(operations.last().map(String::as_str), Some("delete"));
+    }
+
+    #[cfg(feature = "kv")]
+    #[pg_test]
+    fn test_pgnats_kv_bucket_management() {
+        let bucket = "test_kv_bucket_management".to_string();
+        let options = serde_json::json!({
+            "history": 5,
+            "ttl": 60,
+            "storage": "memory",
+        });
+
+        api::nats_kv_bucket_create(bucket.clone(), Some(pgrx::JsonB(options))).unwrap();
+        api::nats_put_text(bucket.clone(), "key", "value").unwrap();
+
+        let (name, values, _, history, ttl, max_bytes, storage, _, _) =
+            api::nats_kv_bucket_status(bucket.clone())
+                .unwrap()
+                .next()
+                .unwrap();
+        assert_eq!(name, bucket);
+        assert_eq!(values, 1);
+        assert_eq!(history, 5);
+        assert_eq!(ttl, Some(60.0));
+        assert_eq!(max_bytes, None);
+        assert_eq!(storage, "memory");
+
+        api::nats_kv_bucket_delete(bucket.clone()).unwrap();
+        assert!(api::nats_kv_bucket_status(bucket).is_err());
+    }
+
+    #[cfg(feature = "kv")]
+    #[pg_test]
+    fn test_pgnats_kv_purge() {
+        let bucket = "test_kv_purge".to_string();
+        let key = "purged";
+
+        api::nats_put_text(bucket.clone(), key, "value").unwrap();
+        api::nats_kv_purge(bucket.clone(), key).unwrap();
+
+        assert_eq!(api::nats_get_text(bucket.clone(), key).unwrap(), None);
+
+        let operations: Vec<_> = api::nats_kv_history(bucket, key)
+            .unwrap()
+            .map(|(_, operation, _, _)| operation)
+            .collect();
+        assert_eq!(operations, vec!["purge"]);
+    }
+
+    #[cfg(feature = "kv")]
+    #[pg_test]
+    fn test_pgnats_put_with_ttl() {
+        let bucket = "test_kv_ttl".to_string();
+        let key = "expiring";
+        let options = serde_json::json!({ "limit_markers": 60 });
+
+        api::nats_kv_bucket_create(bucket.clone(), Some(pgrx::JsonB(options))).unwrap();
+
+        let ttl = pgrx::datum::Interval::new(0, 0, 1_000_000).unwrap();
+        api::nats_put_text_with_ttl(bucket.clone(), key, "value", ttl).unwrap();
+        assert_eq!(
+            api::nats_get_text(bucket.clone(), key).unwrap().as_deref(),
+            Some("value")
+        );
+
+        std::thread::sleep(std::time::Duration::from_secs(3));
+        assert_eq!(api::nats_get_text(bucket.clone(), key).unwrap(), None);
+
+        api::nats_kv_bucket_delete(bucket).unwrap();
+    }
+
+    #[cfg(feature = "kv")]
+    #[pg_test(error = "TTL must be a whole number of seconds")]
+    fn test_pgnats_put_with_fractional_ttl() {
+        pgrx::Spi::run(
+            "SELECT nats_put_text_with_ttl('test_kv_ttl', 'expiring', 'value', '1.5 seconds')",
+        )
+        .unwrap();
+    }
+
+    #[cfg(feature = "kv")]
+    #[pg_test(
+        error = "Invalid bucket options: unknown field `size`, expected one of `history`, `ttl`, `limit_markers`, `max_bytes`, `storage`, `replicas`, `compression`"
+    )]
+    fn test_pgnats_kv_bucket_create_unknown_option() {
+        pgrx::Spi::run(
+            "SELECT nats_kv_bucket_create('test_kv_bucket_unknown_option', '{\"size\": 1}')",
+        )
+        .unwrap();
+    }
+
+    #[pg_test]
+    fn test_pgnats_stream_admin() {
+        let stream = "test_stream_admin".to_string();
+        let config = serde_json::json!({
+            "subjects": ["test_stream_admin.a"],
+            "storage": "memory",
+        });
+
+        api::nats_stream_create(stream.clone(), Some(pgrx::JsonB(config))).unwrap();
+
+        let changes = serde_json::json!({
+            "subjects": ["test_stream_admin.a", "test_stream_admin.b"],
+        });
+        api::nats_stream_update(stream.clone(), pgrx::JsonB(changes)).unwrap();
+
+        api::nats_publish_text_stream("test_stream_admin.b", "message".to_string(), None).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(100));
+
+        let (name, subjects, messages, _, first_seq, last_seq, consumers, _, config) =
+            api::nats_stream_info(stream.clone())
+                .unwrap()
+                .next()
+                .unwrap();
+        assert_eq!(name, stream);
+        assert_eq!(subjects, vec!["test_stream_admin.a", "test_stream_admin.b"]);
+        assert_eq!((messages, first_seq, last_seq, consumers), (1, 1, 1, 0));
+        assert_eq!(
+            config.unwrap().0.get("storage"),
+            Some(&serde_json::json!("memory"))
+        );
+
+        assert!(api::nats_stream_list()
+            .unwrap()
+            .any(|(name, ..)| name == stream));
+
+        api::nats_stream_delete(stream.clone()).unwrap();
+        assert!(api::nats_stream_info(stream).is_err());
+    }
+
+    #[pg_test]
+    fn test_pgnats_consumer_admin() {
+        let stream = "test_consumer_admin".to_string();
+        let subject = "test_consumer_admin.a";
+        let config = serde_json::json!({ "subjects": [subject], "storage": "memory" });
+
+        api::nats_stream_create(stream.clone(), Some(pgrx::JsonB(config))).unwrap();
+
+        let config = serde_json::json!({ "ack_policy": "explicit", "filter_subject": subject });
+        api::nats_consumer_create(&stream, "lagging", Some(pgrx::JsonB(config))).unwrap();
+
+        api::nats_publish_text_stream(subject, "first".to_string(), None).unwrap();
+        api::nats_publish_text_stream(subject, "second".to_string(), None).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(100));
+
+        let (stream_name, name, pending, ack_pending, redelivered, ..) =
+            api::nats_consumer_info(&stream, "lagging")
+                .unwrap()
+                .next()
+                .unwrap();
+        assert_eq!(
+            (stream_name.as_str(), name.as_str()),
+            (stream.as_str(), "lagging")
+        );
+        assert_eq!((pending, ack_pending, redelivered), (2, 0, 0));
+
+        let names: Vec<_> = api::nats_consumer_list(&stream)
+            .unwrap()
+            .map(|(_, name, ..)| name)
+            .collect();
+        assert_eq!(names, vec!["lagging"]);
+
+        api::nats_consumer_delete(&stream, "lagging").unwrap();
+        assert!(api::nats_consumer_info(&stream, "lagging").is_err());
+
+        api::nats_stream_delete(stream).unwrap();
+    }
+
+    #[pg_test]
+    fn test_pgnats_consumer_fetch_and_ack() {
+        let stream = "test_consumer_fetch".to_string();
+        let subject = "test_consumer_fetch.a";
+        let config = serde_json::json!({ "subjects": [subject], "storage": "memory" });
+
+        api::nats_stream_create(stream.clone(), Some(pgrx::JsonB(config))).unwrap();
+
+        let config = serde_json::json!({ "ack_policy": "explicit" });
+        api::nats_consumer_create(&stream, "puller", Some(pgrx::JsonB(config))).unwrap();
+
+        api::nats_publish_text_stream(subject, "first".to_string(), None).unwrap();
+        api::nats_publish_text_stream(subject, "second".to_string(), None).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(100));
+
+        let timeout = pgrx::datum::Interval::new(0, 0, 1_000_000).unwrap();
+        let (messages, tokens): (Vec<_>, Vec<_>) =
+            api::nats_consumer_fetch(&stream, "puller", 10, timeout, false)
+                .unwrap()
+                .map(|(_, _, _, payload, _, delivered, token)| ((payload, delivered), token))
+                .unzip();
+        assert_eq!(
+            messages,
+            vec![(b"first".to_vec(), 1), (b"second".to_vec(), 1)]
+        );
+
+        api::nats_ack(tokens[0].clone()).unwrap();
+        api::nats_nak(tokens[1].clone(), None).unwrap();
+
+        let (messages, tokens): (Vec<_>, Vec<_>) =
+            api::nats_consumer_fetch(&stream, "puller", 10, timeout, false)
+                .unwrap()
+                .map(|(_, _, _, payload, _, delivered, token)| ((payload, delivered), token))
+                .unzip();
+        assert_eq!(messages, vec![(b"second".to_vec(), 2)]);
+
+        api::nats_ack(tokens[0].clone()).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(100));
+
+        let (_, _, pending, ack_pending, ..) = api::nats_consumer_info(&stream, "puller")
+            .unwrap()
+            .next()
+            .unwrap();
+        assert_eq!((pending, ack_pending), (0, 0));
+
+        assert!(api::nats_ack("test_consumer_fetch.a".to_string()).is_err());
+        assert!(api::nats_consumer_fetch(&stream, "puller", 0, timeout, false).is_err());
+
+        api::nats_stream_delete(stream).unwrap();
+    }
+
+    #[pg_test]
+    fn test_pgnats_stream_read_messages() {
+        let stream = "test_stream_read".to_string();
+        let config = serde_json::json!({ "subjects": ["test_stream_read.*"], "storage": "memory" });
+
+        api::nats_stream_create(stream.clone(), Some(pgrx::JsonB(config))).unwrap();
+
+        for (subject, payload) in [
+            ("test_stream_read.a", "first"),
+            ("test_stream_read.b", "second"),
+            ("test_stream_read.a", "third"),
+        ] {
+            api::nats_publish_text_stream(subject, payload.to_string(), None).unwrap();
+        }
+        std::thread::sleep(std::time::Duration::from_millis(100));
+
+        let (seq, subject, headers, payload, time) = api::nats_stream_get_message(&stream, 2)
+            .unwrap()
+            .next()
+            .unwrap();
+        assert_eq!(
+            (seq, subject.as_str(), payload.as_slice()),
+            (2, "test_stream_read.b", b"second".as_slice())
+        );
+        assert!(headers.is_none());
+        assert!(time.is_some());
+
+        let (seq, _, _, payload, _) = api::nats_stream_get_last(&stream, "test_stream_read.a")
+            .unwrap()
+            .next()
+            .unwrap();
+        assert_eq!((seq, payload.as_slice()), (3, b"third".as_slice()));
+
+        assert!(api::nats_stream_get_message(&stream, 10).is_err());
+
+        let payloads: Vec<_> = api::nats_stream_read(&stream, 1, None, None)
+            .unwrap()
+            .map(|(_, _, _, payload, _)| payload)
+            .collect();
+        assert_eq!(
+            payloads,
+            vec![b"first".to_vec(), b"second".to_vec(), b"third".to_vec()]
+        );
+
+        let seqs: Vec<_> =
+            api::nats_stream_read(&stream, 2, Some(1), Some("test_stream_read.a".to_string()))
+                .unwrap()
+                .map(|(seq, ..)| seq)
+                .collect();
+        assert_eq!(seqs, vec![3]);
+
+        api::nats_stream_delete(stream).unwrap();
+    }
+
     #[cfg(feature = "object_store")]
     #[pg_test]
     fn test_pgnats_put_and_get_file() {
@@ -260,13 +1058,247 @@ mod tests {
         let put_res = api::nats_put_file(bucket.clone(), key, content.clone());
         assert!(put_res.is_ok(), "put_file failed: {:?}", put_res);
 
-        let get_res = api::nats_get_file(bucket.clone(), key);
+        let get_res = api::nats_get_file(bucket.clone(), key, false);
         assert!(get_res.is_ok(), "get_file failed: {:?}", get_res);
 
         let returned = get_res.unwrap();
         assert_eq!(content, returned);
     }
 
+    #[cfg(feature = "object_store")]
+    #[pg_test]
+    fn test_pgnats_file_streaming() {
+        let bucket = "test_file_streaming".to_string();
+        // Spans several chunks and ends with a partial one.
+        let content: Vec<u8> = (0..2_500_000_u32).map(|i| (i % 251) as u8).collect();
+
+        let source = std::env::temp_dir().join("pgnats_test_file_streaming.in");
+        let target = std::env::temp_dir().join("pgnats_test_file_streaming.out");
+        std::fs::write(&source, &content).unwrap();
+
+        let size =
+            api::nats_put_file_from_path(bucket.clone(), "from_path", source.to_str().unwrap())
+                .unwrap();
+        assert_eq!(size as usize, content.len());
+
+        let written =
+            api::nats_get_file_to_path(bucket.clone(), "from_path", target.to_str().unwrap())
+                .unwrap();
+        assert_eq!(written as usize, content.len());
+        assert_eq!(std::fs::read(&target).unwrap(), content);
+
+        let loid = api::nats_get_file_to_lo(bucket.clone(), "from_path").unwrap();
+        let lo_content =
+            pgrx::Spi::get_one_with_args::<Vec<u8>>("SELECT lo_get($1)", &[loid.into()])
+                .unwrap()
+                .unwrap();
+        assert_eq!(lo_content, content);
+
+        let size = api::nats_put_file_from_lo(bucket.clone(), "from_lo".to_string(), loid).unwrap();
+        assert_eq!(size as usize, content.len());
+        assert_eq!(
+            api::nats_get_file(bucket.clone(), "from_lo", false).unwrap(),
+            content
+        );
+
+        let empty = pgrx::Spi::get_one::<pgrx::pg_sys::Oid>("SELECT lo_from_bytea(0, ''::bytea)")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            api::nats_put_file_from_lo(bucket.clone(), "empty".to_string(), empty).unwrap(),
+            0
+        );
+
+        let _ = std::fs::remove_file(source);
+        let _ = std::fs::remove_file(target);
+    }
+
+    #[cfg(feature = "object_store")]
+    #[pg_test]
+    fn test_pgnats_file_meta() {
+        let bucket = "test_file_meta".to_string();
+        let metadata = serde_json::json!({ "owner": "finance" });
+        let headers = serde_json::json!({ "Content-Type": "text/plain" });
+
+        let (name, description, meta, headers, ..) = api::nats_put_file_with_meta(
+            bucket.clone(),
+            "report.txt",
+            b"report".to_vec(),
+            Some("Quarterly report".to_string()),
+            Some(pgrx::JsonB(metadata.clone())),
+            Some(pgrx::JsonB(headers)),
+        )
+        .unwrap()
+        .next()
+        .unwrap();
+        assert_eq!(name, "report.txt");
+        assert_eq!(description.as_deref(), Some("Quarterly report"));
+        assert_eq!(meta.0, metadata);
+        assert!(headers.is_some());
+
+        let (name, description, meta, ..) = api::nats_update_file_meta(
+            bucket.clone(),
+            "report.txt",
+            Some("report-q1.txt".to_string()),
+            None,
+            Some(pgrx::JsonB(serde_json::json!({ "owner": "audit" }))),
+            None,
+        )
+        .unwrap()
+        .next()
+        .unwrap();
+        assert_eq!(name, "report-q1.txt");
+        assert_eq!(description.as_deref(), Some("Quarterly report"));
+        assert_eq!(meta.0, serde_json::json!({ "owner": "audit" }));
+
+        assert_eq!(
+            api::nats_get_file(bucket.clone(), "report-q1.txt", false).unwrap(),
+            b"report"
+        );
+        assert!(api::nats_get_file(bucket.clone(), "report.txt", false).is_err());
+
+        assert!(api::nats_put_file_with_meta(
+            bucket,
+            "invalid.txt",
+            Vec::new(),
+            None,
+            Some(pgrx::JsonB(serde_json::json!({ "size": 1 }))),
+            None,
+        )
+        .is_err());
+    }
+
+    #[cfg(feature = "object_store")]
+    #[pg_test]
+    fn test_pgnats_object_store_management() {
+        let store = "test_object_store_admin".to_string();
+        let other = "test_object_store_admin_other".to_string();
+        let options = serde_json::json!({ "description": "Reports", "storage": "memory" });
+
+        api::nats_object_store_create(store.clone(), Some(pgrx::JsonB(options))).unwrap();
+        api::nats_object_store_create(other.clone(), None).unwrap();
+
+        api::nats_put_file(store.clone(), "report.txt", b"report".to_vec()).unwrap();
+
+        let (name, ..) = api::nats_link_file(store.clone(), "latest.txt", "report.txt", None)
+            .unwrap()
+            .next()
+            .unwrap();
+        assert_eq!(name, "latest.txt");
+        assert_eq!(
+            api::nats_get_file(store.clone(), "latest.txt", false).unwrap(),
+            b"report"
+        );
+
+        api::nats_link_file(
+            other.clone(),
+            "report.txt",
+            "report.txt",
+            Some(store.clone()),
+        )
+        .unwrap();
+        assert_eq!(
+            api::nats_get_file(other.clone(), "report.txt", false).unwrap(),
+            b"report"
+        );
+
+        api::nats_link_object_store(other.clone(), "reports", store.clone()).unwrap();
+
+        let (bucket, description, size, sealed, stream, _, _, storage, ..) =
+            api::nats_object_store_status(store.clone())
+                .unwrap()
+                .next()
+                .unwrap();
+        assert_eq!(bucket, store);
+        assert_eq!(description.as_deref(), Some("Reports"));
+        assert!(size > 0);
+        assert!(!sealed);
+        assert_eq!(stream, "OBJ_test_object_store_admin");
+        assert_eq!(storage, "memory");
+
+        api::nats_object_store_seal(&store).unwrap();
+        let (_, _, _, sealed, ..) = api::nats_object_store_status(store.clone())
+            .unwrap()
+            .next()
+            .unwrap();
+        assert!(sealed);
+        assert!(api::nats_put_file(store.clone(), "new.txt", b"new".to_vec()).is_err());
+        assert_eq!(
+            api::nats_get_file(store.clone(), "report.txt", false).unwrap(),
+            b"report"
+        );
+
+        api::nats_object_store_delete(other.clone()).unwrap();
+        api::nats_object_store_delete(store.clone()).unwrap();
+        assert!(api::nats_object_store_status(store).is_err());
+    }
+
+    #[cfg(feature = "object_store")]
+    #[pg_test]
+    fn test_pgnats_verify_file() {
+        let bucket = "test_verify_file".to_string();
+        let content = b"verified content".to_vec();
+
+        api::nats_put_file(bucket.clone(), "intact.txt", content.clone()).unwrap();
+        api::nats_put_file(bucket.clone(), "corrupted.txt", content.clone()).unwrap();
+        corrupt_file_digest(&bucket, "corrupted.txt");
+
+        assert!(api::nats_verify_file(bucket.clone(), "intact.txt").unwrap());
+        assert!(!api::nats_verify_file(bucket.clone(), "corrupted.txt").unwrap());
+
+        assert_eq!(
+            api::nats_get_file(bucket, "intact.txt", true).unwrap(),
+            content
+        );
+    }
+
+    #[cfg(feature = "object_store")]
+    #[pg_test(error = "[PGNATS]: Content of file 'corrupted.txt' does not match its digest")]
+    fn test_pgnats_get_file_verify_corrupted() {
+        let bucket = "test_verify_file_on_read".to_string();
+
+        api::nats_put_file(bucket.clone(), "corrupted.txt", b"content".to_vec()).unwrap();
+        corrupt_file_digest(&bucket, "corrupted.txt");
+
+        let _ = api::nats_get_file(bucket, "corrupted.txt", true);
+    }
+
+    /// Replaces the stored info of a file with one carrying a wrong digest.
+    #[cfg(feature = "object_store")]
+    fn corrupt_file_digest(bucket: &str, name: &str) {
+        use base64::Engine;
+
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+
+        rt.block_on(async {
+            let client = async_nats::connect(format!("{NATS_HOST}:{NATS_PORT}"))
+                .await
+                .unwrap();
+            let js = async_nats::jetstream::new(client);
+            let store = js.get_object_store(bucket).await.unwrap();
+
+            let mut info = store.info(name).await.unwrap();
+            let encoding = base64::engine::general_purpose::URL_SAFE;
+            info.digest = Some(format!("SHA-256={}", encoding.encode([0_u8; 32])));
+
+            let mut headers = async_nats::HeaderMap::new();
+            headers.insert("Nats-Rollup", "sub");
+
+            js.publish_with_headers(
+                format!("$O.{bucket}.M.{}", encoding.encode(name)),
+                headers,
+                serde_json::to_vec(&info).unwrap().into(),
+            )
+            .await
+            .unwrap()
+            .await
+            .unwrap();
+        });
+    }
+
     #[cfg(feature = "object_store")]
     #[pg_test]
     fn test_pgnats_file_info() {
@@ -319,7 +1351,7 @@ mod tests {
         api::nats_put_file(bucket.clone(), key, content).unwrap();
         api::nats_delete_file(bucket.clone(), key).unwrap();
 
-        let get_res = api::nats_get_file(bucket.clone(), key);
+        let get_res = api::nats_get_file(bucket.clone(), key, false);
         assert!(
             get_res.is_err(),
             "get_file after delete failed: {:?}",
@@ -21,6 +21,18 @@ pub fn init_test_shared_memory() {
 
     pg_shmem_init!(LAUNCHER_MESSAGE_BUS6);
     pg_shmem_init!(TEST_RESULT6);
+
+    pg_shmem_init!(LAUNCHER_MESSAGE_BUS7);
+    pg_shmem_init!(TEST_RESULT7);
+
+    pg_shmem_init!(LAUNCHER_MESSAGE_BUS8);
+    pg_shmem_init!(TEST_RESULT8);
+
+    pg_shmem_init!(LAUNCHER_MESSAGE_BUS9);
+    pg_shmem_init!(TEST_RESULT9);
+
+    pg_shmem_init!(LAUNCHER_MESSAGE_BUS10);
+    pg_shmem_init!(TEST_RESULT10);
 }
 
 #[cfg(any(test, feature = "pg_test"))]
@@ -36,8 +48,16 @@ mod tests_items {
         CREATE TABLE test_subscription_table_1 (
             subject TEXT NOT NULL,
             callback TEXT NOT NULL,
+            with_subject BOOLEAN NOT NULL DEFAULT false,
             UNIQUE(subject, callback)
         );
+
+        CREATE TABLE test_kv_watch_table_1 (
+            bucket TEXT NOT NULL,
+            key_pattern TEXT NOT NULL,
+            callback TEXT NOT NULL,
+            UNIQUE(bucket, key_pattern, callback)
+        );
         CREATE FOREIGN DATA WRAPPER pgnats_fdw_test_1;
         CREATE SERVER test_background_worker_sub_call_unsub_call FOREIGN DATA WRAPPER pgnats_fdw_test_1 OPTIONS (host 'localhost', port '4222');
         "#  // Extension SQL code
@@ -52,9 +72,17 @@ mod tests_items {
         CREATE TABLE test_subscription_table_2 (
             subject TEXT NOT NULL,
             callback TEXT NOT NULL,
+            with_subject BOOLEAN NOT NULL DEFAULT false,
             UNIQUE(subject, callback)
         );
 
+        CREATE TABLE test_kv_watch_table_2 (
+            bucket TEXT NOT NULL,
+            key_pattern TEXT NOT NULL,
+            callback TEXT NOT NULL,
+            UNIQUE(bucket, key_pattern, callback)
+        );
+
         INSERT INTO test_subscription_table_2 (subject, callback) VALUES
             ('test_background_worker_restore_after_restart', 'public.test_2_fn')
         ;
@@ -73,9 +101,17 @@ mod tests_items {
         CREATE TABLE test_subscription_table_3 (
             subject TEXT NOT NULL,
             callback TEXT NOT NULL,
+            with_subject BOOLEAN NOT NULL DEFAULT false,
             UNIQUE(subject, callback)
         );
 
+        CREATE TABLE test_kv_watch_table_3 (
+            bucket TEXT NOT NULL,
+            key_pattern TEXT NOT NULL,
+            callback TEXT NOT NULL,
+            UNIQUE(bucket, key_pattern, callback)
+        );
+
         CREATE FOREIGN DATA WRAPPER pgnats_fdw_test_3 VALIDATOR pgnats_fdw_validator_test_3;
         CREATE SERVER test_background_worker_changed_fdw_config FOREIGN DATA WRAPPER pgnats_fdw_test_3 OPTIONS (host 'localhost', port '4222');
         "#
@@ -90,9 +126,17 @@ mod tests_items {
         CREATE TABLE test_subscription_table_4 (
             subject TEXT NOT NULL,
             callback TEXT NOT NULL,
+            with_subject BOOLEAN NOT NULL DEFAULT false,
             UNIQUE(subject, callback)
         );
 
+        CREATE TABLE test_kv_watch_table_4 (
+            bucket TEXT NOT NULL,
+            key_pattern TEXT NOT NULL,
+            callback TEXT NOT NULL,
+            UNIQUE(bucket, key_pattern, callback)
+        );
+
         CREATE FOREIGN DATA WRAPPER pgnats_fdw_test_4 VALIDATOR pgnats_fdw_validator_test_4;
         CREATE SERVER test_background_worker_whoami FOREIGN DATA WRAPPER pgnats_fdw_test_4 OPTIONS (host 'localhost', port '4222', notify_subject 'test_background_worker_whoami', patroni_url 'http://localhost:28008/patroni/');
         "#
@@ -107,9 +151,17 @@ mod tests_items {
         CREATE TABLE test_subscription_table_5 (
             subject TEXT NOT NULL,
             callback TEXT NOT NULL,
+            with_subject BOOLEAN NOT NULL DEFAULT false,
             UNIQUE(subject, callback)
         );
 
+        CREATE TABLE test_kv_watch_table_5 (
+            bucket TEXT NOT NULL,
+            key_pattern TEXT NOT NULL,
+            callback TEXT NOT NULL,
+            UNIQUE(bucket, key_pattern, callback)
+        );
+
         CREATE FOREIGN DATA WRAPPER pgnats_fdw_test_5 VALIDATOR pgnats_fdw_validator_test_5;
         CREATE SERVER test_background_worker_m2r FOREIGN DATA WRAPPER pgnats_fdw_test_5 OPTIONS (host 'localhost', port '4222');
         "#
@@ -124,13 +176,174 @@ mod tests_items {
         CREATE TABLE test_subscription_table_6 (
             subject TEXT NOT NULL,
             callback TEXT NOT NULL,
+            with_subject BOOLEAN NOT NULL DEFAULT false,
             UNIQUE(subject, callback)
         );
 
+        CREATE TABLE test_kv_watch_table_6 (
+            bucket TEXT NOT NULL,
+            key_pattern TEXT NOT NULL,
+            callback TEXT NOT NULL,
+            UNIQUE(bucket, key_pattern, callback)
+        );
+
         CREATE FOREIGN DATA WRAPPER pgnats_fdw_test_6 VALIDATOR pgnats_fdw_validator_test_6;
         CREATE SERVER test_background_worker_r2m FOREIGN DATA WRAPPER pgnats_fdw_test_6 OPTIONS (host 'localhost', port '4222');
         "#
     );
+
+    generate_test_background_worker!(
+        7,
+        c"l7",
+        c"r7",
+        "create_test_fdw_7",
+        r#"
+        CREATE TABLE test_subscription_table_7 (
+            subject TEXT NOT NULL,
+            callback TEXT NOT NULL,
+            with_subject BOOLEAN NOT NULL DEFAULT false,
+            UNIQUE(subject, callback)
+        );
+
+        CREATE TABLE test_kv_watch_table_7 (
+            bucket TEXT NOT NULL,
+            key_pattern TEXT NOT NULL,
+            callback TEXT NOT NULL,
+            UNIQUE(bucket, key_pattern, callback)
+        );
+
+        CREATE FOREIGN DATA WRAPPER pgnats_fdw_test_7 VALIDATOR pgnats_fdw_validator_test_7;
+        CREATE SERVER test_background_worker_kv_watch FOREIGN DATA WRAPPER pgnats_fdw_test_7 OPTIONS (host 'localhost', port '4222');
+        "#
+    );
+
+    #[pgrx::pg_extern]
+    pub fn test_7_kv_fn(key: String, value: Option<Vec<u8>>, revision: i64, operation: String) {
+        use std::hash::{DefaultHasher, Hasher};
+
+        let mut hasher = DefaultHasher::new();
+        hasher.write(key.as_bytes());
+        hasher.write(&value.unwrap_or_default());
+        hasher.write_i64(revision);
+        hasher.write(operation.as_bytes());
+
+        *TEST_RESULT7.exclusive() = hasher.finish();
+    }
+
+    generate_test_background_worker!(
+        8,
+        c"l8",
+        c"r8",
+        "create_test_fdw_8",
+        r#"
+        CREATE TABLE test_subscription_table_8 (
+            subject TEXT NOT NULL,
+            callback TEXT NOT NULL,
+            with_subject BOOLEAN NOT NULL DEFAULT false,
+            UNIQUE(subject, callback)
+        );
+
+        CREATE TABLE test_kv_watch_table_8 (
+            bucket TEXT NOT NULL,
+            key_pattern TEXT NOT NULL,
+            callback TEXT NOT NULL,
+            UNIQUE(bucket, key_pattern, callback)
+        );
+
+        CREATE FOREIGN DATA WRAPPER pgnats_fdw_test_8 VALIDATOR pgnats_fdw_validator_test_8;
+        CREATE SERVER test_background_worker_wildcards FOREIGN DATA WRAPPER pgnats_fdw_test_8 OPTIONS (host 'localhost', port '4222');
+        "#
+    );
+
+    #[pgrx::pg_extern]
+    pub fn test_8_subject_fn(bytes: Vec<u8>, subject: String) {
+        use std::hash::{DefaultHasher, Hasher};
+
+        let mut hasher = DefaultHasher::new();
+        hasher.write(subject.as_bytes());
+        hasher.write(&bytes);
+
+        let mut result = TEST_RESULT8.exclusive();
+        *result = result.wrapping_add(hasher.finish());
+    }
+
+    generate_test_background_worker!(
+        9,
+        c"l9",
+        c"r9",
+        "create_test_fdw_9",
+        r#"
+        CREATE TABLE test_subscription_table_9 (
+            subject TEXT NOT NULL,
+            callback TEXT NOT NULL,
+            with_subject BOOLEAN NOT NULL DEFAULT false,
+            UNIQUE(subject, callback)
+        );
+
+        CREATE TABLE test_kv_watch_table_9 (
+            bucket TEXT NOT NULL,
+            key_pattern TEXT NOT NULL,
+            callback TEXT NOT NULL,
+            UNIQUE(bucket, key_pattern, callback)
+        );
+
+        CREATE FOREIGN DATA WRAPPER pgnats_fdw_test_9 VALIDATOR pgnats_fdw_validator_test_9;
+        CREATE SERVER test_background_worker_overlapping_wildcards FOREIGN DATA WRAPPER pgnats_fdw_test_9 OPTIONS (host 'localhost', port '4222');
+        "#
+    );
+
+    #[pgrx::pg_extern]
+    pub fn test_9_subject_fn(bytes: Vec<u8>, subject: String) {
+        use std::hash::{DefaultHasher, Hasher};
+
+        let mut hasher = DefaultHasher::new();
+        hasher.write(subject.as_bytes());
+        hasher.write(&bytes);
+
+        let mut result = TEST_RESULT9.exclusive();
+        *result = result.wrapping_add(hasher.finish());
+    }
+
+    generate_test_background_worker!(
+        10,
+        c"l10",
+        c"r10",
+        "create_test_fdw_10",
+        r#"
+        CREATE TABLE test_subscription_table_10 (
+            subject TEXT NOT NULL,
+            callback TEXT NOT NULL,
+            with_subject BOOLEAN NOT NULL DEFAULT false,
+            UNIQUE(subject, callback)
+        );
+
+        CREATE TABLE test_kv_watch_table_10 (
+            bucket TEXT NOT NULL,
+            key_pattern TEXT NOT NULL,
+            callback TEXT NOT NULL,
+            UNIQUE(bucket, key_pattern, callback)
+        );
+
+        CREATE FOREIGN DATA WRAPPER pgnats_fdw_test_10 VALIDATOR pgnats_fdw_validator_test_10;
+        CREATE SERVER test_background_worker_resubscribe FOREIGN DATA WRAPPER pgnats_fdw_test_10 OPTIONS (host 'localhost', port '4222');
+        "#
+    );
+
+    /// Sets the bit of the message number in the payload, so that duplicates do not
+    /// hide a lost message.
+    #[pgrx::pg_extern]
+    pub fn test_10_seq_fn(bytes: Vec<u8>) {
+        let seq = std::str::from_utf8(&bytes)
+            .ok()
+            .and_then(|seq| seq.parse::<u32>().ok());
+
+        if let Some(bit) = seq.and_then(|seq| 1u64.checked_shl(seq)) {
+            *TEST_RESULT10.exclusive() |= bit;
+        }
+    }
+
+    #[pgrx::pg_extern]
+    pub fn test_10_noop_fn(_bytes: Vec<u8>) {}
 }
 
 #[cfg(any(test, feature = "pg_test"))]
@@ -178,11 +391,12 @@ pub(super) mod tests {
         pgnats_subscribe(
             subject.to_string(),
             fn_name.to_string(),
+            false,
             &LAUNCHER_MESSAGE_BUS1,
         );
         std::thread::sleep(std::time::Duration::from_secs(3));
 
-        api::nats_publish_text(subject, content1.to_string(), None, None).unwrap();
+        api::nats_publish_text(subject, content1.to_string(), None, None, false).unwrap();
         std::thread::sleep(std::time::Duration::from_secs(3));
 
         let mut hasher = DefaultHasher::new();
@@ -196,7 +410,7 @@ pub(super) mod tests {
         );
         std::thread::sleep(std::time::Duration::from_secs(3));
 
-        api::nats_publish_text(subject, content2.to_string(), None, None).unwrap();
+        api::nats_publish_text(subject, content2.to_string(), None, None, false).unwrap();
         std::thread::sleep(std::time::Duration::from_secs(3));
 
         let mut hasher = DefaultHasher::new();
@@ -226,7 +440,7 @@ pub(super) mod tests {
         let _ = worker.wait_for_startup().unwrap();
         std::thread::sleep(std::time::Duration::from_secs(3));
 
-        api::nats_publish_text(subject, content.to_string(), None, None).unwrap();
+        api::nats_publish_text(subject, content.to_string(), None, None, false).unwrap();
         std::thread::sleep(std::time::Duration::from_secs(3));
 
         let mut hasher = DefaultHasher::new();
@@ -261,6 +475,7 @@ pub(super) mod tests {
         pgnats_subscribe(
             subject.to_string(),
             fn_name.to_string(),
+            false,
             &LAUNCHER_MESSAGE_BUS3,
         );
         std::thread::sleep(std::time::Duration::from_secs(3));
@@ -272,7 +487,7 @@ pub(super) mod tests {
         .unwrap();
         std::thread::sleep(std::time::Duration::from_secs(3));
 
-        api::nats_publish_text(subject, content1.to_string(), None, None).unwrap();
+        api::nats_publish_text(subject, content1.to_string(), None, None, false).unwrap();
         std::thread::sleep(std::time::Duration::from_secs(3));
 
         let mut hasher = DefaultHasher::new();
@@ -286,7 +501,7 @@ pub(super) mod tests {
 
         std::thread::sleep(std::time::Duration::from_secs(3));
 
-        api::nats_publish_text(subject, content2.to_string(), None, None).unwrap();
+        api::nats_publish_text(subject, content2.to_string(), None, None, false).unwrap();
         std::thread::sleep(std::time::Duration::from_secs(3));
 
         let mut hasher = DefaultHasher::new();
@@ -406,11 +621,12 @@ pub(super) mod tests {
         pgnats_subscribe(
             subject.to_string(),
             fn_name.to_string(),
+            false,
             &LAUNCHER_MESSAGE_BUS5,
         );
         std::thread::sleep(std::time::Duration::from_secs(3));
 
-        api::nats_publish_text(subject, content1.to_string(), None, None).unwrap();
+        api::nats_publish_text(subject, content1.to_string(), None, None, false).unwrap();
         std::thread::sleep(std::time::Duration::from_secs(3));
 
         let mut hasher = DefaultHasher::new();
@@ -420,7 +636,7 @@ pub(super) mod tests {
         __internal_change_state(false, &LAUNCHER_MESSAGE_BUS5);
         std::thread::sleep(std::time::Duration::from_secs(3));
 
-        api::nats_publish_text(subject, content2.to_string(), None, None).unwrap();
+        api::nats_publish_text(subject, content2.to_string(), None, None, false).unwrap();
         std::thread::sleep(std::time::Duration::from_secs(3));
 
         let mut hasher = DefaultHasher::new();
@@ -455,11 +671,12 @@ pub(super) mod tests {
         pgnats_subscribe(
             subject.to_string(),
             fn_name.to_string(),
+            false,
             &LAUNCHER_MESSAGE_BUS6,
         );
         std::thread::sleep(std::time::Duration::from_secs(3));
 
-        api::nats_publish_text(subject, content1.to_string(), None, None).unwrap();
+        api::nats_publish_text(subject, content1.to_string(), None, None, false).unwrap();
         std::thread::sleep(std::time::Duration::from_secs(3));
 
         let mut hasher = DefaultHasher::new();
@@ -469,7 +686,7 @@ pub(super) mod tests {
         __internal_change_state(false, &LAUNCHER_MESSAGE_BUS6);
         std::thread::sleep(std::time::Duration::from_secs(3));
 
-        api::nats_publish_text(subject, content2.to_string(), None, None).unwrap();
+        api::nats_publish_text(subject, content2.to_string(), None, None, false).unwrap();
         std::thread::sleep(std::time::Duration::from_secs(3));
 
         let mut hasher = DefaultHasher::new();
@@ -479,7 +696,7 @@ pub(super) mod tests {
         __internal_change_state(true, &LAUNCHER_MESSAGE_BUS6);
         std::thread::sleep(std::time::Duration::from_secs(3));
 
-        api::nats_publish_text(subject, content2.to_string(), None, None).unwrap();
+        api::nats_publish_text(subject, content2.to_string(), None, None, false).unwrap();
         std::thread::sleep(std::time::Duration::from_secs(3));
 
         let mut hasher = DefaultHasher::new();
@@ -490,9 +707,310 @@ pub(super) mod tests {
         terminate.wait_for_shutdown().unwrap();
     }
 
+    #[pg_test]
+    fn test_background_worker_kv_watch() {
+        use pgrx::function_name;
+
+        let bucket = function_name!().split("::").last().unwrap().to_string();
+        let key = "watched.key";
+        let fn_name = "test_7_kv_fn";
+        let content1 = "Hello, World!";
+        let content2 = "Привет, Мир!";
+
+        let worker = BackgroundWorkerBuilder::new("PGNats Background Worker Launcher 7")
+            .set_function("background_worker_launcher_entry_point_test_7")
+            .set_library(EXTENSION_NAME)
+            .enable_spi_access()
+            .set_notify_pid(unsafe { pgrx::pg_sys::MyProcPid })
+            .load_dynamic()
+            .unwrap();
+
+        let _ = worker.wait_for_startup().unwrap();
+        std::thread::sleep(std::time::Duration::from_secs(3));
+
+        let _ = api::nats_put_text(bucket.clone(), "unwatched", content1.to_string()).unwrap();
+
+        pgnats_kv_watch(
+            bucket.clone(),
+            "watched.>".to_string(),
+            fn_name.to_string(),
+            &LAUNCHER_MESSAGE_BUS7,
+        );
+        std::thread::sleep(std::time::Duration::from_secs(3));
+
+        let revision = api::nats_put_text(bucket.clone(), key, content2.to_string()).unwrap();
+        std::thread::sleep(std::time::Duration::from_secs(3));
+
+        let mut hasher = DefaultHasher::new();
+        hasher.write(key.as_bytes());
+        hasher.write(content2.as_bytes());
+        hasher.write_i64(revision);
+        hasher.write(b"put");
+        assert_eq!(*TEST_RESULT7.share(), hasher.finish());
+
+        let count = Spi::get_one::<i64>(
+            "SELECT COUNT(*) FROM test_kv_watch_table_7 WHERE callback = 'test_7_kv_fn'",
+        )
+        .unwrap();
+        assert_eq!(count, Some(1));
+
+        let _ = api::nats_put_text(bucket.clone(), "unwatched", content2.to_string()).unwrap();
+        std::thread::sleep(std::time::Duration::from_secs(3));
+        assert_eq!(*TEST_RESULT7.share(), hasher.finish());
+
+        let terminate = worker.terminate();
+        terminate.wait_for_shutdown().unwrap();
+    }
+
+    #[pg_test]
+    fn test_background_worker_wildcard_subscriptions() {
+        use pgrx::function_name;
+
+        let prefix = function_name!().split("::").last().unwrap();
+        let fn_name = "test_8_subject_fn";
+        let content = "Hello, World!";
+
+        let hash = |subject: &str| {
+            let mut hasher = DefaultHasher::new();
+            hasher.write(subject.as_bytes());
+            hasher.write(content.as_bytes());
+            hasher.finish()
+        };
+
+        let worker = BackgroundWorkerBuilder::new("PGNats Background Worker Launcher 8")
+            .set_function("background_worker_launcher_entry_point_test_8")
+            .set_library(EXTENSION_NAME)
+            .enable_spi_access()
+            .set_notify_pid(unsafe { pgrx::pg_sys::MyProcPid })
+            .load_dynamic()
+            .unwrap();
+
+        let _ = worker.wait_for_startup().unwrap();
+        std::thread::sleep(std::time::Duration::from_secs(3));
+
+        pgnats_subscribe(
+            format!("{prefix}.>"),
+            fn_name.to_string(),
+            true,
+            &LAUNCHER_MESSAGE_BUS8,
+        );
+        pgnats_subscribe(
+            format!("{prefix}.*.created"),
+            fn_name.to_string(),
+            true,
+            &LAUNCHER_MESSAGE_BUS8,
+        );
+        std::thread::sleep(std::time::Duration::from_secs(3));
+
+        // Matches both subscriptions, delivered once by the single NATS subscription
+        let created = format!("{prefix}.eu.created");
+        api::nats_publish_text(&created, content.to_string(), None, None, false).unwrap();
+        std::thread::sleep(std::time::Duration::from_secs(3));
+
+        let mut expected = hash(&created).wrapping_mul(2);
+        assert_eq!(*TEST_RESULT8.share(), expected);
+
+        let deleted = format!("{prefix}.eu.deleted");
+        api::nats_publish_text(&deleted, content.to_string(), None, None, false).unwrap();
+        std::thread::sleep(std::time::Duration::from_secs(3));
+
+        expected = expected.wrapping_add(hash(&deleted));
+        assert_eq!(*TEST_RESULT8.share(), expected);
+
+        pgnats_unsubscribe(
+            format!("{prefix}.>"),
+            fn_name.to_string(),
+            &LAUNCHER_MESSAGE_BUS8,
+        );
+        std::thread::sleep(std::time::Duration::from_secs(3));
+
+        // The remaining subscription gets its own NATS subscription
+        let created = format!("{prefix}.us.created");
+        api::nats_publish_text(&created, content.to_string(), None, None, false).unwrap();
+        api::nats_publish_text(&deleted, content.to_string(), None, None, false).unwrap();
+        std::thread::sleep(std::time::Duration::from_secs(3));
+
+        expected = expected.wrapping_add(hash(&created));
+        assert_eq!(*TEST_RESULT8.share(), expected);
+
+        let terminate = worker.terminate();
+        terminate.wait_for_shutdown().unwrap();
+    }
+
+    #[pg_test]
+    fn test_background_worker_overlapping_wildcard_subscriptions() {
+        use pgrx::function_name;
+
+        let prefix = function_name!().split("::").last().unwrap();
+        let fn_name = "test_9_subject_fn";
+        let content = "Hello, World!";
+
+        let hash = |subject: &str| {
+            let mut hasher = DefaultHasher::new();
+            hasher.write(subject.as_bytes());
+            hasher.write(content.as_bytes());
+            hasher.finish()
+        };
+
+        let worker = BackgroundWorkerBuilder::new("PGNats Background Worker Launcher 9")
+            .set_function("background_worker_launcher_entry_point_test_9")
+            .set_library(EXTENSION_NAME)
+            .enable_spi_access()
+            .set_notify_pid(unsafe { pgrx::pg_sys::MyProcPid })
+            .load_dynamic()
+            .unwrap();
+
+        let _ = worker.wait_for_startup().unwrap();
+        std::thread::sleep(std::time::Duration::from_secs(3));
+
+        // Neither pattern covers the other, so each gets its own NATS subscription
+        pgnats_subscribe(
+            format!("{prefix}.*.created"),
+            fn_name.to_string(),
+            true,
+            &LAUNCHER_MESSAGE_BUS9,
+        );
+        pgnats_subscribe(
+            format!("{prefix}.eu.>"),
+            fn_name.to_string(),
+            true,
+            &LAUNCHER_MESSAGE_BUS9,
+        );
+        std::thread::sleep(std::time::Duration::from_secs(3));
+
+        // Received by both NATS subscriptions, the callback runs once per pattern
+        let created = format!("{prefix}.eu.created");
+        api::nats_publish_text(&created, content.to_string(), None, None, false).unwrap();
+        std::thread::sleep(std::time::Duration::from_secs(3));
+
+        let mut expected = hash(&created).wrapping_mul(2);
+        assert_eq!(*TEST_RESULT9.share(), expected);
+
+        let created = format!("{prefix}.us.created");
+        let deleted = format!("{prefix}.eu.deleted");
+        api::nats_publish_text(&created, content.to_string(), None, None, false).unwrap();
+        api::nats_publish_text(&deleted, content.to_string(), None, None, false).unwrap();
+        api::nats_publish_text(
+            &format!("{prefix}.us.deleted"),
+            content.to_string(),
+            None,
+            None,
+            false,
+        )
+        .unwrap();
+        std::thread::sleep(std::time::Duration::from_secs(3));
+
+        expected = expected
+            .wrapping_add(hash(&created))
+            .wrapping_add(hash(&deleted));
+        assert_eq!(*TEST_RESULT9.share(), expected);
+
+        let terminate = worker.terminate();
+        terminate.wait_for_shutdown().unwrap();
+    }
+
+    #[pg_test]
+    fn test_background_worker_resubscribe_while_publishing() {
+        use pgrx::function_name;
+
+        let prefix = function_name!().split("::").last().unwrap();
+        let subject = format!("{prefix}.eu.created");
+
+        let worker = BackgroundWorkerBuilder::new("PGNats Background Worker Launcher 10")
+            .set_function("background_worker_launcher_entry_point_test_10")
+            .set_library(EXTENSION_NAME)
+            .enable_spi_access()
+            .set_notify_pid(unsafe { pgrx::pg_sys::MyProcPid })
+            .load_dynamic()
+            .unwrap();
+
+        let _ = worker.wait_for_startup().unwrap();
+        std::thread::sleep(std::time::Duration::from_secs(3));
+
+        pgnats_subscribe(
+            subject.clone(),
+            "test_10_seq_fn".to_string(),
+            false,
+            &LAUNCHER_MESSAGE_BUS10,
+        );
+        std::thread::sleep(std::time::Duration::from_secs(3));
+
+        // One message every 100 ms, numbered by the bits of the result
+        let publisher = {
+            let subject = subject.clone();
+
+            std::thread::spawn(move || {
+                let rt = tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                    .unwrap();
+
+                rt.block_on(async {
+                    let client = async_nats::connect("127.0.0.1:4222").await.unwrap();
+
+                    for seq in 0..u64::BITS {
+                        client
+                            .publish(subject.clone(), seq.to_string().into())
+                            .await
+                            .unwrap();
+                        client.flush().await.unwrap();
+                        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+                    }
+                });
+            })
+        };
+
+        // The covering pattern takes over the subscription of the subject and hands it
+        // back while messages keep coming
+        let covering = format!("{prefix}.>");
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        pgnats_subscribe(
+            covering.clone(),
+            "test_10_noop_fn".to_string(),
+            false,
+            &LAUNCHER_MESSAGE_BUS10,
+        );
+        std::thread::sleep(std::time::Duration::from_millis(2500));
+        pgnats_unsubscribe(
+            covering,
+            "test_10_noop_fn".to_string(),
+            &LAUNCHER_MESSAGE_BUS10,
+        );
+
+        publisher.join().unwrap();
+        std::thread::sleep(std::time::Duration::from_secs(3));
+
+        let received = *TEST_RESULT10.share();
+        assert_eq!(received, u64::MAX, "lost messages: {:064b}", !received);
+
+        let terminate = worker.terminate();
+        terminate.wait_for_shutdown().unwrap();
+    }
+
+    fn pgnats_kv_watch<const N: usize>(
+        bucket: String,
+        key_pattern: String,
+        fn_name: String,
+        queue: &PgLwLock<RingQueue<N>>,
+    ) {
+        crate::bgw::launcher::send_message_to_launcher_with_retry(
+            queue,
+            crate::bgw::launcher::message::LauncherMessage::KvWatch {
+                db_oid: unsafe { pgrx::pg_sys::MyDatabaseId }.to_u32(),
+                bucket,
+                key_pattern,
+                fn_name,
+            },
+            5,
+            std::time::Duration::from_secs(1),
+        )
+        .unwrap();
+    }
+
     fn pgnats_subscribe<const N: usize>(
         subject: String,
         fn_name: String,
+        with_subject: bool,
         queue: &PgLwLock<RingQueue<N>>,
     ) {
         crate::bgw::launcher::send_message_to_launcher_with_retry(
@@ -501,6 +1019,7 @@ pub(super) mod tests {
                 db_oid: unsafe { pgrx::pg_sys::MyDatabaseId }.to_u32(),
                 subject,
                 fn_name,
+                with_subject,
             },
             5,
             std::time::Duration::from_secs(1),
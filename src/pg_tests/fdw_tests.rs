@@ -0,0 +1,240 @@
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::prelude::pg_schema]
+mod tests {
+    use pgrx::{pg_test, Spi};
+
+    const NATS_HOST: &str = "127.0.0.1";
+    const NATS_PORT: u16 = 4222;
+
+    fn setup_foreign_server() {
+        // `pgnats_fdw` itself notifies the launcher on server creation,
+        // so tests use a wrapper sharing only its handler.
+        Spi::run(
+            "CREATE FOREIGN DATA WRAPPER test_pgnats_fdw HANDLER pgnats_fdw_handler;
+             CREATE SERVER test_pgnats_server FOREIGN DATA WRAPPER test_pgnats_fdw;",
+        )
+        .unwrap();
+    }
+
+    fn setup_stream(stream: &str, subject: &str, messages: &[&str]) {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+
+        rt.block_on(async {
+            let client = async_nats::connect(format!("{NATS_HOST}:{NATS_PORT}"))
+                .await
+                .expect("failed to connect to NATS server");
+            let js = async_nats::jetstream::new(client);
+
+            let _ = js.delete_stream(stream).await;
+            js.create_stream(async_nats::jetstream::stream::Config {
+                name: stream.to_string(),
+                subjects: vec![format!("{subject}.>")],
+                ..Default::default()
+            })
+            .await
+            .expect("failed to create stream");
+
+            for (i, message) in messages.iter().enumerate() {
+                js.publish(format!("{subject}.{i}"), message.to_string().into())
+                    .await
+                    .expect("failed to publish")
+                    .await
+                    .expect("failed to get ack");
+            }
+        });
+    }
+
+    #[pg_test]
+    fn test_pgnats_fdw_stream_scan() {
+        setup_foreign_server();
+        setup_stream(
+            "test_fdw_stream_scan",
+            "test.fdw_stream_scan",
+            &["first", "second", "third"],
+        );
+
+        Spi::run(
+            "CREATE FOREIGN TABLE test_fdw_stream (
+                seq bigint,
+                subject text,
+                time timestamptz,
+                headers jsonb,
+                payload bytea
+            ) SERVER test_pgnats_server OPTIONS (stream 'test_fdw_stream_scan');",
+        )
+        .unwrap();
+
+        let count = Spi::get_one::<i64>("SELECT count(*) FROM test_fdw_stream").unwrap();
+        assert_eq!(count, Some(3));
+
+        let payload = Spi::get_one::<String>(
+            "SELECT convert_from(payload, 'UTF8') FROM test_fdw_stream WHERE seq > 2",
+        )
+        .unwrap();
+        assert_eq!(payload.as_deref(), Some("third"));
+
+        let seq = Spi::get_one::<i64>(
+            "SELECT seq FROM test_fdw_stream WHERE subject = 'test.fdw_stream_scan.1'",
+        )
+        .unwrap();
+        assert_eq!(seq, Some(2));
+
+        let count =
+            Spi::get_one::<i64>("SELECT count(*) FROM test_fdw_stream WHERE seq > 1 AND seq < 3")
+                .unwrap();
+        assert_eq!(count, Some(1));
+    }
+
+    #[cfg(feature = "kv")]
+    #[pg_test]
+    fn test_pgnats_fdw_kv_modify() {
+        setup_foreign_server();
+
+        Spi::run(
+            "CREATE FOREIGN TABLE test_fdw_kv (
+                key text,
+                value jsonb,
+                revision bigint,
+                created timestamptz
+            ) SERVER test_pgnats_server OPTIONS (bucket 'test_fdw_kv_modify');
+
+            DELETE FROM test_fdw_kv;
+
+            INSERT INTO test_fdw_kv (key, value) VALUES
+                ('app.a', '{\"v\": 1}'),
+                ('app.b', '{\"v\": 2}'),
+                ('other', '{\"v\": 3}');",
+        )
+        .unwrap();
+
+        let count = Spi::get_one::<i64>("SELECT count(*) FROM test_fdw_kv").unwrap();
+        assert_eq!(count, Some(3));
+
+        let count =
+            Spi::get_one::<i64>("SELECT count(*) FROM test_fdw_kv WHERE key LIKE 'app.%'").unwrap();
+        assert_eq!(count, Some(2));
+
+        Spi::run("UPDATE test_fdw_kv SET value = '{\"v\": 10}' WHERE key = 'app.a'").unwrap();
+
+        let value =
+            Spi::get_one::<pgrx::JsonB>("SELECT value FROM test_fdw_kv WHERE key = 'app.a'")
+                .unwrap();
+        assert_eq!(value.map(|v| v.0), Some(serde_json::json!({"v": 10})));
+
+        Spi::run("DELETE FROM test_fdw_kv WHERE key = 'other'").unwrap();
+
+        let count = Spi::get_one::<i64>("SELECT count(*) FROM test_fdw_kv").unwrap();
+        assert_eq!(count, Some(2));
+    }
+
+    #[cfg(feature = "object_store")]
+    #[pg_test]
+    fn test_pgnats_fdw_object_store_modify() {
+        setup_foreign_server();
+
+        Spi::run(
+            "CREATE FOREIGN TABLE test_fdw_files (
+                name text,
+                size bigint,
+                digest text,
+                modified timestamptz,
+                metadata jsonb,
+                content bytea
+            ) SERVER test_pgnats_server OPTIONS (object_store 'test_fdw_object_store');
+
+            DELETE FROM test_fdw_files;
+
+            INSERT INTO test_fdw_files (name, metadata, content) VALUES
+                ('a.txt', NULL, 'first'::bytea),
+                ('b.txt', '{\"kind\": \"text\"}', 'second'::bytea);",
+        )
+        .unwrap();
+
+        let count = Spi::get_one::<i64>("SELECT count(*) FROM test_fdw_files").unwrap();
+        assert_eq!(count, Some(2));
+
+        let size =
+            Spi::get_one::<i64>("SELECT size FROM test_fdw_files WHERE name = 'b.txt'").unwrap();
+        assert_eq!(size, Some(6));
+
+        let content =
+            Spi::get_one::<Vec<u8>>("SELECT content FROM test_fdw_files WHERE name = 'a.txt'")
+                .unwrap();
+        assert_eq!(content.as_deref(), Some(b"first".as_slice()));
+
+        let metadata =
+            Spi::get_one::<pgrx::JsonB>("SELECT metadata FROM test_fdw_files WHERE name = 'b.txt'")
+                .unwrap();
+        assert_eq!(
+            metadata.map(|v| v.0),
+            Some(serde_json::json!({"kind": "text"}))
+        );
+
+        Spi::run(
+            "UPDATE test_fdw_files SET metadata = '{\"kind\": \"note\"}' WHERE name = 'b.txt'",
+        )
+        .unwrap();
+
+        let (metadata, content) = Spi::get_two::<pgrx::JsonB, Vec<u8>>(
+            "SELECT metadata, content FROM test_fdw_files WHERE name = 'b.txt'",
+        )
+        .unwrap();
+        assert_eq!(
+            metadata.map(|v| v.0),
+            Some(serde_json::json!({"kind": "note"}))
+        );
+        assert_eq!(content.as_deref(), Some(b"second".as_slice()));
+
+        Spi::run("UPDATE test_fdw_files SET content = 'third'::bytea WHERE name = 'b.txt'")
+            .unwrap();
+
+        let (metadata, size) = Spi::get_two::<pgrx::JsonB, i64>(
+            "SELECT metadata, size FROM test_fdw_files WHERE name = 'b.txt'",
+        )
+        .unwrap();
+        assert_eq!(
+            metadata.map(|v| v.0),
+            Some(serde_json::json!({"kind": "note"}))
+        );
+        assert_eq!(size, Some(5));
+
+        Spi::run("DELETE FROM test_fdw_files WHERE name = 'a.txt'").unwrap();
+
+        let count = Spi::get_one::<i64>("SELECT count(*) FROM test_fdw_files").unwrap();
+        assert_eq!(count, Some(1));
+    }
+
+    #[cfg(feature = "object_store")]
+    #[pg_test(error = "[PGNATS]: Column 'size' of an object store foreign table is read-only")]
+    fn test_pgnats_fdw_object_store_update_size() {
+        setup_foreign_server();
+
+        Spi::run(
+            "CREATE FOREIGN TABLE test_fdw_files (
+                name text,
+                size bigint,
+                content bytea
+            ) SERVER test_pgnats_server OPTIONS (object_store 'test_fdw_object_store_size');
+
+            INSERT INTO test_fdw_files (name, content) VALUES ('a.txt', 'first'::bytea);
+            UPDATE test_fdw_files SET size = 0 WHERE name = 'a.txt';",
+        )
+        .unwrap();
+    }
+
+    #[pg_test(
+        error = "[PGNATS]: Foreign table must specify one of the options: stream, bucket, object_store"
+    )]
+    fn test_pgnats_fdw_missing_stream_option() {
+        setup_foreign_server();
+
+        Spi::run(
+            "CREATE FOREIGN TABLE test_fdw_invalid (seq bigint) SERVER test_pgnats_server;
+             SELECT * FROM test_fdw_invalid;",
+        )
+        .unwrap();
+    }
+}
@@ -1,5 +1,7 @@
 mod api_tests;
 
+mod fdw_tests;
+
 #[cfg(feature = "sub")]
 pub(crate) mod bgw_tests;
 
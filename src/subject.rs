@@ -0,0 +1,163 @@
+//! NATS subject validation, wildcard matching and templates.
+
+/// Wildcard token matching exactly one token.
+pub const SINGLE_WILDCARD: &str = "*";
+
+/// Wildcard token matching one or more trailing tokens.
+pub const FULL_WILDCARD: &str = ">";
+
+/// Checks that `subject` is a valid NATS subject: dot-separated non-empty tokens without
+/// whitespace. With `allow_wildcards`, `*` tokens and a final `>` token are accepted as well.
+pub fn validate_subject(subject: &str, allow_wildcards: bool) -> anyhow::Result<()> {
+    if subject.is_empty() {
+        anyhow::bail!("Invalid subject: subject is empty");
+    }
+
+    if subject.chars().any(char::is_whitespace) {
+        anyhow::bail!("Invalid subject '{subject}': subjects cannot contain whitespace");
+    }
+
+    let mut tokens = subject.split('.').peekable();
+
+    while let Some(token) = tokens.next() {
+        match token {
+            "" => {
+                anyhow::bail!("Invalid subject '{subject}': subjects cannot contain empty tokens")
+            }
+            SINGLE_WILDCARD | FULL_WILDCARD if !allow_wildcards => {
+                anyhow::bail!("Invalid subject '{subject}': wildcards are not allowed here")
+            }
+            FULL_WILDCARD if tokens.peek().is_some() => {
+                anyhow::bail!("Invalid subject '{subject}': '>' must be the last token")
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns whether `subject` matches `pattern`, where a `*` token of the pattern matches
+/// any single token and a final `>` token matches one or more tokens.
+pub fn subject_matches(subject: &str, pattern: &str) -> bool {
+    let mut subject_tokens = subject.split('.');
+
+    for pattern_token in pattern.split('.') {
+        match (pattern_token, subject_tokens.next()) {
+            (FULL_WILDCARD, Some(_)) => return true,
+            (SINGLE_WILDCARD, Some(_)) => {}
+            (pattern_token, Some(subject_token)) if pattern_token == subject_token => {}
+            _ => return false,
+        }
+    }
+
+    subject_tokens.next().is_none()
+}
+
+/// Builds a subject from a template with `{name}` placeholders replaced by the values of
+/// `params`, a JSON object of strings, numbers or booleans.
+///
+/// Each value must form a single token, so that parameters cannot add tokens or wildcards
+/// to the subject. Wildcards written in the template itself are kept.
+pub fn format_subject(template: &str, params: &serde_json::Value) -> anyhow::Result<String> {
+    let mut subject = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        let (literal, placeholder) = rest.split_at(start);
+        check_no_closing_brace(template, literal)?;
+        subject.push_str(literal);
+
+        let end = placeholder.find('}').ok_or_else(|| {
+            anyhow::anyhow!("Invalid subject template '{template}': unclosed '{{'")
+        })?;
+        let name = placeholder.get(1..end).unwrap_or_default();
+
+        let value = match params.get(name) {
+            Some(serde_json::Value::String(value)) => value.clone(),
+            Some(value @ (serde_json::Value::Number(_) | serde_json::Value::Bool(_))) => {
+                value.to_string()
+            }
+            Some(_) => {
+                anyhow::bail!("Subject parameter '{name}' must be a string, a number or a boolean")
+            }
+            None => anyhow::bail!("Missing subject parameter '{name}'"),
+        };
+
+        if value.contains('.') || validate_subject(&value, false).is_err() {
+            anyhow::bail!("Subject parameter '{name}' is not a valid subject token: '{value}'");
+        }
+
+        subject.push_str(&value);
+        rest = placeholder.get(end + 1..).unwrap_or_default();
+    }
+
+    check_no_closing_brace(template, rest)?;
+    subject.push_str(rest);
+    validate_subject(&subject, true)?;
+
+    Ok(subject)
+}
+
+fn check_no_closing_brace(template: &str, literal: &str) -> anyhow::Result<()> {
+    if literal.contains('}') {
+        anyhow::bail!("Invalid subject template '{template}': unmatched '}}'");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_subject() {
+        assert!(validate_subject("orders.eu.created", false).is_ok());
+        assert!(validate_subject("orders.*.created", true).is_ok());
+        assert!(validate_subject("orders.>", true).is_ok());
+
+        assert!(validate_subject("", true).is_err());
+        assert!(validate_subject("orders eu", true).is_err());
+        assert!(validate_subject("orders..created", true).is_err());
+        assert!(validate_subject(".orders", true).is_err());
+        assert!(validate_subject("orders.", true).is_err());
+        assert!(validate_subject("orders.>.created", true).is_err());
+        assert!(validate_subject("orders.*", false).is_err());
+    }
+
+    #[test]
+    fn test_subject_matches() {
+        assert!(subject_matches("orders.eu.created", "orders.eu.created"));
+        assert!(subject_matches("orders.eu.created", "orders.*.created"));
+        assert!(subject_matches("orders.eu.created", "orders.>"));
+        assert!(subject_matches("orders.eu.created", ">"));
+
+        assert!(!subject_matches("orders", "orders.>"));
+        assert!(!subject_matches("orders.eu", "orders.*.created"));
+        assert!(!subject_matches("orders.eu.created.v2", "orders.*.created"));
+        assert!(!subject_matches("orders.eu.created", "orders.us.created"));
+    }
+
+    #[test]
+    fn test_format_subject() {
+        let params = serde_json::json!({ "region": "eu", "id": 42, "urgent": true });
+
+        assert_eq!(
+            format_subject("orders.{region}.{id}.{urgent}", &params).unwrap(),
+            "orders.eu.42.true"
+        );
+        assert_eq!(
+            format_subject("orders.{region}.>", &params).unwrap(),
+            "orders.eu.>"
+        );
+
+        assert!(format_subject("orders.{missing}", &params).is_err());
+        assert!(format_subject("orders.{region", &params).is_err());
+        assert!(format_subject("orders.region}", &params).is_err());
+        assert!(format_subject("orders}.{region}", &params).is_err());
+        assert!(format_subject("orders.{id}", &serde_json::json!({ "id": "a.b" })).is_err());
+        assert!(format_subject("orders.{id}", &serde_json::json!({ "id": "*" })).is_err());
+        assert!(format_subject("orders.{id}", &serde_json::json!({ "id": "" })).is_err());
+    }
+}
@@ -106,18 +106,100 @@ impl ToBytes for pgrx::JsonB {
     }
 }
 
-pub(crate) fn extract_headers(v: serde_json::Value) -> async_nats::HeaderMap {
+/// Converts a JSON object into NATS headers. Arrays are added as repeated values of the same
+/// header, and numbers and booleans are converted to their text representation.
+pub(crate) fn extract_headers(v: serde_json::Value) -> anyhow::Result<async_nats::HeaderMap> {
+    let serde_json::Value::Object(obj) = v else {
+        anyhow::bail!("Headers must be a JSON object");
+    };
+
     let mut map = async_nats::HeaderMap::new();
 
-    if let Some(obj) = v.as_object() {
-        for (k, v) in obj {
-            if let Some(v) = v.as_str() {
-                map.append(k.as_str(), v);
-            }
+    for (name, value) in obj {
+        anyhow::ensure!(
+            !name.is_empty() && name.chars().all(|c| c.is_ascii_graphic() && c != ':'),
+            "Invalid header name '{name}'"
+        );
+
+        let values = match value {
+            serde_json::Value::Array(values) => values,
+            value => vec![value],
+        };
+
+        anyhow::ensure!(!values.is_empty(), "Header '{name}' has no values");
+
+        for value in values {
+            let value = match value {
+                serde_json::Value::String(value) => value,
+                serde_json::Value::Number(_) | serde_json::Value::Bool(_) => value.to_string(),
+                _ => anyhow::bail!(
+                    "Value of header '{name}' must be a string, a number, a boolean or an array of them"
+                ),
+            };
+
+            anyhow::ensure!(
+                !value.contains(['\r', '\n']),
+                "Value of header '{name}' cannot contain line breaks"
+            );
+
+            map.append(name.as_str(), value.as_str());
         }
     }
 
-    map
+    Ok(map)
+}
+
+/// Converts a JSON object of strings into the metadata of an object store file.
+pub(crate) fn json_to_file_metadata(
+    v: serde_json::Value,
+) -> anyhow::Result<std::collections::HashMap<String, String>> {
+    serde_json::from_value(v)
+        .map_err(|err| anyhow::anyhow!("File metadata must be a JSON object of strings: {err}"))
+}
+
+/// Converts NATS headers into a JSON object, the reverse of [`extract_headers`]: a header
+/// with a single value becomes a string and a repeated header becomes an array.
+pub(crate) fn headers_to_json(headers: &async_nats::HeaderMap) -> serde_json::Value {
+    let map = headers
+        .iter()
+        .map(|(k, values)| {
+            let value = match values.as_slice() {
+                [value] => serde_json::Value::String(value.to_string()),
+                values => values.iter().map(|v| v.to_string()).collect(),
+            };
+
+            (k.to_string(), value)
+        })
+        .collect();
+
+    serde_json::Value::Object(map)
+}
+
+/// Microseconds between the Unix epoch and the Postgres epoch (2000-01-01).
+const POSTGRES_EPOCH_OFFSET_MICROS: i64 = 946_684_800_000_000;
+
+pub(crate) fn pg_timestamp_to_unix_nanos(timestamp: i64) -> i128 {
+    (i128::from(timestamp) + i128::from(POSTGRES_EPOCH_OFFSET_MICROS)) * 1000
+}
+
+pub(crate) fn unix_nanos_to_pg_timestamp(nanos: i128) -> i64 {
+    i64::try_from(nanos / 1000 - i128::from(POSTGRES_EPOCH_OFFSET_MICROS)).unwrap_or(i64::MAX)
+}
+
+/// Converts an interval to a duration, counting a month as 30 days like `EXTRACT(EPOCH ...)`.
+pub(crate) fn interval_to_duration(
+    interval: pgrx::datum::Interval,
+) -> anyhow::Result<std::time::Duration> {
+    const MICROS_PER_DAY: i128 = 86_400_000_000;
+
+    let micros = (i128::from(interval.months()) * 30 + i128::from(interval.days()))
+        * MICROS_PER_DAY
+        + i128::from(interval.micros());
+
+    let micros =
+        u64::try_from(micros).map_err(|_| anyhow::anyhow!("Interval must not be negative"))?;
+
+    Ok(std::time::Duration::from_micros(micros))
 }
 
 pub fn pack_oid_dsmh_to_i64(oid: sys::Oid, dsmh: DsmHandle) -> i64 {
@@ -149,6 +231,26 @@ pub fn get_database_name(oid: sys::Oid) -> Option<String> {
     Some(db_name.to_string_lossy().to_string())
 }
 
+/// Fails with SQLSTATE `42501` (`insufficient_privilege`) unless the current user has the
+/// privileges of `role`, the same check `COPY` performs for server files.
+pub(crate) fn check_server_files_access(role: &str) -> anyhow::Result<()> {
+    let granted = pgrx::Spi::get_one_with_args::<bool>(
+        "SELECT pg_has_role(current_user, $1::name, 'USAGE')",
+        &[role.into()],
+    )?
+    .unwrap_or(false);
+
+    if !granted {
+        pgrx::ereport!(
+            ERROR,
+            pgrx::PgSqlErrorCode::ERRCODE_INSUFFICIENT_PRIVILEGE,
+            format!("[PGNATS]: Only roles with privileges of the \"{role}\" role may access server files")
+        );
+    }
+
+    Ok(())
+}
+
 pub fn is_extension_installed(name: &str) -> bool {
     let query = "SELECT 1 FROM pg_extension WHERE extname = $1";
 
@@ -158,13 +260,29 @@ pub fn is_extension_installed(name: &str) -> bool {
     })
 }
 
-pub fn resolve_bytea_name(func_oid: sys::Oid) -> anyhow::Result<Option<String>> {
+/// Resolves the name of a subscription callback, which takes the message payload as `bytea`
+/// and optionally the message subject as `text`, together with whether it takes the subject.
+pub fn resolve_subscriber_name(func_oid: sys::Oid) -> anyhow::Result<Option<(String, bool)>> {
+    match resolve_function_name(func_oid, &[sys::BYTEAOID]) {
+        Ok(name) => Ok(name.map(|name| (name, false))),
+        Err(_) => resolve_function_name(func_oid, &[sys::BYTEAOID, sys::TEXTOID])
+            .map(|name| name.map(|name| (name, true)))
+            .map_err(|_| anyhow::anyhow!("Arguments must be (bytea) or (bytea, text)")),
+    }
+}
+
+pub fn resolve_function_name(
+    func_oid: sys::Oid,
+    arg_types: &[sys::Oid],
+) -> anyhow::Result<Option<String>> {
     // SAFETY:
     // 1. All Postgres FFI calls follow documented lifetimes.
     // 2. `SearchSysCache` result is wrapped in `SysHeapTuple` to ensure proper release.
     // 3. Returned C strings are checked for null before dereferencing.
     // 4. Argument metadata pointers returned by Postgres remain valid for the
     //    lifetime of the syscache tuple.
+    // 5. `p_argtypes` holds exactly `num_args` entries, which is checked against
+    //    `arg_types.len()` before the slice is built.
     unsafe {
         let schema_oid = sys::get_func_namespace(func_oid);
         let schema_name = sys::get_namespace_name(schema_oid);
@@ -197,9 +315,23 @@ pub fn resolve_bytea_name(func_oid: sys::Oid) -> anyhow::Result<Option<String>>
             &mut p_argmodes,
         );
 
-        anyhow::ensure!(num_args == 1, "Argument count must be 1");
+        anyhow::ensure!(
+            usize::try_from(num_args).is_ok_and(|n| n == arg_types.len()),
+            "Argument count must be {}",
+            arg_types.len()
+        );
         anyhow::ensure!(!p_argtypes.is_null(), "Postgres internal error");
-        anyhow::ensure!(*p_argtypes == sys::BYTEAOID, "Argument type must be bytea");
+
+        let actual = std::slice::from_raw_parts(p_argtypes, arg_types.len());
+        for (idx, (actual, expected)) in actual.iter().zip(arg_types).enumerate() {
+            if actual != expected {
+                let type_name = CStr::from_ptr(sys::format_type_be(*expected)).to_string_lossy();
+                if arg_types.len() == 1 {
+                    anyhow::bail!("Argument type must be {type_name}");
+                }
+                anyhow::bail!("Argument {} type must be {type_name}", idx + 1);
+            }
+        }
 
         let fn_name = CStr::from_ptr(fn_name).to_string_lossy().to_string();
 